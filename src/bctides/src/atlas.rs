@@ -0,0 +1,633 @@
+use super::bctides::{ElevationHarmonic, NodeValues, VelocityHarmonic};
+use derive_builder::Builder;
+use schismrs_hgrid::reproject::{to_hgrid_ll, ReprojectError};
+use schismrs_hgrid::Hgrid;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// ESRI ASCII grid. Rows are stored as in the file, from north to south.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsciiGrid {
+    ncols: usize,
+    nrows: usize,
+    // Center of the lower left cell.
+    origin: [f64; 2],
+    cellsize: f64,
+    values: Vec<f64>,
+}
+
+impl AsciiGrid {
+    pub fn from_path(path: &Path) -> Result<Self, AtlasError> {
+        let fname = path.display().to_string();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| AtlasError::IoError(fname.clone(), e.to_string()))?;
+        Self::parse(&contents).map_err(|e| AtlasError::ParseError(fname, e))
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut header: BTreeMap<String, f64> = BTreeMap::new();
+        let mut tokens = contents.split_whitespace().peekable();
+        while let Some(token) = tokens.peek() {
+            if token.parse::<f64>().is_ok() {
+                break;
+            }
+            let key = tokens.next().unwrap().to_lowercase();
+            let value = tokens
+                .next()
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| format!("Expected a number after {}", key))?;
+            header.insert(key, value);
+        }
+        let get = |key: &str| {
+            header
+                .get(key)
+                .copied()
+                .ok_or_else(|| format!("Missing {} in header", key))
+        };
+        let ncols = get("ncols")? as usize;
+        let nrows = get("nrows")? as usize;
+        let cellsize = get("cellsize")?;
+        if ncols == 0 || nrows == 0 || cellsize <= 0. {
+            return Err("ncols, nrows and cellsize must be > 0".to_string());
+        }
+        let origin = match (header.get("xllcenter"), header.get("yllcenter")) {
+            (Some(x), Some(y)) => [*x, *y],
+            _ => [
+                get("xllcorner")? + cellsize / 2.,
+                get("yllcorner")? + cellsize / 2.,
+            ],
+        };
+        let nodata = header.get("nodata_value").copied();
+        let mut values = Vec::with_capacity(ncols * nrows);
+        for token in tokens {
+            let value = token
+                .parse::<f64>()
+                .map_err(|_| format!("Expected a number but found {}", token))?;
+            values.push(match nodata {
+                Some(nodata) if value == nodata => f64::NAN,
+                _ => value,
+            });
+        }
+        if values.len() != ncols * nrows {
+            return Err(format!(
+                "Expected {} values but found {}",
+                ncols * nrows,
+                values.len()
+            ));
+        }
+        Ok(Self {
+            ncols,
+            nrows,
+            origin,
+            cellsize,
+            values,
+        })
+    }
+
+    pub fn shape(&self) -> [usize; 2] {
+        [self.nrows, self.ncols]
+    }
+
+    // Value of the cell in column i and row j counted from the south, None when masked.
+    pub fn value(&self, i: usize, j: usize) -> Option<f64> {
+        let value = self.values[(self.nrows - 1 - j) * self.ncols + i];
+        value.is_finite().then_some(value)
+    }
+
+    fn same_layout(&self, other: &Self) -> bool {
+        self.ncols == other.ncols
+            && self.nrows == other.nrows
+            && self.origin == other.origin
+            && self.cellsize == other.cellsize
+    }
+
+    // Global longitude grids, possibly with a repeated column, wrap around in x.
+    fn is_global(&self) -> bool {
+        let extent = self.ncols as f64 * self.cellsize;
+        extent >= 360. - 1e-6 && extent <= 360. + self.cellsize + 1e-6
+    }
+}
+
+// Amplitude and phase (degrees) grids held as complex values, so that interpolation does not
+// break down across phase wraps and amphidromes.
+#[derive(Debug, Clone)]
+pub struct HarmonicGrid {
+    layout: AsciiGrid,
+    real: Vec<f64>,
+    imaginary: Vec<f64>,
+}
+
+impl HarmonicGrid {
+    pub fn new(amplitude: &AsciiGrid, phase: &AsciiGrid) -> Result<Self, AtlasError> {
+        if !amplitude.same_layout(phase) {
+            return Err(AtlasError::LayoutMismatch);
+        }
+        let (real, imaginary) = amplitude
+            .values
+            .iter()
+            .zip(phase.values.iter())
+            .map(|(a, p)| {
+                let p = p.to_radians();
+                (a * p.cos(), a * p.sin())
+            })
+            .unzip();
+        Ok(Self {
+            layout: amplitude.clone(),
+            real,
+            imaginary,
+        })
+    }
+
+    pub fn from_paths(amplitude: &Path, phase: &Path) -> Result<Self, AtlasError> {
+        Self::new(
+            &AsciiGrid::from_path(amplitude)?,
+            &AsciiGrid::from_path(phase)?,
+        )
+    }
+
+    fn cell(&self, i: usize, j: usize) -> Option<[f64; 2]> {
+        let index = (self.layout.nrows - 1 - j) * self.layout.ncols + i;
+        let (re, im) = (self.real[index], self.imaginary[index]);
+        (re.is_finite() && im.is_finite()).then_some([re, im])
+    }
+
+    // Bilinear interpolation over the wet cells around the point, or the nearest wet cell within
+    // max_search_cells when all of them are masked. Returns amplitude and phase in degrees.
+    pub fn interpolate(&self, point: [f64; 2], max_search_cells: usize) -> Option<[f64; 2]> {
+        let layout = &self.layout;
+        // Longitudes are moved by whole turns to within half a turn of the middle of the grid,
+        // so that hgrids in [-180, 180) sample atlases in [0, 360) and the other way around.
+        let middle = layout.origin[0] + (layout.ncols - 1) as f64 * layout.cellsize / 2.;
+        let x = middle + (point[0] - middle + 180.).rem_euclid(360.) - 180.;
+        let fi = (x - layout.origin[0]) / layout.cellsize;
+        let fj = (point[1] - layout.origin[1]) / layout.cellsize;
+        let column = |i: i64| -> Option<usize> {
+            if layout.is_global() {
+                Some(i.rem_euclid(layout.ncols as i64) as usize)
+            } else {
+                (0..layout.ncols as i64).contains(&i).then_some(i as usize)
+            }
+        };
+        let row = |j: i64| (0..layout.nrows as i64).contains(&j).then_some(j as usize);
+        let (i0, j0) = (fi.floor() as i64, fj.floor() as i64);
+        let (wi, wj) = (fi - i0 as f64, fj - j0 as f64);
+        let mut sum = [0.; 2];
+        let mut total = 0.;
+        for (di, dj, weight) in [
+            (0, 0, (1. - wi) * (1. - wj)),
+            (1, 0, wi * (1. - wj)),
+            (0, 1, (1. - wi) * wj),
+            (1, 1, wi * wj),
+        ] {
+            let value = column(i0 + di)
+                .zip(row(j0 + dj))
+                .and_then(|(i, j)| self.cell(i, j));
+            if let Some([re, im]) = value {
+                sum[0] += weight * re;
+                sum[1] += weight * im;
+                total += weight;
+            }
+        }
+        let value = if total > 1e-12 {
+            Some([sum[0] / total, sum[1] / total])
+        } else {
+            self.nearest(fi, fj, max_search_cells, &column, &row)
+        };
+        value.map(|[re, im]| [re.hypot(im), (im.atan2(re).to_degrees() + 360.) % 360.])
+    }
+
+    fn nearest(
+        &self,
+        fi: f64,
+        fj: f64,
+        max_search_cells: usize,
+        column: &dyn Fn(i64) -> Option<usize>,
+        row: &dyn Fn(i64) -> Option<usize>,
+    ) -> Option<[f64; 2]> {
+        let (ci, cj) = (fi.round() as i64, fj.round() as i64);
+        let radius = max_search_cells as i64;
+        let mut best: Option<(f64, [f64; 2])> = None;
+        for dj in -radius..=radius {
+            for di in -radius..=radius {
+                let value = column(ci + di)
+                    .zip(row(cj + dj))
+                    .and_then(|(i, j)| self.cell(i, j));
+                if let Some(value) = value {
+                    let distance = (fi - (ci + di) as f64).hypot(fj - (cj + dj) as f64);
+                    if best.is_none_or(|(d, _)| distance < d) {
+                        best = Some((distance, value));
+                    }
+                }
+            }
+        }
+        best.map(|(_, value)| value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstituentAtlas {
+    name: String,
+    elevation: HarmonicGrid,
+    // Eastward and northward velocities in m/s.
+    velocity: Option<[HarmonicGrid; 2]>,
+}
+
+impl ConstituentAtlas {
+    pub fn new(name: &str, elevation: HarmonicGrid, velocity: Option<[HarmonicGrid; 2]>) -> Self {
+        Self {
+            name: name.to_string(),
+            elevation,
+            velocity,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+}
+
+// Per-constituent amplitude and phase grids in a directory, named <constituent>_amp.asc and
+// <constituent>_phase.asc for elevation and <constituent>_u_amp.asc, <constituent>_u_phase.asc,
+// <constituent>_v_amp.asc and <constituent>_v_phase.asc for velocity, with lowercase names.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct AtlasFiles {
+    #[builder(setter(into))]
+    directory: PathBuf,
+    constituents: Vec<String>,
+    #[builder(default = "true")]
+    velocity: bool,
+    // How far (in cells) to look for wet cells when a node falls on masked land cells.
+    #[builder(default = "10")]
+    max_search_cells: usize,
+}
+
+impl AtlasFilesBuilder {
+    pub fn validate(&self) -> Result<(), AtlasFilesBuilderError> {
+        if let Some(constituents) = &self.constituents {
+            if constituents.is_empty() {
+                return Err(AtlasFilesBuilderError::ValidationError(
+                    "At least one constituent is required".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AtlasFiles {
+    pub fn load(&self) -> Result<TidalAtlas, AtlasError> {
+        let path = |name: &str, suffix: &str| {
+            self.directory
+                .join(format!("{}_{}.asc", name.to_lowercase(), suffix))
+        };
+        let constituents = self
+            .constituents
+            .iter()
+            .map(|name| {
+                let elevation = HarmonicGrid::from_paths(&path(name, "amp"), &path(name, "phase"))?;
+                let velocity = if self.velocity {
+                    Some([
+                        HarmonicGrid::from_paths(&path(name, "u_amp"), &path(name, "u_phase"))?,
+                        HarmonicGrid::from_paths(&path(name, "v_amp"), &path(name, "v_phase"))?,
+                    ])
+                } else {
+                    None
+                };
+                Ok(ConstituentAtlas::new(name, elevation, velocity))
+            })
+            .collect::<Result<Vec<_>, AtlasError>>()?;
+        Ok(TidalAtlas {
+            constituents,
+            max_search_cells: self.max_search_cells,
+        })
+    }
+}
+
+// Node ids and coordinates of every open boundary segment.
+type BoundaryPoints = Vec<Vec<(u32, [f64; 2])>>;
+
+#[derive(Debug, Clone)]
+pub struct TidalAtlas {
+    constituents: Vec<ConstituentAtlas>,
+    max_search_cells: usize,
+}
+
+impl TidalAtlas {
+    pub fn new(constituents: Vec<ConstituentAtlas>, max_search_cells: usize) -> Self {
+        Self {
+            constituents,
+            max_search_cells,
+        }
+    }
+
+    pub fn constituent_names(&self) -> Vec<&str> {
+        self.constituents
+            .iter()
+            .map(|constituent| constituent.name.as_str())
+            .collect()
+    }
+
+    // Longitude and latitude of the nodes of every open boundary segment. Hgrids without a CRS
+    // are taken to be geographic already.
+    fn boundary_points(hgrid: &Hgrid) -> Result<BoundaryPoints, AtlasError> {
+        let ll;
+        let hgrid = match hgrid.crs() {
+            Some(_) if !hgrid.is_geographic() => {
+                ll = to_hgrid_ll(hgrid)?;
+                &ll
+            }
+            _ => hgrid,
+        };
+        let nodes = hgrid.nodes().btree_map();
+        Ok(hgrid
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|node_ids| {
+                node_ids
+                    .into_iter()
+                    .map(|node_id| {
+                        let (coord, _) = &nodes[&node_id];
+                        (node_id, [coord[0], coord[1]])
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn sample(
+        &self,
+        grid: &HarmonicGrid,
+        name: &str,
+        node_id: u32,
+        point: [f64; 2],
+    ) -> Result<[f64; 2], AtlasError> {
+        grid.interpolate(point, self.max_search_cells)
+            .ok_or_else(|| AtlasError::NoWetCells(name.to_string(), node_id))
+    }
+
+    // Elevation harmonics for every open boundary segment and constituent, in the order that
+    // ElevationBoundary::Tidal expects.
+    pub fn boundary_elevation(
+        &self,
+        hgrid: &Hgrid,
+    ) -> Result<Vec<Vec<NodeValues<ElevationHarmonic>>>, AtlasError> {
+        Self::boundary_points(hgrid)?
+            .iter()
+            .map(|points| {
+                self.constituents
+                    .iter()
+                    .map(|constituent| {
+                        points
+                            .iter()
+                            .map(|(node_id, point)| {
+                                self.sample(
+                                    &constituent.elevation,
+                                    &constituent.name,
+                                    *node_id,
+                                    *point,
+                                )
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                            .map(NodeValues::PerNode)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn boundary_velocity(
+        &self,
+        hgrid: &Hgrid,
+    ) -> Result<Vec<Vec<NodeValues<VelocityHarmonic>>>, AtlasError> {
+        Self::boundary_points(hgrid)?
+            .iter()
+            .map(|points| {
+                self.constituents
+                    .iter()
+                    .map(|constituent| {
+                        let [u, v] = constituent
+                            .velocity
+                            .as_ref()
+                            .ok_or_else(|| AtlasError::MissingVelocity(constituent.name.clone()))?;
+                        points
+                            .iter()
+                            .map(|(node_id, point)| {
+                                let [u_amplitude, u_phase] =
+                                    self.sample(u, &constituent.name, *node_id, *point)?;
+                                let [v_amplitude, v_phase] =
+                                    self.sample(v, &constituent.name, *node_id, *point)?;
+                                Ok([u_amplitude, u_phase, v_amplitude, v_phase])
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                            .map(NodeValues::PerNode)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AtlasError {
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Error parsing {0}: {1}")]
+    ParseError(String, String),
+    #[error("Amplitude and phase grids do not share the same layout")]
+    LayoutMismatch,
+    #[error("No wet {0} atlas cells found near node {1}")]
+    NoWetCells(String, u32),
+    #[error("The atlas has no velocity grids for {0}")]
+    MissingVelocity(String),
+    #[error(transparent)]
+    ReprojectError(#[from] ReprojectError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schismrs_hgrid::crs::Crs;
+    use schismrs_hgrid::reproject::reproject;
+    use schismrs_hgrid::synthetic::RectangularChannelBuilder;
+    use std::sync::Arc;
+
+    // Grid of cellsize 1 with the given cell centers, from a function of longitude and latitude
+    // returning the complex value or None for masked cells.
+    fn harmonic_grid(
+        ncols: usize,
+        nrows: usize,
+        origin: [f64; 2],
+        value: impl Fn(f64, f64) -> Option<[f64; 2]>,
+    ) -> HarmonicGrid {
+        let mut real = Vec::new();
+        let mut imaginary = Vec::new();
+        for row in 0..nrows {
+            let y = origin[1] + (nrows - 1 - row) as f64;
+            for i in 0..ncols {
+                let [re, im] = value(origin[0] + i as f64, y).unwrap_or([f64::NAN; 2]);
+                real.push(re);
+                imaginary.push(im);
+            }
+        }
+        let values = real
+            .iter()
+            .zip(imaginary.iter())
+            .map(|(re, im)| re.hypot(*im))
+            .collect();
+        HarmonicGrid {
+            layout: AsciiGrid {
+                ncols,
+                nrows,
+                origin,
+                cellsize: 1.,
+                values,
+            },
+            real,
+            imaginary,
+        }
+    }
+
+    fn assert_harmonic(value: [f64; 2], expected: [f64; 2]) {
+        let phase_difference = (value[1] - expected[1] + 540.) % 360. - 180.;
+        assert!(
+            (value[0] - expected[0]).abs() < 1e-9 && phase_difference.abs() < 1e-6,
+            "{:?} differs from {:?}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ascii_grid() {
+        let grid = AsciiGrid::parse(
+            "ncols 3\nnrows 2\nxllcorner 0\nyllcorner 10\ncellsize 0.5\nNODATA_value -9999\n\
+             1 2 3\n4 -9999 6\n",
+        )
+        .unwrap();
+        assert_eq!(grid.shape(), [2, 3]);
+        assert_eq!(grid.origin, [0.25, 10.25]);
+        assert_eq!(grid.value(0, 1), Some(1.));
+        assert_eq!(grid.value(1, 0), None);
+        assert!(
+            AsciiGrid::parse("ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_complex_interpolation() {
+        // Equal amplitudes at phases 350 and 10 degrees average to phase 0, not 180.
+        let grid = harmonic_grid(2, 1, [0., 0.], |x, _| {
+            let phase: f64 = if x == 0. { -10. } else { 10. };
+            Some([phase.to_radians().cos(), phase.to_radians().sin()])
+        });
+        assert_harmonic(
+            grid.interpolate([0.5, 0.], 0).unwrap(),
+            [10f64.to_radians().cos(), 0.],
+        );
+        // Bilinear interpolation reproduces fields linear in the real and imaginary parts.
+        let linear = |x: f64, y: f64| [1. + 0.1 * x, 0.2 * y - 0.5];
+        let grid = harmonic_grid(4, 3, [0., 0.], |x, y| Some(linear(x, y)));
+        let [re, im] = linear(1.3, 0.6);
+        assert_harmonic(
+            grid.interpolate([1.3, 0.6], 0).unwrap(),
+            [re.hypot(im), (im.atan2(re).to_degrees() + 360.) % 360.],
+        );
+    }
+
+    #[test]
+    fn test_masked_cells() {
+        // Only the cells with x >= 2 are wet.
+        let grid = harmonic_grid(5, 3, [0., 0.], |x, _| (x >= 2.).then_some([x, 0.]));
+        // Masked corners are left out of the bilinear weights.
+        assert_harmonic(grid.interpolate([1.5, 1.], 0).unwrap(), [2., 0.]);
+        assert_harmonic(grid.interpolate([2.5, 1.], 0).unwrap(), [2.5, 0.]);
+        // All four corners masked: the nearest wet cell within the search radius.
+        assert_eq!(grid.interpolate([0.2, 1.], 1), None);
+        assert_harmonic(grid.interpolate([0.2, 1.], 2).unwrap(), [2., 0.]);
+        // Outside of the grid the nearest wet cell is used as well.
+        assert_harmonic(grid.interpolate([7., 1.], 3).unwrap(), [4., 0.]);
+        assert_eq!(grid.interpolate([-3., 1.], 3), None);
+    }
+
+    #[test]
+    fn test_longitude_ranges() {
+        let linear = |x: f64, y: f64| Some([1. + 0.01 * x, 0.05 * y]);
+        // A regional atlas in [0, 360) sampled at western longitudes, and one in [-180, 180)
+        // sampled at eastern ones.
+        let regional = harmonic_grid(21, 11, [270., 30.], linear);
+        assert_eq!(
+            regional.interpolate([-75.5, 35.2], 0),
+            regional.interpolate([284.5, 35.2], 0)
+        );
+        assert!(regional.interpolate([-75.5, 35.2], 0).is_some());
+        let western = harmonic_grid(21, 11, [-90., 30.], linear);
+        assert_eq!(
+            western.interpolate([284.5, 35.2], 0),
+            western.interpolate([-75.5, 35.2], 0)
+        );
+        // A global atlas wraps around between its last and first columns: -0.25 is 359.75,
+        // between the cells at 359.5 and 0.5.
+        let global = harmonic_grid(360, 3, [0.5, 0.], |x, _| Some([x, 0.]));
+        assert_harmonic(
+            global.interpolate([-0.25, 1.], 0).unwrap(),
+            [0.75 * 359.5 + 0.25 * 0.5, 0.],
+        );
+    }
+
+    #[test]
+    fn test_boundary_harmonics() {
+        let linear = |x: f64, y: f64| Some([1. + 0.01 * x, 0.05 * y]);
+        let atlas = TidalAtlas::new(
+            vec![ConstituentAtlas::new(
+                "M2",
+                harmonic_grid(21, 11, [270., 30.], linear),
+                Some([
+                    harmonic_grid(21, 11, [270., 30.], linear),
+                    harmonic_grid(21, 11, [270., 30.], linear),
+                ]),
+            )],
+            2,
+        );
+        let geographic = RectangularChannelBuilder::default()
+            .length(1.)
+            .width(0.5)
+            .origin([-75., 35.])
+            .crs(Crs::new("EPSG:4326").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        // The field is linear, so every node gets the value at its longitude moved to [0, 360).
+        let elevation = atlas.boundary_elevation(&geographic).unwrap();
+        let nodes = geographic.nodes().btree_map();
+        let open = geographic.boundaries().unwrap().open().unwrap().nodes_ids();
+        assert_eq!(elevation.len(), open.len());
+        for (harmonics, node_ids) in elevation.iter().zip(open.iter()) {
+            for (harmonic, node_id) in harmonics[0].values(6).iter().zip(node_ids) {
+                let (coord, _) = &nodes[node_id];
+                let [re, im] = linear(coord[0] + 360., coord[1]).unwrap();
+                assert_harmonic(*harmonic, [re.hypot(im), im.atan2(re).to_degrees()]);
+            }
+        }
+        // The same boundaries in web mercator are sampled at the same longitudes and latitudes.
+        let projected = reproject(&geographic, "EPSG:3857").unwrap();
+        let projected_elevation = atlas.boundary_elevation(&projected).unwrap();
+        for (a, b) in elevation.iter().zip(projected_elevation.iter()) {
+            for (a, b) in a[0].values(6).iter().zip(b[0].values(6).iter()) {
+                assert_harmonic(*b, *a);
+            }
+        }
+        let velocity = atlas.boundary_velocity(&geographic).unwrap();
+        assert_eq!(
+            velocity[1][0].values(6)[0][..2],
+            elevation[1][0].values(6)[0]
+        );
+    }
+}
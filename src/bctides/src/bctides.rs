@@ -0,0 +1,580 @@
+use chrono::NaiveDateTime;
+use derive_builder::Builder;
+use schismrs_hgrid::th::ThKind;
+use schismrs_hgrid::Hgrid;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TidalPotential {
+    pub name: String,
+    // 0 for declinational (long period), 1 for diurnal and 2 for semi-diurnal constituents.
+    pub species: u8,
+    pub amplitude: f64,
+    // Angular frequency in rad/s.
+    pub frequency: f64,
+    pub nodal_factor: f64,
+    // Earth equilibrium argument in degrees.
+    pub equilibrium_argument: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TidalForcing {
+    pub name: String,
+    pub frequency: f64,
+    pub nodal_factor: f64,
+    pub equilibrium_argument: f64,
+}
+
+// Values given once for a whole segment or once per node of the segment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeValues<T> {
+    Constant(T),
+    PerNode(Vec<T>),
+}
+
+impl<T: Clone> NodeValues<T> {
+    pub fn values(&self, node_count: usize) -> Vec<T> {
+        match self {
+            NodeValues::Constant(value) => vec![value.clone(); node_count],
+            NodeValues::PerNode(values) => values.clone(),
+        }
+    }
+
+    fn check(&self, node_count: usize) -> Result<(), usize> {
+        match self {
+            NodeValues::PerNode(values) if values.len() != node_count => Err(values.len()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Amplitude (m) and phase (degrees) of the elevation.
+pub type ElevationHarmonic = [f64; 2];
+// Amplitude (m/s) and phase (degrees) of the u velocity, followed by those of the v velocity.
+pub type VelocityHarmonic = [f64; 4];
+
+// Tidal variants hold one set of harmonics per tidal forcing constituent, in the same order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ElevationBoundary {
+    #[default]
+    None,
+    // elev.th
+    TimeHistory,
+    Constant(f64),
+    Tidal(Vec<NodeValues<ElevationHarmonic>>),
+    // elev2D.th.nc
+    SpaceTime,
+    TidalSpaceTime(Vec<NodeValues<ElevationHarmonic>>),
+}
+
+impl ElevationBoundary {
+    pub fn flag(&self) -> i32 {
+        match self {
+            ElevationBoundary::None => 0,
+            ElevationBoundary::TimeHistory => 1,
+            ElevationBoundary::Constant(_) => 2,
+            ElevationBoundary::Tidal(_) => 3,
+            ElevationBoundary::SpaceTime => 4,
+            ElevationBoundary::TidalSpaceTime(_) => 5,
+        }
+    }
+
+    fn harmonics(&self) -> Option<&Vec<NodeValues<ElevationHarmonic>>> {
+        match self {
+            ElevationBoundary::Tidal(harmonics) | ElevationBoundary::TidalSpaceTime(harmonics) => {
+                Some(harmonics)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum VelocityBoundary {
+    #[default]
+    None,
+    // flux.th
+    TimeHistory,
+    // Discharge in m3/s, negative for inflow.
+    Constant(f64),
+    Tidal(Vec<NodeValues<VelocityHarmonic>>),
+    // uv3D.th.nc
+    SpaceTime,
+    TidalSpaceTime(Vec<NodeValues<VelocityHarmonic>>),
+    // uv3D.th.nc with relaxation constants for inflow and outflow.
+    RelaxedSpaceTime {
+        inflow: f64,
+        outflow: f64,
+    },
+}
+
+impl VelocityBoundary {
+    pub fn flag(&self) -> i32 {
+        match self {
+            VelocityBoundary::None => 0,
+            VelocityBoundary::TimeHistory => 1,
+            VelocityBoundary::Constant(_) => 2,
+            VelocityBoundary::Tidal(_) => 3,
+            VelocityBoundary::SpaceTime => 4,
+            VelocityBoundary::TidalSpaceTime(_) => 5,
+            VelocityBoundary::RelaxedSpaceTime { .. } => -4,
+        }
+    }
+
+    fn harmonics(&self) -> Option<&Vec<NodeValues<VelocityHarmonic>>> {
+        match self {
+            VelocityBoundary::Tidal(harmonics) | VelocityBoundary::TidalSpaceTime(harmonics) => {
+                Some(harmonics)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Temperature and salinity boundaries; nudging is the relaxation factor between 0 and 1.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TracerBoundary {
+    #[default]
+    None,
+    // TEM_1.th or SAL_1.th
+    TimeHistory {
+        nudging: f64,
+    },
+    Constant {
+        value: f64,
+        nudging: f64,
+    },
+    // Initial profile for inflow.
+    Initial {
+        nudging: f64,
+    },
+    // TEM_3D.th.nc or SAL_3D.th.nc
+    SpaceTime {
+        nudging: f64,
+    },
+}
+
+impl TracerBoundary {
+    pub fn flag(&self) -> i32 {
+        match self {
+            TracerBoundary::None => 0,
+            TracerBoundary::TimeHistory { .. } => 1,
+            TracerBoundary::Constant { .. } => 2,
+            TracerBoundary::Initial { .. } => 3,
+            TracerBoundary::SpaceTime { .. } => 4,
+        }
+    }
+
+    pub fn nudging(&self) -> Option<f64> {
+        match self {
+            TracerBoundary::None => None,
+            TracerBoundary::TimeHistory { nudging }
+            | TracerBoundary::Constant { nudging, .. }
+            | TracerBoundary::Initial { nudging }
+            | TracerBoundary::SpaceTime { nudging } => Some(*nudging),
+        }
+    }
+}
+
+#[derive(Builder, Debug, Clone, Default, PartialEq)]
+pub struct BoundaryForcing {
+    #[builder(default)]
+    elevation: ElevationBoundary,
+    #[builder(default)]
+    velocity: VelocityBoundary,
+    #[builder(default)]
+    temperature: TracerBoundary,
+    #[builder(default)]
+    salinity: TracerBoundary,
+}
+
+impl BoundaryForcing {
+    pub fn elevation(&self) -> &ElevationBoundary {
+        &self.elevation
+    }
+
+    pub fn velocity(&self) -> &VelocityBoundary {
+        &self.velocity
+    }
+
+    pub fn temperature(&self) -> &TracerBoundary {
+        &self.temperature
+    }
+
+    pub fn salinity(&self) -> &TracerBoundary {
+        &self.salinity
+    }
+
+    pub fn flags(&self) -> [i32; 4] {
+        [
+            self.elevation.flag(),
+            self.velocity.flag(),
+            self.temperature.flag(),
+            self.salinity.flag(),
+        ]
+    }
+}
+
+// Boundary forcing for every open boundary segment of an hgrid, in segment order.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct Bctides {
+    #[builder(setter(strip_option), default)]
+    start_date: Option<NaiveDateTime>,
+    #[builder(default)]
+    tidal_potential: Vec<TidalPotential>,
+    // Tidal potential is not applied in waters shallower than the cutoff depth.
+    #[builder(default = "40.")]
+    cutoff_depth: f64,
+    #[builder(default)]
+    tidal_forcing: Vec<TidalForcing>,
+    boundaries: Vec<BoundaryForcing>,
+}
+
+impl BctidesBuilder {
+    pub fn validate(&self) -> Result<(), BctidesBuilderError> {
+        if let Some(cutoff_depth) = self.cutoff_depth {
+            if cutoff_depth < 0. {
+                return Err(BctidesBuilderError::ValidationError(format!(
+                    "cutoff_depth must be >= 0. but got {}",
+                    cutoff_depth
+                )));
+            }
+        }
+        let constituents = self.tidal_forcing.as_ref().map_or(0, Vec::len);
+        for (index, boundary) in self.boundaries.iter().flatten().enumerate() {
+            let counts = [
+                boundary.elevation.harmonics().map(Vec::len),
+                boundary.velocity.harmonics().map(Vec::len),
+            ];
+            if let Some(count) = counts.into_iter().flatten().find(|&c| c != constituents) {
+                return Err(BctidesBuilderError::ValidationError(format!(
+                    "Open boundary {} has harmonics for {} constituents but there are {} tidal forcing constituents",
+                    index + 1,
+                    count,
+                    constituents
+                )));
+            }
+            for nudging in [boundary.temperature.nudging(), boundary.salinity.nudging()]
+                .into_iter()
+                .flatten()
+            {
+                if !(0. ..=1.).contains(&nudging) {
+                    return Err(BctidesBuilderError::ValidationError(format!(
+                        "Open boundary {} has a nudging factor of {} outside of [0, 1]",
+                        index + 1,
+                        nudging
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Bctides {
+    pub fn boundaries(&self) -> &Vec<BoundaryForcing> {
+        &self.boundaries
+    }
+
+    pub fn tidal_potential(&self) -> &Vec<TidalPotential> {
+        &self.tidal_potential
+    }
+
+    pub fn tidal_forcing(&self) -> &Vec<TidalForcing> {
+        &self.tidal_forcing
+    }
+
+    // Open boundaries whose flag for the given .th file is 1, in segment order, for
+    // TimeHistory::validate_with_mask.
+    pub fn th_boundaries(&self, kind: ThKind) -> Vec<bool> {
+        self.boundaries
+            .iter()
+            .map(|boundary| {
+                let flag = match kind {
+                    ThKind::Elevation => boundary.elevation.flag(),
+                    ThKind::Flux => boundary.velocity.flag(),
+                    ThKind::Temperature => boundary.temperature.flag(),
+                    ThKind::Salinity => boundary.salinity.flag(),
+                };
+                flag == 1
+            })
+            .collect()
+    }
+
+    // Checks the boundaries against the open boundary segments of the hgrid and returns the
+    // node count of every segment.
+    pub fn check(&self, hgrid: &Hgrid) -> Result<Vec<usize>, BctidesError> {
+        let node_counts: Vec<usize> = hgrid
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids().iter().map(Vec::len).collect())
+            .unwrap_or_default();
+        if node_counts.len() != self.boundaries.len() {
+            return Err(BctidesError::BoundaryCountMismatch(
+                node_counts.len(),
+                self.boundaries.len(),
+            ));
+        }
+        for (index, (boundary, &node_count)) in
+            self.boundaries.iter().zip(node_counts.iter()).enumerate()
+        {
+            let elevation = boundary.elevation.harmonics().into_iter().flatten();
+            let velocity = boundary.velocity.harmonics().into_iter().flatten();
+            let checks = elevation
+                .map(|values| values.check(node_count))
+                .chain(velocity.map(|values| values.check(node_count)));
+            for check in checks {
+                if let Err(count) = check {
+                    return Err(BctidesError::NodeCountMismatch(
+                        index + 1,
+                        node_count,
+                        count,
+                    ));
+                }
+            }
+        }
+        Ok(node_counts)
+    }
+
+    pub fn render(&self, hgrid: &Hgrid) -> Result<String, BctidesError> {
+        let node_counts = self.check(hgrid)?;
+        let mut out = String::new();
+        self.render_into(&mut out, &node_counts)
+            .expect("Writing to a String cannot fail");
+        Ok(out)
+    }
+
+    fn render_into(&self, out: &mut String, node_counts: &[usize]) -> std::fmt::Result {
+        match &self.start_date {
+            Some(start_date) => writeln!(out, "{}", start_date.format("%Y-%m-%d %H:%M:%S"))?,
+            None => writeln!(out, "bctides.in")?,
+        }
+        writeln!(
+            out,
+            "{} {} !# of tidal potential constituents, cutoff depth",
+            self.tidal_potential.len(),
+            self.cutoff_depth
+        )?;
+        for potential in self.tidal_potential.iter() {
+            writeln!(out, "{}", potential.name)?;
+            writeln!(
+                out,
+                "{} {} {} {} {}",
+                potential.species,
+                potential.amplitude,
+                potential.frequency,
+                potential.nodal_factor,
+                potential.equilibrium_argument
+            )?;
+        }
+        writeln!(
+            out,
+            "{} !# of tidal forcing frequencies",
+            self.tidal_forcing.len()
+        )?;
+        for forcing in self.tidal_forcing.iter() {
+            writeln!(out, "{}", forcing.name)?;
+            writeln!(
+                out,
+                "{} {} {}",
+                forcing.frequency, forcing.nodal_factor, forcing.equilibrium_argument
+            )?;
+        }
+        writeln!(
+            out,
+            "{} !# of open boundary segments",
+            self.boundaries.len()
+        )?;
+        for (boundary, &node_count) in self.boundaries.iter().zip(node_counts.iter()) {
+            let [iettype, ifltype, itetype, isatype] = boundary.flags();
+            writeln!(
+                out,
+                "{} {} {} {} {} !# of nodes, iettype, ifltype, itetype, isatype",
+                node_count, iettype, ifltype, itetype, isatype
+            )?;
+            match &boundary.elevation {
+                ElevationBoundary::Constant(value) => writeln!(out, "{}", value)?,
+                ElevationBoundary::Tidal(harmonics)
+                | ElevationBoundary::TidalSpaceTime(harmonics) => {
+                    for (forcing, values) in self.tidal_forcing.iter().zip(harmonics.iter()) {
+                        writeln!(out, "{}", forcing.name)?;
+                        for [amplitude, phase] in values.values(node_count) {
+                            writeln!(out, "{} {}", amplitude, phase)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            match &boundary.velocity {
+                VelocityBoundary::Constant(value) => writeln!(out, "{}", value)?,
+                VelocityBoundary::Tidal(harmonics)
+                | VelocityBoundary::TidalSpaceTime(harmonics) => {
+                    for (forcing, values) in self.tidal_forcing.iter().zip(harmonics.iter()) {
+                        writeln!(out, "{}", forcing.name)?;
+                        for [u_amplitude, u_phase, v_amplitude, v_phase] in
+                            values.values(node_count)
+                        {
+                            writeln!(
+                                out,
+                                "{} {} {} {}",
+                                u_amplitude, u_phase, v_amplitude, v_phase
+                            )?;
+                        }
+                    }
+                }
+                VelocityBoundary::RelaxedSpaceTime { inflow, outflow } => {
+                    writeln!(out, "{} {} !inflow and outflow relaxation", inflow, outflow)?
+                }
+                _ => {}
+            }
+            for tracer in [&boundary.temperature, &boundary.salinity] {
+                if let TracerBoundary::Constant { value, .. } = tracer {
+                    writeln!(out, "{}", value)?;
+                }
+                if let Some(nudging) = tracer.nudging() {
+                    writeln!(out, "{} !nudging factor", nudging)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, hgrid: &Hgrid, path: &Path) -> Result<(), BctidesError> {
+        let contents = self.render(hgrid)?;
+        fs::write(path, contents)
+            .map_err(|e| BctidesError::IoError(path.display().to_string(), e.to_string()))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BctidesError {
+    #[error("The hgrid has {0} open boundary segments but {1} boundaries were given")]
+    BoundaryCountMismatch(usize, usize),
+    #[error("Open boundary {0} has {1} nodes but its harmonics have {2} values")]
+    NodeCountMismatch(usize, usize, usize),
+    #[error("Error writing {0}: {1}")]
+    IoError(String, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use schismrs_hgrid::synthetic::RectangularChannelBuilder;
+
+    #[test]
+    fn test_render() {
+        // Two open boundaries of 6 nodes each, at both ends of the channel.
+        let hgrid = RectangularChannelBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let tidal_boundary = BoundaryForcingBuilder::default()
+            .elevation(ElevationBoundary::Tidal(vec![NodeValues::PerNode(
+                (0..6).map(|i| [0.5 + 0.1 * i as f64, 10.]).collect(),
+            )]))
+            .velocity(VelocityBoundary::Tidal(vec![NodeValues::Constant([
+                0.1, 20., 0.2, 30.,
+            ])]))
+            .temperature(TracerBoundary::Constant {
+                value: 15.,
+                nudging: 0.5,
+            })
+            .salinity(TracerBoundary::TimeHistory { nudging: 1. })
+            .build()
+            .unwrap();
+        let river_boundary = BoundaryForcingBuilder::default()
+            .elevation(ElevationBoundary::TimeHistory)
+            .velocity(VelocityBoundary::RelaxedSpaceTime {
+                inflow: 0.5,
+                outflow: 0.9,
+            })
+            .build()
+            .unwrap();
+        let bctides = BctidesBuilder::default()
+            .start_date(
+                NaiveDate::from_ymd_opt(2018, 9, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .tidal_potential(vec![TidalPotential {
+                name: "M2".to_string(),
+                species: 2,
+                amplitude: 0.242334,
+                frequency: 0.0001405189,
+                nodal_factor: 1.0,
+                equilibrium_argument: 35.5,
+            }])
+            .tidal_forcing(vec![TidalForcing {
+                name: "M2".to_string(),
+                frequency: 0.0001405189,
+                nodal_factor: 1.0,
+                equilibrium_argument: 35.5,
+            }])
+            .boundaries(vec![tidal_boundary.clone(), river_boundary.clone()])
+            .build()
+            .unwrap();
+        let expected = "\
+2018-09-01 00:00:00
+1 40 !# of tidal potential constituents, cutoff depth
+M2
+2 0.242334 0.0001405189 1 35.5
+1 !# of tidal forcing frequencies
+M2
+0.0001405189 1 35.5
+2 !# of open boundary segments
+6 3 3 2 1 !# of nodes, iettype, ifltype, itetype, isatype
+M2
+0.5 10
+0.6 10
+0.7 10
+0.8 10
+0.9 10
+1 10
+M2
+0.1 20 0.2 30
+0.1 20 0.2 30
+0.1 20 0.2 30
+0.1 20 0.2 30
+0.1 20 0.2 30
+0.1 20 0.2 30
+15
+0.5 !nudging factor
+1 !nudging factor
+6 1 -4 0 0 !# of nodes, iettype, ifltype, itetype, isatype
+0.5 0.9 !inflow and outflow relaxation
+";
+        assert_eq!(bctides.render(&hgrid).unwrap(), expected);
+        assert_eq!(bctides.th_boundaries(ThKind::Elevation), vec![false, true]);
+        assert_eq!(bctides.th_boundaries(ThKind::Salinity), vec![true, false]);
+        let mut short = tidal_boundary.clone();
+        short.elevation = ElevationBoundary::Tidal(vec![NodeValues::PerNode(vec![[1., 0.]; 5])]);
+        let mismatched = BctidesBuilder::default()
+            .tidal_forcing(bctides.tidal_forcing().clone())
+            .boundaries(vec![short, river_boundary.clone()])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            mismatched.render(&hgrid),
+            Err(BctidesError::NodeCountMismatch(1, 6, 5))
+        ));
+        let single = BctidesBuilder::default()
+            .boundaries(vec![river_boundary])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            single.render(&hgrid),
+            Err(BctidesError::BoundaryCountMismatch(2, 1))
+        ));
+        assert!(BctidesBuilder::default()
+            .boundaries(vec![tidal_boundary])
+            .build()
+            .is_err());
+    }
+}
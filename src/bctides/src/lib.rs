@@ -0,0 +1,6 @@
+pub use bctides::{Bctides, BctidesBuilder};
+pub use constituents::TidalFactors;
+
+pub mod atlas;
+pub mod bctides;
+pub mod constituents;
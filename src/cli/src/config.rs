@@ -0,0 +1,101 @@
+use clap::Command;
+use figment::providers::{Format, Json, Toml, Yaml};
+use figment::value::{Dict, Value};
+use figment::Figment;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// Finds --config before clap parses the command line, since the config file changes the defaults
+// clap parses with.
+pub fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(|path| PathBuf::from(path.as_ref()));
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+pub fn load(path: &Path) -> Result<Dict, ConfigError> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let figment = match extension.as_str() {
+        "toml" => Figment::from(Toml::file(path)),
+        "yaml" | "yml" => Figment::from(Yaml::file(path)),
+        "json" => Figment::from(Json::file(path)),
+        _ => return Err(ConfigError::UnknownFormat(path.display().to_string())),
+    };
+    if !path.is_file() {
+        return Err(ConfigError::NotFound(path.display().to_string()));
+    }
+    figment
+        .extract()
+        .map_err(|e| ConfigError::ParseError(path.display().to_string(), e.to_string()))
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(_, value) => Some(value.clone()),
+        Value::Char(_, value) => Some(value.to_string()),
+        Value::Bool(_, value) => Some(value.to_string()),
+        Value::Num(_, value) => value
+            .to_i128()
+            .map(|value| value.to_string())
+            .or_else(|| value.to_f64().map(|value| value.to_string())),
+        _ => None,
+    }
+}
+
+// Turns the config tables into defaults of the matching (sub)commands, keyed by subcommand name
+// and option name, e.g. [vgrid.sz] slevels = 20. Options given on the command line still win.
+pub fn apply(mut command: Command, dict: &Dict, path: &str) -> Result<Command, ConfigError> {
+    for (key, value) in dict.iter() {
+        let id = key.replace('-', "_");
+        let key_path = match path.is_empty() {
+            true => key.clone(),
+            false => format!("{}.{}", path, key),
+        };
+        if let (Value::Dict(_, inner), Some(subcommand)) = (value, command.find_subcommand(key)) {
+            let subcommand = apply(subcommand.clone(), inner, &key_path)?;
+            command = command.mut_subcommand(key, |_| subcommand);
+            continue;
+        }
+        if !command
+            .get_arguments()
+            .any(|arg| arg.get_id().as_str() == id)
+        {
+            return Err(ConfigError::UnknownKey(key_path));
+        }
+        let values = match value {
+            Value::Array(_, values) => values.iter().map(scalar).collect::<Option<Vec<_>>>(),
+            value => scalar(value).map(|value| vec![value]),
+        }
+        .ok_or_else(|| ConfigError::InvalidValue(key_path.clone()))?;
+        command = command.mut_arg(id, |arg| arg.default_values(values).required(false));
+    }
+    Ok(command)
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Config file {0} does not exist")]
+    NotFound(String),
+    #[error("Config file {0} must have a .toml, .yaml, .yml or .json extension")]
+    UnknownFormat(String),
+    #[error("Error reading config file {0}: {1}")]
+    ParseError(String, String),
+    #[error("Config key {0} is not a subcommand or option")]
+    UnknownKey(String),
+    #[error("Config key {0} must be a string, number, boolean or a list of them")]
+    InvalidValue(String),
+}
@@ -0,0 +1,408 @@
+use super::plot::write_svg;
+use super::Output;
+use clap::{Args, Subcommand, ValueEnum};
+use schismrs_hgrid::boundaries::BoundaryType;
+use schismrs_hgrid::clip::ClipMode;
+use schismrs_hgrid::crs::GEOGRAPHIC_CRS;
+use schismrs_hgrid::geometry::{polygons_from_geojson_path, GeometryMethod};
+use schismrs_hgrid::hgrid::Hgrid;
+use schismrs_hgrid::quality::{QualityCheckBuilder, QUALITY_METRICS};
+use schismrs_hgrid::reproject::{reproject, reproject_from, HgridPairCheckBuilder};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand, Debug)]
+pub enum HgridCommand {
+    #[command(about = "Print a summary of an hgrid.")]
+    Info { hgrid_path: PathBuf },
+    #[command(about = "Convert an hgrid between the gr3 and SMS 2dm formats.")]
+    Convert {
+        input_path: PathBuf,
+        #[clap(help = "Output path. Files ending in .2dm are written as SMS 2dm, others as gr3.")]
+        output_path: PathBuf,
+        #[clap(long, action, help = "Split the quads into triangles.")]
+        split_quads: bool,
+    },
+    #[command(about = "Reproject an hgrid, e.g. hgrid.gr3 into hgrid.ll or back.")]
+    Reproject {
+        input_path: PathBuf,
+        output_path: PathBuf,
+        #[clap(
+            long,
+            help = "CRS of the input hgrid. Optional. Defaults to the CRS in the file header."
+        )]
+        from_crs: Option<String>,
+        #[clap(long, default_value = GEOGRAPHIC_CRS, help = "CRS of the output hgrid.")]
+        to_crs: String,
+    },
+    #[command(about = "Keep the part of an hgrid inside polygons or a bounding box.")]
+    Clip(ClipArgs),
+    #[command(about = "Check the mesh connectivity, orientation and boundaries.")]
+    Validate {
+        hgrid_path: PathBuf,
+        #[clap(long, help = "hgrid.ll to check against the hgrid. Optional.")]
+        ll: Option<PathBuf>,
+        #[clap(
+            long,
+            default_value = "1e-2",
+            help = "Coordinate tolerance of the hgrid.ll check, in hgrid units."
+        )]
+        coordinate_tolerance: f64,
+    },
+    #[command(about = "Compute element angles, skewness and aspect ratios.")]
+    Quality(QualityArgs),
+    #[command(about = "Plot the mesh and its depths to an SVG file.")]
+    Plot {
+        hgrid_path: PathBuf,
+        output_path: PathBuf,
+        #[clap(long, action, help = "Do not draw the element edges.")]
+        no_edges: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum ClipModeKind {
+    Centroid,
+    AllNodes,
+    AnyNode,
+}
+
+impl From<&ClipModeKind> for ClipMode {
+    fn from(kind: &ClipModeKind) -> Self {
+        match kind {
+            ClipModeKind::Centroid => ClipMode::Centroid,
+            ClipModeKind::AllNodes => ClipMode::AllNodes,
+            ClipModeKind::AnyNode => ClipMode::AnyNode,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ClipArgs {
+    input_path: PathBuf,
+    output_path: PathBuf,
+    #[clap(
+        long,
+        conflicts_with = "bbox",
+        required_unless_present = "bbox",
+        help = "GeoJSON file with the clipping polygons, in the hgrid CRS."
+    )]
+    polygons: Option<PathBuf>,
+    #[clap(
+        long,
+        num_args = 4,
+        value_names = ["XMIN", "YMIN", "XMAX", "YMAX"],
+        allow_negative_numbers = true
+    )]
+    bbox: Option<Vec<f64>>,
+    #[clap(long, value_enum, default_value = "centroid")]
+    mode: ClipModeKind,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum GeometryMethodKind {
+    Auto,
+    Planar,
+    Spherical,
+}
+
+#[derive(Args, Debug)]
+pub struct QualityArgs {
+    hgrid_path: PathBuf,
+    #[clap(long, value_enum, default_value = "auto")]
+    method: GeometryMethodKind,
+    #[clap(
+        long,
+        default_value = "10.",
+        help = "Smallest accepted angle in degrees."
+    )]
+    min_angle: f64,
+    #[clap(
+        long,
+        default_value = "150.",
+        help = "Largest accepted angle in degrees."
+    )]
+    max_angle: f64,
+    #[clap(
+        long,
+        default_value = "0.8",
+        help = "Largest accepted equiangle skewness."
+    )]
+    max_skewness: f64,
+    #[clap(long, default_value = "10.")]
+    max_aspect_ratio: f64,
+    #[clap(long, help = "Write the element skewness to a .prop file. Optional.")]
+    skewness_prop: Option<PathBuf>,
+}
+
+fn is_2dm(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("2dm"))
+}
+
+pub fn read_hgrid(path: &PathBuf) -> Result<Hgrid, Box<dyn Error>> {
+    match is_2dm(path) {
+        true => Ok(Hgrid::from_2dm_path(path)?),
+        false => Ok(Hgrid::try_from(path)?),
+    }
+}
+
+fn write_hgrid(hgrid: &Hgrid, path: &Path) -> Result<(), Box<dyn Error>> {
+    match is_2dm(path) {
+        true => hgrid.write_2dm(path)?,
+        false => hgrid.write(path)?,
+    }
+    Ok(())
+}
+
+fn written(hgrid: &Hgrid, path: &Path) -> Output {
+    let nodes = hgrid.nodes().len();
+    let elements = hgrid.elements().btree_map().len();
+    Output::new(
+        format!(
+            "Wrote {} with {} nodes and {} elements",
+            path.display(),
+            nodes,
+            elements
+        ),
+        json!({"output": path, "nodes": nodes, "elements": elements}),
+    )
+}
+
+fn info(hgrid: &Hgrid) -> Output {
+    let element_map = hgrid.elements().btree_map();
+    let triangles = element_map
+        .values()
+        .filter(|node_ids| node_ids.len() == 3)
+        .count();
+    let (x, y) = (hgrid.x(), hgrid.y());
+    let bbox = [
+        x.fold(f64::INFINITY, |a, &b| a.min(b)),
+        y.fold(f64::INFINITY, |a, &b| a.min(b)),
+        x.fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+        y.fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+    ];
+    // Node values are elevations, reported here as positive down depths.
+    let depths = hgrid.depths();
+    let depth_range = (!depths.is_empty()).then(|| {
+        [
+            -depths.fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+            -depths.fold(f64::INFINITY, |a, &b| a.min(b)),
+        ]
+    });
+    let crs = hgrid.crs().map(|crs| crs.definition().to_string());
+    let boundaries = hgrid
+        .boundaries()
+        .map(|boundaries| boundaries.to_boundary_type_map())
+        .unwrap_or_default();
+    let segment_sizes = |boundary_type: BoundaryType| -> Vec<usize> {
+        boundaries
+            .get(&boundary_type)
+            .map(|segments| segments.iter().map(Vec::len).collect())
+            .unwrap_or_default()
+    };
+    let (open, land, interior) = (
+        segment_sizes(BoundaryType::Open),
+        segment_sizes(BoundaryType::Land),
+        segment_sizes(BoundaryType::Interior),
+    );
+    let mut text = vec![
+        format!("nodes: {}", hgrid.nodes().len()),
+        format!(
+            "elements: {} ({} triangles, {} quads)",
+            element_map.len(),
+            triangles,
+            element_map.len() - triangles
+        ),
+        format!("bbox: [{}, {}, {}, {}]", bbox[0], bbox[1], bbox[2], bbox[3]),
+    ];
+    if let Some([min, max]) = depth_range {
+        text.push(format!("depth (positive down): [{}, {}]", min, max));
+    }
+    text.push(format!("crs: {}", crs.as_deref().unwrap_or("none")));
+    if let Some(description) = hgrid.description() {
+        text.push(format!("description: {}", description));
+    }
+    for (name, sizes) in [("open", &open), ("land", &land), ("interior", &interior)] {
+        text.push(format!(
+            "{} boundaries: {} ({} nodes)",
+            name,
+            sizes.len(),
+            sizes.iter().sum::<usize>()
+        ));
+    }
+    Output::new(
+        text.join("\n"),
+        json!({
+            "nodes": hgrid.nodes().len(),
+            "elements": element_map.len(),
+            "triangles": triangles,
+            "quads": element_map.len() - triangles,
+            "bbox": bbox,
+            "depth_range": depth_range,
+            "crs": crs,
+            "description": hgrid.description(),
+            "boundaries": {
+                "open": open,
+                "land": land,
+                "interior": interior,
+            },
+        }),
+    )
+}
+
+fn clip(args: &ClipArgs) -> Result<Output, Box<dyn Error>> {
+    let hgrid = read_hgrid(&args.input_path)?;
+    let mode = ClipMode::from(&args.mode);
+    let clipped = match (&args.polygons, &args.bbox) {
+        (Some(path), _) => {
+            let polygons: Vec<_> = polygons_from_geojson_path(path)?
+                .into_iter()
+                .map(|(_, polygon)| polygon)
+                .collect();
+            hgrid.clip(&polygons, mode)?
+        }
+        (None, Some(bbox)) => hgrid.clip_to_bbox([bbox[0], bbox[1], bbox[2], bbox[3]], mode)?,
+        (None, None) => unreachable!("clap requires --polygons or --bbox"),
+    };
+    write_hgrid(&clipped, &args.output_path)?;
+    Ok(written(&clipped, &args.output_path))
+}
+
+fn validate(
+    hgrid_path: &PathBuf,
+    ll: Option<&PathBuf>,
+    coordinate_tolerance: f64,
+) -> Result<Output, Box<dyn Error>> {
+    let hgrid = read_hgrid(hgrid_path)?;
+    let issues: Vec<String> = hgrid
+        .mesh_issues()
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect();
+    let mut text = match issues.is_empty() {
+        true => vec![format!("{} has no mesh issues", hgrid_path.display())],
+        false => issues.clone(),
+    };
+    let mut json = json!({"issues": issues});
+    let mut success = issues.is_empty();
+    if let Some(ll_path) = ll {
+        let ll = read_hgrid(ll_path)?;
+        let report = HgridPairCheckBuilder::default()
+            .coordinate_tolerance(coordinate_tolerance)
+            .build()?
+            .check(&hgrid, &ll)?;
+        text.push(report.to_string());
+        json["ll"] = json!({
+            "consistent": report.is_consistent(),
+            "mismatches": report
+                .mismatches()
+                .iter()
+                .map(|mismatch| mismatch.to_string())
+                .collect::<Vec<_>>(),
+            "max_coordinate_error": report.max_coordinate_error(),
+            "max_depth_error": report.max_depth_error(),
+        });
+        success &= report.is_consistent();
+    }
+    Ok(Output::new(text.join("\n"), json).success(success))
+}
+
+fn quality(args: &QualityArgs) -> Result<Output, Box<dyn Error>> {
+    let hgrid = read_hgrid(&args.hgrid_path)?;
+    let method = match args.method {
+        GeometryMethodKind::Auto => GeometryMethod::Auto,
+        GeometryMethodKind::Planar => GeometryMethod::Planar,
+        GeometryMethodKind::Spherical => GeometryMethod::Spherical,
+    };
+    let report = QualityCheckBuilder::default()
+        .method(method)
+        .min_angle(args.min_angle)
+        .max_angle(args.max_angle)
+        .max_skewness(args.max_skewness)
+        .max_aspect_ratio(args.max_aspect_ratio)
+        .build()?
+        .check(&hgrid);
+    if let Some(path) = &args.skewness_prop {
+        report.write_skewness(path)?;
+    }
+    let summary: serde_json::Map<String, Value> = QUALITY_METRICS
+        .iter()
+        .filter_map(|(name, metric)| {
+            report.summary(metric).map(|summary| {
+                (
+                    name.replace(' ', "_"),
+                    json!({"min": summary.min, "mean": summary.mean, "max": summary.max}),
+                )
+            })
+        })
+        .collect();
+    let flagged: serde_json::Map<String, Value> = report
+        .flagged()
+        .iter()
+        .map(|(element_id, issues)| {
+            let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+            (element_id.to_string(), json!(issues))
+        })
+        .collect();
+    Ok(Output::new(
+        report.to_string(),
+        json!({
+            "elements": report.elements().len(),
+            "summary": summary,
+            "flagged": flagged,
+        }),
+    ))
+}
+
+pub fn run(command: HgridCommand) -> Result<Output, Box<dyn Error>> {
+    match command {
+        HgridCommand::Info { hgrid_path } => Ok(info(&read_hgrid(&hgrid_path)?)),
+        HgridCommand::Convert {
+            input_path,
+            output_path,
+            split_quads,
+        } => {
+            let mut hgrid = read_hgrid(&input_path)?;
+            if split_quads {
+                hgrid = hgrid.split_quads()?;
+            }
+            write_hgrid(&hgrid, &output_path)?;
+            Ok(written(&hgrid, &output_path))
+        }
+        HgridCommand::Reproject {
+            input_path,
+            output_path,
+            from_crs,
+            to_crs,
+        } => {
+            let hgrid = read_hgrid(&input_path)?;
+            let reprojected = match from_crs {
+                Some(from_crs) => reproject_from(&hgrid, &from_crs, &to_crs)?,
+                None => reproject(&hgrid, &to_crs)?,
+            };
+            write_hgrid(&reprojected, &output_path)?;
+            Ok(written(&reprojected, &output_path))
+        }
+        HgridCommand::Clip(args) => clip(&args),
+        HgridCommand::Validate {
+            hgrid_path,
+            ll,
+            coordinate_tolerance,
+        } => validate(&hgrid_path, ll.as_ref(), coordinate_tolerance),
+        HgridCommand::Quality(args) => quality(&args),
+        HgridCommand::Plot {
+            hgrid_path,
+            output_path,
+            no_edges,
+        } => {
+            let hgrid = read_hgrid(&hgrid_path)?;
+            write_svg(&hgrid, &output_path, !no_edges)?;
+            Ok(Output::new(
+                format!("Wrote {}", output_path.display()),
+                json!({"output": output_path}),
+            ))
+        }
+    }
+}
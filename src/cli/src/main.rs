@@ -0,0 +1,162 @@
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::LevelFilter;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod config;
+mod hgrid;
+mod plot;
+mod storm;
+mod vgrid;
+
+const VERSION: &str = concat! {
+    env! {"CARGO_PKG_VERSION"},
+    "-",
+    env! {"VERGEN_GIT_DESCRIBE"}
+};
+
+#[derive(Parser, Debug)]
+#[command(author, about, long_about = None)]
+#[command(version = VERSION)]
+struct Cli {
+    #[clap(
+        long,
+        global = true,
+        help = "TOML, YAML or JSON file with default option values, one table per \
+                subcommand, e.g. [hgrid.reproject] to_crs = \"EPSG:32618\"."
+    )]
+    config: Option<PathBuf>,
+    #[clap(long, global = true, help = "Print the results as JSON.")]
+    json: bool,
+    #[clap(
+        short,
+        long,
+        global = true,
+        action = ArgAction::Count,
+        help = "Increase the log level, -v for info, -vv for debug and -vvv for trace. \
+                RUST_LOG takes precedence when set."
+    )]
+    verbose: u8,
+    #[clap(
+        short,
+        long,
+        global = true,
+        conflicts_with = "verbose",
+        help = "Log errors only."
+    )]
+    quiet: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(subcommand, about = "Inspect, convert and check horizontal grids.")]
+    Hgrid(hgrid::HgridCommand),
+    #[command(subcommand, about = "Generate vertical grids.")]
+    Vgrid(vgrid::VgridCommand),
+    #[command(subcommand, about = "Fetch storm tracks.")]
+    Storm(storm::StormCommand),
+}
+
+// What a subcommand prints: plain text by default, or the JSON value with --json. A command that
+// ran but found problems, e.g. an invalid mesh, returns success = false.
+pub struct Output {
+    text: String,
+    json: Value,
+    success: bool,
+}
+
+impl Output {
+    pub fn new(text: String, json: Value) -> Self {
+        Self {
+            text,
+            json,
+            success: true,
+        }
+    }
+
+    pub fn success(mut self, success: bool) -> Self {
+        self.success = success;
+        self
+    }
+}
+
+fn init_logging(verbose: u8, quiet: bool) {
+    let mut builder = pretty_env_logger::formatted_builder();
+    match std::env::var("RUST_LOG") {
+        Ok(filters) => {
+            builder.parse_filters(&filters);
+        }
+        Err(_) => {
+            let level = match (quiet, verbose) {
+                (true, _) => LevelFilter::Error,
+                (false, 0) => LevelFilter::Warn,
+                (false, 1) => LevelFilter::Info,
+                (false, 2) => LevelFilter::Debug,
+                (false, _) => LevelFilter::Trace,
+            };
+            builder.filter_level(level);
+        }
+    }
+    builder.init();
+}
+
+fn parse_cli() -> Result<Cli, Box<dyn Error>> {
+    let args: Vec<_> = std::env::args_os().collect();
+    let mut command = Cli::command();
+    if let Some(path) = config::config_path(&args) {
+        let dict = config::load(&path)?;
+        command = config::apply(command, &dict, "")?;
+    }
+    let matches = command
+        .try_get_matches_from(args)
+        .unwrap_or_else(|e| e.exit());
+    Ok(Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()))
+}
+
+fn entrypoint(cli: Cli) -> Result<Output, Box<dyn Error>> {
+    match cli.command {
+        Command::Hgrid(command) => hgrid::run(command),
+        Command::Vgrid(command) => vgrid::run(command),
+        Command::Storm(command) => storm::run(command),
+    }
+}
+
+fn main() -> ExitCode {
+    let json_requested = std::env::args_os().any(|arg| arg == "--json");
+    let cli = match parse_cli() {
+        Ok(cli) => cli,
+        Err(e) => {
+            match json_requested {
+                true => println!("{:#}", json!({"error": e.to_string()})),
+                false => eprintln!("Error: {:?}: {}", e, e),
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    init_logging(cli.verbose, cli.quiet);
+    let json = cli.json;
+    match entrypoint(cli) {
+        Err(e) => {
+            match json {
+                true => println!("{:#}", json!({"error": e.to_string()})),
+                false => eprintln!("Error: {:?}: {}", e, e),
+            }
+            ExitCode::FAILURE
+        }
+        Ok(output) => {
+            match json {
+                true => println!("{:#}", output.json),
+                false if output.text.is_empty() => {}
+                false => println!("{}", output.text),
+            }
+            match output.success {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            }
+        }
+    }
+}
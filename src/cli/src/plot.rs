@@ -0,0 +1,136 @@
+use schismrs_hgrid::hgrid::Hgrid;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const WIDTH: f64 = 1000.;
+const MARGIN: f64 = 20.;
+const COLORBAR_WIDTH: f64 = 100.;
+
+// Viridis sampled at 0, 0.25, 0.5, 0.75 and 1.
+const VIRIDIS: [[f64; 3]; 5] = [
+    [68., 1., 84.],
+    [59., 82., 139.],
+    [33., 145., 140.],
+    [94., 201., 98.],
+    [253., 231., 37.],
+];
+
+fn color(t: f64) -> String {
+    let t = if t.is_finite() { t.clamp(0., 1.) } else { 0. };
+    let position = t * (VIRIDIS.len() - 1) as f64;
+    let index = (position.floor() as usize).min(VIRIDIS.len() - 2);
+    let fraction = position - index as f64;
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let (a, b) = (VIRIDIS[index][channel], VIRIDIS[index + 1][channel]);
+        (a + (b - a) * fraction).round() as u8
+    });
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// Draws the elements filled by their mean depth (positive down), with a colorbar on the right.
+pub fn write_svg(hgrid: &Hgrid, path: &Path, edges: bool) -> std::io::Result<()> {
+    let nodes = hgrid.nodes().btree_map();
+    let coord = |node_id: &u32| {
+        let (coord, _) = &nodes[node_id];
+        [coord[0], coord[1]]
+    };
+    let depth = |node_id: &u32| {
+        let (_, values) = &nodes[node_id];
+        -values
+            .as_ref()
+            .and_then(|values| values.first().copied())
+            .unwrap_or(0.)
+    };
+    let (mut xmin, mut ymin) = (f64::INFINITY, f64::INFINITY);
+    let (mut xmax, mut ymax) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for node_id in nodes.keys() {
+        let [x, y] = coord(node_id);
+        (xmin, ymin, xmax, ymax) = (xmin.min(x), ymin.min(y), xmax.max(x), ymax.max(y));
+    }
+    let scale = (WIDTH - 2. * MARGIN) / (xmax - xmin).max(ymax - ymin).max(f64::MIN_POSITIVE);
+    let height = (ymax - ymin) * scale + 2. * MARGIN;
+    let width = (xmax - xmin) * scale + 2. * MARGIN;
+    let to_svg = |[x, y]: [f64; 2]| {
+        [
+            MARGIN + (x - xmin) * scale,
+            height - MARGIN - (y - ymin) * scale,
+        ]
+    };
+    let elements = hgrid.elements().btree_map();
+    let element_depths: Vec<f64> = elements
+        .values()
+        .map(|node_ids| node_ids.iter().map(depth).sum::<f64>() / node_ids.len() as f64)
+        .collect();
+    let dmin = element_depths.iter().copied().fold(f64::INFINITY, f64::min);
+    let dmax = element_depths
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let range = (dmax - dmin).max(f64::MIN_POSITIVE);
+
+    let mut svg = String::new();
+    let total_width = width + COLORBAR_WIDTH;
+    // Writing to a String cannot fail, so the fmt::Results below are ignored.
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+         viewBox=\"0 0 {:.2} {:.2}\">",
+        total_width, height, total_width, height
+    );
+    let stroke = match edges {
+        true => " stroke=\"#333333\" stroke-width=\"0.3\"",
+        false => "",
+    };
+    let _ = writeln!(svg, "<g{}>", stroke);
+    for (node_ids, element_depth) in elements.values().zip(element_depths.iter()) {
+        let points: Vec<String> = node_ids
+            .iter()
+            .map(|node_id| {
+                let [x, y] = to_svg(coord(node_id));
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect();
+        let fill = color((element_depth - dmin) / range);
+        // Without edges, a stroke of the fill colour hides the seams between elements.
+        let seam = match edges {
+            true => String::new(),
+            false => format!(" stroke=\"{}\" stroke-width=\"0.3\"", fill),
+        };
+        let _ = writeln!(
+            svg,
+            "<polygon points=\"{}\" fill=\"{}\"{}/>",
+            points.join(" "),
+            fill,
+            seam
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(
+        svg,
+        "<defs><linearGradient id=\"colorbar\" x1=\"0\" y1=\"1\" x2=\"0\" y2=\"0\">"
+    );
+    for (i, _) in VIRIDIS.iter().enumerate() {
+        let t = i as f64 / (VIRIDIS.len() - 1) as f64;
+        let _ = writeln!(svg, "<stop offset=\"{}\" stop-color=\"{}\"/>", t, color(t));
+    }
+    let _ = writeln!(svg, "</linearGradient></defs>");
+    let bar_x = width + MARGIN;
+    let bar_height = height - 2. * MARGIN;
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"20\" height=\"{:.2}\" fill=\"url(#colorbar)\"/>",
+        bar_x, MARGIN, bar_height
+    );
+    for (label, y) in [(dmax, MARGIN + 10.), (dmin, height - MARGIN)] {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"12\" font-family=\"sans-serif\">{:.2}</text>",
+            bar_x + 25.,
+            y,
+            label
+        );
+    }
+    let _ = writeln!(svg, "</svg>");
+    fs::write(path, svg)
+}
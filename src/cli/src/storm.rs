@@ -0,0 +1,108 @@
+use super::Output;
+use clap::{Subcommand, ValueEnum};
+use polars::prelude::{AnyValue, CsvWriter, DataFrame, SerWriter};
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::fs::File;
+use std::path::PathBuf;
+use storm_events::atcf::ATCFFileDeck;
+use storm_events::storm_event::StormEventBuilder;
+
+#[derive(Subcommand, Debug)]
+pub enum StormCommand {
+    #[command(about = "Fetch the ATCF track of a storm.")]
+    Fetch {
+        #[clap(help = "Can be NameYear (e.g. Sandy2012) or NHC code (e.g. AL182012)")]
+        storm_id: String,
+        #[clap(long, value_enum, default_value = "best")]
+        file_deck: FileDeckKind,
+        #[clap(
+            short,
+            long,
+            help = "CSV output path. Optional. Defaults to printing the track."
+        )]
+        output_filepath: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum FileDeckKind {
+    Advisory,
+    Best,
+    Fixed,
+}
+
+impl FileDeckKind {
+    fn to_atcf_file_deck(&self) -> ATCFFileDeck {
+        match self {
+            FileDeckKind::Advisory => ATCFFileDeck::ADVISORY,
+            FileDeckKind::Best => ATCFFileDeck::BEST,
+            FileDeckKind::Fixed => ATCFFileDeck::FIXED,
+        }
+    }
+}
+
+fn to_json(value: &AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(value) => json!(value),
+        value if value.is_numeric() => json!(value.extract::<f64>()),
+        value => match value.get_str() {
+            Some(value) => json!(value),
+            None => json!(value.to_string()),
+        },
+    }
+}
+
+fn records(track: &DataFrame) -> Vec<Value> {
+    let columns = track.get_columns();
+    (0..track.height())
+        .map(|row| {
+            let record: Map<String, Value> = columns
+                .iter()
+                .map(|column| {
+                    let value = column.get(row).map_or(Value::Null, |value| to_json(&value));
+                    (column.name().to_string(), value)
+                })
+                .collect();
+            Value::Object(record)
+        })
+        .collect()
+}
+
+pub fn run(command: StormCommand) -> Result<Output, Box<dyn Error>> {
+    match command {
+        StormCommand::Fetch {
+            storm_id,
+            file_deck,
+            output_filepath,
+        } => {
+            let storm_event = StormEventBuilder::default()
+                .file_deck(&file_deck.to_atcf_file_deck())
+                .storm_id(&storm_id)
+                .build()?;
+            let mut track = storm_event.track().clone();
+            let text = match &output_filepath {
+                Some(path) => {
+                    let mut file = File::create(path)?;
+                    CsvWriter::new(&mut file).finish(&mut track)?;
+                    format!("Wrote {} with {} records", path.display(), track.height())
+                }
+                None => {
+                    let mut buffer = Vec::new();
+                    CsvWriter::new(&mut buffer).finish(&mut track)?;
+                    String::from_utf8(buffer)?.trim_end().to_string()
+                }
+            };
+            Ok(Output::new(
+                text,
+                json!({
+                    "storm_id": storm_id,
+                    "output": output_filepath,
+                    "columns": track.get_column_names(),
+                    "records": records(&track),
+                }),
+            ))
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use super::hgrid::read_hgrid;
+use super::Output;
+use clap::{Args, Subcommand, ValueEnum};
+use schismrs_vgrid::sz::SZBuilder;
+use schismrs_vgrid::transforms::quadratic::QuadraticTransformOpts;
+use schismrs_vgrid::transforms::s::STransformOpts;
+use schismrs_vgrid::transforms::StretchingFunction;
+use schismrs_vgrid::vqs::{VQSAutoBuilder, VQSBuilder, VQSKMeansBuilder};
+use schismrs_vgrid::VerticalGrid;
+use serde_json::json;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+fn greater_than_two(s: &str) -> Result<usize, String> {
+    let value: usize = s
+        .parse()
+        .map_err(|_| format!("`{}` isn't a valid positive integer", s))?;
+    if value >= 2 {
+        Ok(value)
+    } else {
+        Err(format!(
+            "The value must be greater or equal than 2, got {}",
+            value
+        ))
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VgridCommand {
+    #[command(about = "Generate an SZ (ivcor = 2) vertical grid.")]
+    Sz(SzArgs),
+    #[command(about = "Generate an LSC2 (ivcor = 1) vertical grid.")]
+    Vqs(VqsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SzArgs {
+    hgrid_path: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Output path. Optional. Defaults to printing the grid."
+    )]
+    output_filepath: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Number of sigma-levels. Must be an integer greater or equal than 2.",
+        value_parser = clap::builder::ValueParser::new(greater_than_two),
+        default_value = "2",
+    )]
+    slevels: usize,
+    #[clap(long, value_delimiter = ' ', num_args = 1..,
+        help = "Space delimited list of depths for each z-level. \
+                Optional. Defaults to pure sigma grid."
+    )]
+    zlevels: Option<Vec<f64>>,
+    #[clap(
+        long,
+        default_value = "0.1",
+        help = "Range is (0., 20.]. Values closer to 0. make the transformation \
+                more similar to traditional sigma. Larger values will increase \
+                resolution at the top and bottom."
+    )]
+    theta_f: f64,
+    #[clap(
+        long,
+        default_value = "0.",
+        help = "Range is [0., 1.]. For values closer to 0. the surface is \
+                resolved. For values closer to 1., both the surface and bottom \
+                are resolved."
+    )]
+    theta_b: f64,
+    #[clap(
+        long,
+        help = "Critical layer depth. Value must be > 5.",
+        alias = "hc",
+        default_value = "5."
+    )]
+    critical_depth: f64,
+    #[clap(
+        long,
+        default_value = "0.",
+        help = "Water level offset. Not typically needed."
+    )]
+    etal: f64,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum StretchingFunctionKind {
+    Quadratic,
+    S,
+}
+
+#[derive(Args, Debug)]
+pub struct VqsArgs {
+    hgrid_path: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Output path. Optional. Defaults to printing the grid."
+    )]
+    output_filepath: Option<PathBuf>,
+    #[clap(long, value_enum)]
+    transform: StretchingFunctionKind,
+    #[clap(
+        long,
+        default_value = "0.",
+        allow_negative_numbers = true,
+        help = "|a_vqs0|<=1. -- -1 skew towards bottom, 1. skew towards surface"
+    )]
+    a_vqs0: f64,
+    #[clap(long, default_value = "0.", help = "defined as positive down")]
+    etal: f64,
+    #[clap(long, default_value = "0.3")]
+    skew_decay_rate: f64,
+    #[clap(long, help = "Required by the s transform. Range is (0., 20.].")]
+    theta_f: Option<f64>,
+    #[clap(long, help = "Required by the s transform. Range is [0., 1.].")]
+    theta_b: Option<f64>,
+    #[clap(long)]
+    dz_bottom_min: f64,
+    #[command(subcommand)]
+    mode: VqsMode,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VqsMode {
+    #[command(about = "Master grids from k-means clusters of the depths.")]
+    Kmeans {
+        #[clap(long, help = "Number of clusters. Must be an interger >= 1")]
+        clusters: usize,
+        #[clap(
+            long,
+            help = "Controls the initial number of layers. Must be an integer >= 2. \
+                    Optional."
+        )]
+        shallow_levels: Option<usize>,
+        #[clap(
+            long,
+            help = "Controls the maximum number of layers in the clustering hierarchy. \
+                    Defaults to shallow_levels + clusters - 1"
+        )]
+        max_levels: Option<usize>,
+    },
+    #[command(about = "Master grids from explicit depths and level counts.")]
+    Hsm {
+        #[clap(long, value_delimiter = ' ', num_args = 1..)]
+        depths: Vec<f64>,
+        #[clap(long, value_delimiter = ' ', num_args = 1..)]
+        nlevels: Vec<usize>,
+    },
+    #[command(
+        about = "Master grids placed automatically between the shallowest and deepest node."
+    )]
+    Auto {
+        #[clap(long, help = "Number of master grids to generate. Must be an int >= 1")]
+        ngrids: usize,
+        #[clap(
+            long,
+            default_value = "1.",
+            help = "This is the first depth below etal. This input is positive down."
+        )]
+        initial_depth: f64,
+        #[clap(
+            long,
+            default_value = "2",
+            help = "Controls the initial number of layers. Must be an integer >= 2."
+        )]
+        shallow_levels: usize,
+        #[clap(
+            long,
+            help = "Controls the maximum number of layers in the clustering hierarchy. \
+                    Defaults to shallow_levels + clusters - 1"
+        )]
+        max_levels: Option<usize>,
+    },
+}
+
+fn finish<V: VerticalGrid + Display>(
+    vgrid: &V,
+    output_filepath: Option<&PathBuf>,
+) -> Result<Output, Box<dyn Error>> {
+    let text = match output_filepath {
+        Some(path) => {
+            vgrid.write_to_file(path)?;
+            format!(
+                "Wrote {} with ivcor = {} and nvrt = {}",
+                path.display(),
+                vgrid.ivcor(),
+                vgrid.nvrt()
+            )
+        }
+        None => vgrid.to_string(),
+    };
+    let mut json = json!({
+        "ivcor": vgrid.ivcor(),
+        "nvrt": vgrid.nvrt(),
+        "output": output_filepath,
+    });
+    if output_filepath.is_none() {
+        json["vgrid"] = json!(vgrid.to_string());
+    }
+    Ok(Output::new(text, json))
+}
+
+fn sz(args: &SzArgs) -> Result<Output, Box<dyn Error>> {
+    let hgrid = read_hgrid(&args.hgrid_path)?;
+    let mut builder = SZBuilder::default();
+    builder.hgrid(&hgrid);
+    builder.slevels(&args.slevels);
+    builder.theta_f(&args.theta_f);
+    builder.theta_b(&args.theta_b);
+    builder.critical_depth(&args.critical_depth);
+    builder.etal(&args.etal);
+    if let Some(zlevels) = &args.zlevels {
+        builder.zlevels(zlevels);
+    }
+    let sz = builder.build()?;
+    finish(&sz, args.output_filepath.as_ref())
+}
+
+fn vqs(args: &VqsArgs) -> Result<Output, Box<dyn Error>> {
+    let hgrid = read_hgrid(&args.hgrid_path)?;
+    let transform = match args.transform {
+        StretchingFunctionKind::Quadratic => {
+            StretchingFunction::Quadratic(QuadraticTransformOpts {
+                a_vqs0: &args.a_vqs0,
+                etal: &args.etal,
+                skew_decay_rate: &args.skew_decay_rate,
+            })
+        }
+        StretchingFunctionKind::S => StretchingFunction::S(STransformOpts {
+            a_vqs0: &args.a_vqs0,
+            etal: &args.etal,
+            theta_b: args
+                .theta_b
+                .as_ref()
+                .ok_or("--theta-b is required by the s transform")?,
+            theta_f: args
+                .theta_f
+                .as_ref()
+                .ok_or("--theta-f is required by the s transform")?,
+        }),
+    };
+    let vqs = match &args.mode {
+        VqsMode::Hsm { depths, nlevels } => VQSBuilder::default()
+            .hgrid(&hgrid)
+            .depths(depths)
+            .nlevels(nlevels)
+            .stretching(&transform)
+            .dz_bottom_min(&args.dz_bottom_min)
+            .build()?,
+        VqsMode::Kmeans {
+            clusters,
+            shallow_levels,
+            max_levels,
+        } => {
+            let mut builder = VQSKMeansBuilder::default();
+            builder.hgrid(&hgrid);
+            builder.stretching(&transform);
+            builder.nclusters(clusters);
+            builder.dz_bottom_min(&args.dz_bottom_min);
+            builder.etal(&args.etal);
+            if let Some(shallow_levels) = shallow_levels {
+                builder.shallow_levels(shallow_levels);
+            }
+            if let Some(max_levels) = max_levels {
+                builder.max_levels(max_levels);
+            }
+            builder.build()?
+        }
+        VqsMode::Auto {
+            ngrids,
+            initial_depth,
+            shallow_levels,
+            max_levels,
+        } => {
+            let mut builder = VQSAutoBuilder::default();
+            builder.hgrid(&hgrid);
+            builder.stretching(&transform);
+            builder.ngrids(ngrids);
+            builder.dz_bottom_min(&args.dz_bottom_min);
+            builder.initial_depth(initial_depth);
+            builder.shallow_levels(shallow_levels);
+            if let Some(max_levels) = max_levels {
+                builder.max_levels(max_levels);
+            }
+            builder.build()?
+        }
+    };
+    finish(&vqs, args.output_filepath.as_ref())
+}
+
+pub fn run(command: VgridCommand) -> Result<Output, Box<dyn Error>> {
+    match command {
+        VgridCommand::Sz(args) => sz(&args),
+        VgridCommand::Vqs(args) => vqs(&args),
+    }
+}
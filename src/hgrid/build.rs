@@ -7,6 +7,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         .all_build()
         .all_cargo()
         .all_git()
+        .git_describe(true, false, None)
         .all_rustc()
         .emit()?;
     Ok(())
@@ -0,0 +1,319 @@
+use super::editing::HgridEditError;
+use super::geometry::Polygon;
+use super::hgrid::Hgrid;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+// Hgrid stores node values with the sign reversed with respect to the gr3 file, so the
+// operations in this module convert to positive-down depths before doing any work.
+fn positive_down_depths(hgrid: &Hgrid) -> BTreeMap<u32, f64> {
+    hgrid
+        .depths_btree_map()
+        .into_iter()
+        .map(|(node_id, value)| (node_id, -value))
+        .collect()
+}
+
+fn commit_depths(
+    hgrid: &mut Hgrid,
+    original: &BTreeMap<u32, f64>,
+    updated: &BTreeMap<u32, f64>,
+) -> Result<BathymetryEditReport, BathymetryEditError> {
+    let changes: BTreeMap<u32, f64> = updated
+        .iter()
+        .filter_map(|(node_id, &new_depth)| {
+            let change = new_depth - original[node_id];
+            if change != 0. {
+                Some((*node_id, change))
+            } else {
+                None
+            }
+        })
+        .collect();
+    if !changes.is_empty() {
+        let reversed: BTreeMap<u32, f64> = updated
+            .iter()
+            .map(|(&node_id, &depth)| (node_id, -depth))
+            .collect();
+        hgrid.set_depths(&reversed)?;
+    }
+    Ok(BathymetryEditReport { changes })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BathymetryEditReport {
+    changes: BTreeMap<u32, f64>,
+}
+
+impl BathymetryEditReport {
+    pub fn changes(&self) -> &BTreeMap<u32, f64> {
+        &self.changes
+    }
+
+    pub fn nodes_changed(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn max_abs_change(&self) -> f64 {
+        self.changes
+            .values()
+            .fold(0., |max_change, change| f64::max(max_change, change.abs()))
+    }
+
+    pub fn mean_abs_change(&self) -> f64 {
+        if self.changes.is_empty() {
+            return 0.;
+        }
+        self.changes
+            .values()
+            .map(|change| change.abs())
+            .sum::<f64>()
+            / self.changes.len() as f64
+    }
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct DepthClamp {
+    #[builder(setter(into, strip_option), default)]
+    hmin: Option<f64>,
+    #[builder(setter(into, strip_option), default)]
+    hmax: Option<f64>,
+    #[builder(setter(into, strip_option), default)]
+    polygon: Option<Polygon>,
+}
+
+impl DepthClampBuilder {
+    pub fn validate(&self) -> Result<(), DepthClampBuilderError> {
+        let hmin = self.hmin.flatten();
+        let hmax = self.hmax.flatten();
+        if hmin.is_none() && hmax.is_none() {
+            return Err(DepthClampBuilderError::ValidationError(
+                "At least one of hmin or hmax must be set.".to_string(),
+            ));
+        }
+        if let (Some(hmin), Some(hmax)) = (hmin, hmax) {
+            if hmin > hmax {
+                return Err(DepthClampBuilderError::ValidationError(format!(
+                    "hmin must be <= hmax but got hmin={} and hmax={}",
+                    hmin, hmax
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DepthClamp {
+    pub fn apply(&self, hgrid: &mut Hgrid) -> Result<BathymetryEditReport, BathymetryEditError> {
+        let original = positive_down_depths(hgrid);
+        let nodes = hgrid.nodes().btree_map();
+        let mut updated = original.clone();
+        for (node_id, depth) in updated.iter_mut() {
+            if let Some(polygon) = &self.polygon {
+                let (coord, _values) = &nodes[node_id];
+                if !polygon.contains(coord[0], coord[1]) {
+                    continue;
+                }
+            }
+            if let Some(hmin) = self.hmin {
+                *depth = depth.max(hmin);
+            }
+            if let Some(hmax) = self.hmax {
+                *depth = depth.min(hmax);
+            }
+        }
+        commit_depths(hgrid, &original, &updated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SmoothingMethod {
+    #[default]
+    Laplacian,
+    AreaWeighted,
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct DepthSmoothing {
+    #[builder(default)]
+    method: SmoothingMethod,
+    #[builder(default = "1")]
+    iterations: usize,
+    #[builder(default = "0.5")]
+    weight: f64,
+}
+
+impl DepthSmoothingBuilder {
+    pub fn validate(&self) -> Result<(), DepthSmoothingBuilderError> {
+        if let Some(weight) = self.weight {
+            if !(weight > 0. && weight <= 1.) {
+                return Err(DepthSmoothingBuilderError::ValidationError(format!(
+                    "weight must be in (0., 1.] but got {}",
+                    weight
+                )));
+            }
+        }
+        if let Some(iterations) = self.iterations {
+            if iterations < 1 {
+                return Err(DepthSmoothingBuilderError::ValidationError(
+                    "iterations must be >= 1".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DepthSmoothing {
+    pub fn apply(&self, hgrid: &mut Hgrid) -> Result<BathymetryEditReport, BathymetryEditError> {
+        let original = positive_down_depths(hgrid);
+        let mut updated = original.clone();
+        match self.method {
+            SmoothingMethod::Laplacian => {
+                let neighbors = hgrid.node_neighbors();
+                for _ in 0..self.iterations {
+                    let previous = updated.clone();
+                    for (node_id, depth) in updated.iter_mut() {
+                        // Neighbors without a value are left out of the mean.
+                        let neighbor_depths: Vec<f64> = match neighbors.get(node_id) {
+                            Some(this_neighbors) => this_neighbors
+                                .iter()
+                                .filter_map(|neighbor_id| previous.get(neighbor_id).copied())
+                                .collect(),
+                            None => continue,
+                        };
+                        if neighbor_depths.is_empty() {
+                            continue;
+                        }
+                        let mean =
+                            neighbor_depths.iter().sum::<f64>() / neighbor_depths.len() as f64;
+                        *depth = (1. - self.weight) * previous[node_id] + self.weight * mean;
+                    }
+                }
+            }
+            SmoothingMethod::AreaWeighted => {
+                let node_elements = hgrid.node_elements();
+                let element_areas = hgrid.element_areas();
+                let elements = hgrid.elements().btree_map();
+                for _ in 0..self.iterations {
+                    let previous = updated.clone();
+                    for (node_id, depth) in updated.iter_mut() {
+                        let this_elements = match node_elements.get(node_id) {
+                            Some(this_elements) => this_elements,
+                            None => continue,
+                        };
+                        let mut weighted_sum = 0.;
+                        let mut total_area = 0.;
+                        for element_id in this_elements {
+                            let element_depths: Vec<f64> = elements[element_id]
+                                .iter()
+                                .filter_map(|element_node_id| {
+                                    previous.get(element_node_id).copied()
+                                })
+                                .collect();
+                            if element_depths.is_empty() {
+                                continue;
+                            }
+                            let element_mean =
+                                element_depths.iter().sum::<f64>() / element_depths.len() as f64;
+                            weighted_sum += element_areas[element_id] * element_mean;
+                            total_area += element_areas[element_id];
+                        }
+                        if total_area > 0. {
+                            *depth = (1. - self.weight) * previous[node_id]
+                                + self.weight * weighted_sum / total_area;
+                        }
+                    }
+                }
+            }
+        }
+        commit_depths(hgrid, &original, &updated)
+    }
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct SlopeLimiter {
+    rx0_max: f64,
+    #[builder(default = "100")]
+    max_iterations: usize,
+}
+
+impl SlopeLimiterBuilder {
+    pub fn validate(&self) -> Result<(), SlopeLimiterBuilderError> {
+        if let Some(rx0_max) = self.rx0_max {
+            if !(rx0_max > 0. && rx0_max < 1.) {
+                return Err(SlopeLimiterBuilderError::ValidationError(format!(
+                    "rx0_max must be in (0., 1.) but got {}",
+                    rx0_max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SlopeLimiter {
+    // Beckmann & Haidvogel (1993) rx0 = |h_i - h_j| / (h_i + h_j) is limited by deepening the
+    // shallower node of each offending edge until the target value is met everywhere.
+    pub fn apply(&self, hgrid: &mut Hgrid) -> Result<BathymetryEditReport, BathymetryEditError> {
+        let original = positive_down_depths(hgrid);
+        let mut updated = original.clone();
+        let neighbors = hgrid.node_neighbors();
+        let ratio = (1. - self.rx0_max) / (1. + self.rx0_max);
+        let mut converged = false;
+        for iteration in 0..self.max_iterations {
+            let mut modified = 0;
+            for (node_id, this_neighbors) in neighbors.iter() {
+                for neighbor_id in this_neighbors.iter().filter(|&id| id > node_id) {
+                    // Edges touching a node without a value are not limited.
+                    let (Some(&depth), Some(&neighbor_depth)) =
+                        (updated.get(node_id), updated.get(neighbor_id))
+                    else {
+                        continue;
+                    };
+                    if depth <= 0. || neighbor_depth <= 0. {
+                        continue;
+                    }
+                    let (shallow_id, deep_depth) = if depth < neighbor_depth {
+                        (*node_id, neighbor_depth)
+                    } else {
+                        (*neighbor_id, depth)
+                    };
+                    let target = deep_depth * ratio;
+                    if f64::min(depth, neighbor_depth) < target * (1. - f64::EPSILON) {
+                        updated.insert(shallow_id, target);
+                        modified += 1;
+                    }
+                }
+            }
+            log::debug!(
+                "Slope limiter iteration {} modified {} nodes.",
+                iteration + 1,
+                modified
+            );
+            if modified == 0 {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err(BathymetryEditError::SlopeLimiterDidNotConverge(
+                self.rx0_max,
+                self.max_iterations,
+            ));
+        }
+        commit_depths(hgrid, &original, &updated)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BathymetryEditError {
+    #[error(transparent)]
+    HgridEditError(#[from] HgridEditError),
+    #[error("Slope limiter did not reach rx0_max={0} after {1} iterations")]
+    SlopeLimiterDidNotConverge(f64, usize),
+}
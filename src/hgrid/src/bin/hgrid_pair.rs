@@ -0,0 +1,105 @@
+use clap::{Parser, Subcommand};
+use schismrs_hgrid::crs::GEOGRAPHIC_CRS;
+use schismrs_hgrid::hgrid::Hgrid;
+use schismrs_hgrid::reproject::{reproject, reproject_from, HgridPairCheckBuilder};
+use std::process::ExitCode;
+use std::{error::Error, path::PathBuf};
+
+const VERSION: &str = concat! {
+    env! {"CARGO_PKG_VERSION"},
+    "-",
+    env! {"VERGEN_GIT_DESCRIBE"}
+};
+
+#[derive(Parser, Debug)]
+#[command(author, about, long_about = None)]
+#[command(version = VERSION)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Reproject an hgrid, e.g. hgrid.gr3 into hgrid.ll or back.")]
+    Convert {
+        input_path: PathBuf,
+        output_path: PathBuf,
+        #[clap(
+            long,
+            help = "CRS of the input hgrid. Optional. Defaults to the CRS in the file header."
+        )]
+        from_crs: Option<String>,
+        #[clap(long, default_value = GEOGRAPHIC_CRS, help = "CRS of the output hgrid.")]
+        to_crs: String,
+    },
+    #[command(about = "Check that hgrid.gr3 and hgrid.ll describe the same mesh.")]
+    Check {
+        gr3_path: PathBuf,
+        ll_path: PathBuf,
+        #[clap(
+            long,
+            help = "CRS of hgrid.gr3. Optional. Defaults to the CRS in the file header."
+        )]
+        gr3_crs: Option<String>,
+        #[clap(
+            long,
+            default_value = "1e-2",
+            help = "Coordinate tolerance in hgrid.gr3 units."
+        )]
+        coordinate_tolerance: f64,
+        #[clap(long, default_value = "1e-6")]
+        depth_tolerance: f64,
+    },
+}
+
+fn entrypoint() -> Result<bool, Box<dyn Error>> {
+    pretty_env_logger::init();
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Convert {
+            input_path,
+            output_path,
+            from_crs,
+            to_crs,
+        } => {
+            let hgrid = Hgrid::try_from(&input_path)?;
+            let reprojected = match from_crs {
+                Some(from_crs) => reproject_from(&hgrid, &from_crs, &to_crs)?,
+                None => reproject(&hgrid, &to_crs)?,
+            };
+            reprojected.write(&output_path)?;
+            Ok(true)
+        }
+        Command::Check {
+            gr3_path,
+            ll_path,
+            gr3_crs,
+            coordinate_tolerance,
+            depth_tolerance,
+        } => {
+            let gr3 = Hgrid::try_from(&gr3_path)?;
+            let ll = Hgrid::try_from(&ll_path)?;
+            let mut builder = HgridPairCheckBuilder::default();
+            builder.coordinate_tolerance(coordinate_tolerance);
+            builder.depth_tolerance(depth_tolerance);
+            if let Some(gr3_crs) = gr3_crs {
+                builder.gr3_crs(gr3_crs);
+            }
+            let report = builder.build()?.check(&gr3, &ll)?;
+            println!("{}", report);
+            Ok(report.is_consistent())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match entrypoint() {
+        Err(e) => {
+            eprintln!("Error: {:?}: {}", e, e);
+            ExitCode::FAILURE
+        }
+        Ok(false) => ExitCode::FAILURE,
+        Ok(true) => ExitCode::SUCCESS,
+    }
+}
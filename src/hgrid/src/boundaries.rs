@@ -116,12 +116,27 @@ impl InteriorBoundaries {
 #[derive(Builder, Debug, Clone)]
 #[builder(setter(into))]
 pub struct Boundaries {
+    #[builder(default)]
     open: Option<OpenBoundaries>,
+    #[builder(default)]
     land: Option<LandBoundaries>,
+    #[builder(default)]
     interior: Option<InteriorBoundaries>,
 }
 
 impl Boundaries {
+    pub fn open(&self) -> Option<&OpenBoundaries> {
+        self.open.as_ref()
+    }
+
+    pub fn land(&self) -> Option<&LandBoundaries> {
+        self.land.as_ref()
+    }
+
+    pub fn interior(&self) -> Option<&InteriorBoundaries> {
+        self.interior.as_ref()
+    }
+
     pub fn to_boundary_type_map(&self) -> BTreeMap<BoundaryType, Vec<Vec<u32>>> {
         let mut btree_map = BTreeMap::new();
 
@@ -141,7 +156,7 @@ impl Boundaries {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Ord, PartialOrd)]
+#[derive(Hash, Eq, PartialEq, Debug, Ord, PartialOrd, Clone, Copy)]
 pub enum BoundaryType {
     Open,
     Land,
@@ -0,0 +1,319 @@
+use super::geometry::GeometryMethod;
+use super::hgrid::Hgrid;
+use super::prop::Prop;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CharacteristicLength {
+    // Diameter of the circle with the same area as the element.
+    #[default]
+    EquivalentDiameter,
+    SquareRootArea,
+    ShortestSide,
+}
+
+impl Hgrid {
+    pub fn element_characteristic_lengths(
+        &self,
+        method: CharacteristicLength,
+    ) -> BTreeMap<u32, f64> {
+        match method {
+            CharacteristicLength::EquivalentDiameter => self
+                .element_areas()
+                .into_iter()
+                .map(|(element_id, area)| (element_id, 2. * (area / PI).sqrt()))
+                .collect(),
+            CharacteristicLength::SquareRootArea => self
+                .element_areas()
+                .into_iter()
+                .map(|(element_id, area)| (element_id, area.sqrt()))
+                .collect(),
+            CharacteristicLength::ShortestSide => {
+                let metric = self.metric(GeometryMethod::Auto);
+                let nodes = self.nodes().btree_map();
+                self.elements()
+                    .btree_map()
+                    .into_iter()
+                    .map(|(element_id, node_ids)| {
+                        let n = node_ids.len();
+                        let shortest = (0..n)
+                            .map(|i| {
+                                let (a, _) = &nodes[&node_ids[i]];
+                                let (b, _) = &nodes[&node_ids[(i + 1) % n]];
+                                metric.distance([a[0], a[1]], [b[0], b[1]])
+                            })
+                            .fold(f64::INFINITY, f64::min);
+                        (element_id, shortest)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Mean positive-down depth over the nodes of every element.
+    pub fn element_depths(&self) -> BTreeMap<u32, f64> {
+        let depths = self.depths_btree_map();
+        self.elements()
+            .btree_map()
+            .into_iter()
+            .map(|(element_id, node_ids)| {
+                let depth = node_ids
+                    .iter()
+                    .map(|node_id| -depths.get(node_id).copied().unwrap_or(0.))
+                    .sum::<f64>()
+                    / node_ids.len() as f64;
+                (element_id, depth)
+            })
+            .collect()
+    }
+}
+
+// Courant numbers of a time step over the mesh. The barotropic number is
+// sqrt(g * h) * dt / dx, with h clipped to min_depth over dry and shallow elements; the
+// advective number is U * dt / dx for a velocity scale U.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct CflCheck {
+    dt: f64,
+    #[builder(setter(strip_option), default)]
+    velocity_scale: Option<f64>,
+    #[builder(default)]
+    characteristic_length: CharacteristicLength,
+    #[builder(default = "0.1")]
+    min_depth: f64,
+    #[builder(default = "9.81")]
+    gravity: f64,
+    // SCHISM recommends barotropic Courant numbers between 0.4 and 0.8.
+    #[builder(default = "0.4")]
+    min_cfl: f64,
+    #[builder(default = "0.8")]
+    max_cfl: f64,
+}
+
+impl CflCheckBuilder {
+    pub fn validate(&self) -> Result<(), CflCheckBuilderError> {
+        if let Some(dt) = self.dt {
+            if dt <= 0. {
+                return Err(CflCheckBuilderError::ValidationError(format!(
+                    "dt must be > 0. but got {}",
+                    dt
+                )));
+            }
+        }
+        if let Some(Some(velocity_scale)) = self.velocity_scale {
+            if velocity_scale < 0. {
+                return Err(CflCheckBuilderError::ValidationError(format!(
+                    "velocity_scale must be >= 0. but got {}",
+                    velocity_scale
+                )));
+            }
+        }
+        let min_cfl = self.min_cfl.unwrap_or(0.4);
+        let max_cfl = self.max_cfl.unwrap_or(0.8);
+        if min_cfl < 0. || min_cfl > max_cfl {
+            return Err(CflCheckBuilderError::ValidationError(format!(
+                "CFL range must satisfy 0 <= min_cfl <= max_cfl but got [{}, {}]",
+                min_cfl, max_cfl
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl CflCheck {
+    pub fn check(&self, hgrid: &Hgrid) -> CflReport {
+        let lengths = hgrid.element_characteristic_lengths(self.characteristic_length);
+        let depths = hgrid.element_depths();
+        let barotropic = lengths
+            .iter()
+            .map(|(element_id, length)| {
+                let depth = depths[element_id].max(self.min_depth);
+                (
+                    *element_id,
+                    (self.gravity * depth).sqrt() * self.dt / length,
+                )
+            })
+            .collect();
+        let advective = self.velocity_scale.map(|velocity_scale| {
+            lengths
+                .iter()
+                .map(|(element_id, length)| (*element_id, velocity_scale * self.dt / length))
+                .collect()
+        });
+        CflReport {
+            dt: self.dt,
+            lengths,
+            barotropic,
+            advective,
+            min_cfl: self.min_cfl,
+            max_cfl: self.max_cfl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CflSummary {
+    pub count: usize,
+    pub min: f64,
+    pub p05: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl CflSummary {
+    fn new(values: &BTreeMap<u32, f64>) -> Option<Self> {
+        let mut sorted: Vec<f64> = values.values().copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        let quantile = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+        Some(Self {
+            count: sorted.len(),
+            min: sorted[0],
+            p05: quantile(0.05),
+            median: quantile(0.5),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p95: quantile(0.95),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for CflSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {:.3}, p05 {:.3}, median {:.3}, mean {:.3}, p95 {:.3}, max {:.3}",
+            self.min, self.p05, self.median, self.mean, self.p95, self.max
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CflReport {
+    dt: f64,
+    lengths: BTreeMap<u32, f64>,
+    barotropic: BTreeMap<u32, f64>,
+    advective: Option<BTreeMap<u32, f64>>,
+    min_cfl: f64,
+    max_cfl: f64,
+}
+
+impl CflReport {
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    pub fn lengths(&self) -> &BTreeMap<u32, f64> {
+        &self.lengths
+    }
+
+    pub fn barotropic(&self) -> &BTreeMap<u32, f64> {
+        &self.barotropic
+    }
+
+    pub fn advective(&self) -> Option<&BTreeMap<u32, f64>> {
+        self.advective.as_ref()
+    }
+
+    pub fn barotropic_summary(&self) -> Option<CflSummary> {
+        CflSummary::new(&self.barotropic)
+    }
+
+    pub fn advective_summary(&self) -> Option<CflSummary> {
+        self.advective.as_ref().and_then(CflSummary::new)
+    }
+
+    // -1 where the barotropic CFL is below min_cfl, 1 where it is above max_cfl and 0 elsewhere.
+    pub fn flags(&self) -> BTreeMap<u32, i8> {
+        self.barotropic
+            .iter()
+            .map(|(&element_id, &cfl)| {
+                let flag = if cfl < self.min_cfl {
+                    -1
+                } else if cfl > self.max_cfl {
+                    1
+                } else {
+                    0
+                };
+                (element_id, flag)
+            })
+            .collect()
+    }
+
+    pub fn below_range(&self) -> Vec<u32> {
+        self.flagged(-1)
+    }
+
+    pub fn above_range(&self) -> Vec<u32> {
+        self.flagged(1)
+    }
+
+    fn flagged(&self, value: i8) -> Vec<u32> {
+        self.flags()
+            .into_iter()
+            .filter(|(_, flag)| *flag == value)
+            .map(|(element_id, _)| element_id)
+            .collect()
+    }
+
+    pub fn barotropic_prop(&self) -> Prop {
+        Prop::from(self.barotropic.clone())
+    }
+
+    pub fn flags_prop(&self) -> Prop {
+        Prop::from(
+            self.flags()
+                .into_iter()
+                .map(|(element_id, flag)| (element_id, flag as f64))
+                .collect::<BTreeMap<u32, f64>>(),
+        )
+    }
+
+    pub fn write_barotropic(&self, path: &Path) -> std::io::Result<()> {
+        self.barotropic_prop().write(path)
+    }
+
+    pub fn write_flags(&self, path: &Path) -> std::io::Result<()> {
+        self.flags_prop().write(path)
+    }
+}
+
+impl fmt::Display for CflReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "dt: {} s", self.dt)?;
+        if let Some(summary) = CflSummary::new(&self.lengths) {
+            writeln!(f, "element length: {}", summary)?;
+        }
+        if let Some(summary) = self.barotropic_summary() {
+            writeln!(f, "barotropic CFL: {}", summary)?;
+        }
+        if let Some(summary) = self.advective_summary() {
+            writeln!(f, "advective CFL: {}", summary)?;
+        }
+        let count = self.barotropic.len().max(1) as f64;
+        let below = self.below_range().len();
+        let above = self.above_range().len();
+        writeln!(
+            f,
+            "elements below {}: {} ({:.2}%)",
+            self.min_cfl,
+            below,
+            100. * below as f64 / count
+        )?;
+        write!(
+            f,
+            "elements above {}: {} ({:.2}%)",
+            self.max_cfl,
+            above,
+            100. * above as f64 / count
+        )
+    }
+}
@@ -0,0 +1,82 @@
+use super::editing::HgridEditError;
+use super::geometry::Polygon;
+use super::hgrid::Hgrid;
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ClipMode {
+    // Keeps the elements whose centroid is inside.
+    #[default]
+    Centroid,
+    // Keeps the elements with all of their nodes inside.
+    AllNodes,
+    // Keeps the elements with at least one node inside.
+    AnyNode,
+}
+
+impl Hgrid {
+    // Keeps the elements inside any of the polygons and rebuilds the boundaries from the new mesh
+    // boundary, keeping open the sides that were on an open boundary.
+    pub fn clip(&self, polygons: &[Polygon], mode: ClipMode) -> Result<Hgrid, ClipError> {
+        let nodes = self.nodes().btree_map();
+        let inside = |coord: &[f64; 2]| {
+            polygons
+                .iter()
+                .any(|polygon| polygon.contains(coord[0], coord[1]))
+        };
+        let centroids = self.element_centroids();
+        let mut editor = self.edit();
+        for (element_id, node_ids) in self.elements().btree_map() {
+            let mut node_inside = node_ids.iter().map(|node_id| {
+                let (coord, _) = &nodes[node_id];
+                inside(&[coord[0], coord[1]])
+            });
+            let keep = match mode {
+                ClipMode::Centroid => inside(&centroids[&element_id]),
+                ClipMode::AllNodes => node_inside.all(|is_inside| is_inside),
+                ClipMode::AnyNode => node_inside.any(|is_inside| is_inside),
+            };
+            if !keep {
+                editor.remove_element(element_id)?;
+            }
+        }
+        if editor.elements().is_empty() {
+            return Err(ClipError::Empty);
+        }
+        editor.remove_orphan_nodes();
+        let open_sides: BTreeSet<(u32, u32)> = self
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids())
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|segment| {
+                segment
+                    .windows(2)
+                    .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        editor.rebuild_boundaries(&|a, b| open_sides.contains(&(a.min(b), a.max(b))))?;
+        Ok(editor.commit()?)
+    }
+
+    pub fn clip_to_bbox(&self, bbox: [f64; 4], mode: ClipMode) -> Result<Hgrid, ClipError> {
+        let [xmin, ymin, xmax, ymax] = bbox;
+        let polygon = Polygon::new(
+            vec![[xmin, ymin], [xmax, ymin], [xmax, ymax], [xmin, ymax]],
+            Vec::new(),
+        )
+        .expect("A bounding box has 4 vertices");
+        self.clip(&[polygon], mode)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ClipError {
+    #[error("No element of the hgrid is inside the clipping polygons")]
+    Empty,
+    #[error(transparent)]
+    HgridEditError(#[from] HgridEditError),
+}
@@ -0,0 +1,98 @@
+use proj::Proj;
+use std::fmt;
+use thiserror::Error;
+
+pub const GEOGRAPHIC_CRS: &str = "EPSG:4326";
+
+// PROJ only keeps a proj-string definition for CRSs built from one, so the definition the CRS
+// was created from (EPSG code, WKT or proj-string) is kept next to it for writing and
+// for building transformations.
+pub struct Crs {
+    definition: String,
+    proj: Proj,
+    is_geographic: bool,
+}
+
+impl Crs {
+    pub fn new(definition: &str) -> Result<Self, CrsError> {
+        let definition = definition.trim().to_string();
+        let proj = Proj::new(&definition)
+            .map_err(|e| CrsError::InvalidCrs(definition.clone(), e.to_string()))?;
+        let is_geographic = probe_geographic(&definition);
+        Ok(Self {
+            definition,
+            proj,
+            is_geographic,
+        })
+    }
+
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    pub fn proj(&self) -> &Proj {
+        &self.proj
+    }
+
+    pub fn is_geographic(&self) -> bool {
+        self.is_geographic
+    }
+
+    pub fn to_geographic(&self) -> Result<Proj, CrsError> {
+        transformer(&self.definition, GEOGRAPHIC_CRS)
+    }
+
+    pub fn from_geographic(&self) -> Result<Proj, CrsError> {
+        transformer(GEOGRAPHIC_CRS, &self.definition)
+    }
+}
+
+impl fmt::Debug for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Crs")
+            .field("definition", &self.definition)
+            .field("is_geographic", &self.is_geographic)
+            .finish()
+    }
+}
+
+impl fmt::Display for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.definition)
+    }
+}
+
+// The proj crate does not expose the CRS type, so a CRS is taken as geographic when points far
+// apart come out of the transformation to EPSG:4326 where they went in, i.e. its coordinates
+// already are longitudes and latitudes in degrees.
+fn probe_geographic(definition: &str) -> bool {
+    let lowercase = definition.to_lowercase();
+    if lowercase.contains("proj=longlat") || lowercase.contains("proj=latlong") {
+        return true;
+    }
+    let Ok(transformer) = transformer(definition, GEOGRAPHIC_CRS) else {
+        return false;
+    };
+    [(-75., 35.), (120., -10.)].iter().all(|&(lon, lat)| {
+        transformer
+            .convert((lon, lat))
+            .is_ok_and(|(x, y): (f64, f64)| (x - lon).abs() < 0.1 && (y - lat).abs() < 0.1)
+    })
+}
+
+pub fn transformer(from: &str, to: &str) -> Result<Proj, CrsError> {
+    Proj::new_known_crs(from, to, None)
+        .map_err(|e| CrsError::TransformerError(from.to_string(), to.to_string(), e.to_string()))
+}
+
+#[derive(Error, Debug)]
+pub enum CrsError {
+    #[error("The hgrid has no CRS attached")]
+    MissingCrs,
+    #[error("Could not create a CRS from {0}: {1}")]
+    InvalidCrs(String, String),
+    #[error("Could not create a transformer from {0} to {1}: {2}")]
+    TransformerError(String, String, String),
+    #[error("Could not transform node {0}: {1}")]
+    ConversionError(u32, String),
+}
@@ -0,0 +1,184 @@
+use super::geometry::GeometryMethod;
+use super::hgrid::Hgrid;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DistanceMethod {
+    #[default]
+    AlongMesh,
+    StraightLine,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BoundarySelection {
+    #[default]
+    AllOpen,
+    Open(usize),
+    Nodes(Vec<u32>),
+}
+
+impl BoundarySelection {
+    pub(crate) fn segments(&self, hgrid: &Hgrid) -> Result<Vec<Vec<u32>>, DistanceError> {
+        let open_boundaries = hgrid
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids())
+            .unwrap_or_default();
+        let segments = match self {
+            BoundarySelection::AllOpen => open_boundaries,
+            BoundarySelection::Open(index) => match open_boundaries.get(*index) {
+                Some(segment) => vec![segment.clone()],
+                None => {
+                    return Err(DistanceError::OpenBoundaryIndexOutOfRange(
+                        *index,
+                        open_boundaries.len(),
+                    ))
+                }
+            },
+            BoundarySelection::Nodes(node_ids) => {
+                let nodes = hgrid.nodes().btree_map();
+                if let Some(node_id) = node_ids.iter().find(|id| !nodes.contains_key(id)) {
+                    return Err(DistanceError::UnknownNode(*node_id));
+                }
+                vec![node_ids.clone()]
+            }
+        };
+        if segments.iter().all(|segment| segment.is_empty()) {
+            return Err(DistanceError::EmptyBoundarySelection);
+        }
+        Ok(segments)
+    }
+}
+
+#[derive(PartialEq)]
+struct State {
+    cost: f64,
+    node_id: u32,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Dijkstra along element sides from the source nodes, with the cost of each side given by
+// side_cost. The search stops as soon as target is settled, and the returned maps only hold the
+// nodes that were reached: their cost and the node they were reached from.
+pub(crate) fn shortest_paths<F>(
+    hgrid: &Hgrid,
+    neighbors: &BTreeMap<u32, BTreeSet<u32>>,
+    sources: &[u32],
+    target: Option<u32>,
+    side_cost: F,
+) -> (BTreeMap<u32, f64>, BTreeMap<u32, u32>)
+where
+    F: Fn(&[f64], &[f64]) -> f64,
+{
+    let nodes = hgrid.nodes().btree_map();
+    let mut costs: BTreeMap<u32, f64> = BTreeMap::new();
+    let mut previous: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut heap = BinaryHeap::new();
+    for &node_id in sources {
+        costs.insert(node_id, 0.);
+        heap.push(State { cost: 0., node_id });
+    }
+    while let Some(State { cost, node_id }) = heap.pop() {
+        if Some(node_id) == target {
+            break;
+        }
+        if cost > costs.get(&node_id).copied().unwrap_or(f64::INFINITY) {
+            continue;
+        }
+        let (coord, _) = &nodes[&node_id];
+        for &next_node_id in neighbors.get(&node_id).into_iter().flatten() {
+            let (next_coord, _) = &nodes[&next_node_id];
+            let next_cost = cost + side_cost(coord, next_coord);
+            if next_cost < costs.get(&next_node_id).copied().unwrap_or(f64::INFINITY) {
+                costs.insert(next_node_id, next_cost);
+                previous.insert(next_node_id, node_id);
+                heap.push(State {
+                    cost: next_cost,
+                    node_id: next_node_id,
+                });
+            }
+        }
+    }
+    (costs, previous)
+}
+
+pub(crate) fn along_mesh_distance(hgrid: &Hgrid, sources: &[u32]) -> BTreeMap<u32, f64> {
+    let metric = hgrid.metric(GeometryMethod::Auto);
+    let (costs, _previous) =
+        shortest_paths(hgrid, &hgrid.node_neighbors(), sources, None, |a, b| {
+            metric.distance([a[0], a[1]], [b[0], b[1]])
+        });
+    hgrid
+        .nodes()
+        .btree_map()
+        .keys()
+        .map(|node_id| {
+            (
+                *node_id,
+                costs.get(node_id).copied().unwrap_or(f64::INFINITY),
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn straight_line_distance(hgrid: &Hgrid, segments: &[Vec<u32>]) -> BTreeMap<u32, f64> {
+    let metric = hgrid.metric(GeometryMethod::Auto);
+    let nodes = hgrid.nodes().btree_map();
+    let polylines: Vec<Vec<[f64; 2]>> = segments
+        .iter()
+        .map(|segment| {
+            segment
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect()
+        })
+        .collect();
+    nodes
+        .iter()
+        .map(|(&node_id, (coord, _))| {
+            let point = [coord[0], coord[1]];
+            let mut distance = f64::INFINITY;
+            for polyline in polylines.iter() {
+                if polyline.len() == 1 {
+                    distance = distance.min(metric.point_segment_distance(
+                        point,
+                        polyline[0],
+                        polyline[0],
+                    ));
+                }
+                for pair in polyline.windows(2) {
+                    distance = distance.min(metric.point_segment_distance(point, pair[0], pair[1]));
+                }
+            }
+            (node_id, distance)
+        })
+        .collect()
+}
+
+#[derive(Error, Debug)]
+pub enum DistanceError {
+    #[error("Open boundary index {0} is out of range, the hgrid has {1} open boundaries")]
+    OpenBoundaryIndexOutOfRange(usize, usize),
+    #[error("Node {0} is not in the hgrid")]
+    UnknownNode(u32),
+    #[error("The boundary selection does not contain any node")]
+    EmptyBoundarySelection,
+}
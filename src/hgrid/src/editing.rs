@@ -0,0 +1,794 @@
+use super::boundaries::BoundaryType;
+use super::crs::Crs;
+use super::geometry::signed_polygon_area;
+use super::hgrid::{Hgrid, HgridTryFromError};
+use super::validation::MeshIssue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use thiserror::Error;
+
+// Batches edits on owned copies of the hgrid tables; nothing is validated against the Arc<Nodes>
+// shared by elements and boundaries until commit() rebuilds them all at once.
+#[derive(Debug, Clone)]
+pub struct HgridEditor {
+    nodes: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+    crs: Option<Arc<Crs>>,
+    elements: BTreeMap<u32, Vec<u32>>,
+    boundaries: BTreeMap<BoundaryType, Vec<Vec<u32>>>,
+    description: Option<String>,
+}
+
+impl HgridEditor {
+    pub fn new(hgrid: &Hgrid) -> Self {
+        Self {
+            nodes: hgrid.nodes().btree_map(),
+            crs: hgrid.crs(),
+            elements: hgrid.elements().btree_map(),
+            boundaries: hgrid
+                .boundaries()
+                .map(|boundaries| boundaries.to_boundary_type_map())
+                .unwrap_or_default(),
+            description: hgrid.description().cloned(),
+        }
+    }
+
+    pub fn nodes(&self) -> &BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)> {
+        &self.nodes
+    }
+
+    pub fn elements(&self) -> &BTreeMap<u32, Vec<u32>> {
+        &self.elements
+    }
+
+    pub fn boundaries(&self, boundary_type: BoundaryType) -> &[Vec<u32>] {
+        self.boundaries
+            .get(&boundary_type)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) -> &mut Self {
+        self.description = description;
+        self
+    }
+
+    // Depths use the same sign as Hgrid::depths(), i.e. negative below the datum.
+    pub fn set_depth(&mut self, node_id: u32, depth: f64) -> Result<&mut Self, HgridEditError> {
+        let (_coord, values) = self
+            .nodes
+            .get_mut(&node_id)
+            .ok_or(HgridEditError::UnknownNode(node_id))?;
+        match values {
+            Some(values) if !values.is_empty() => values[0] = depth,
+            _ => *values = Some(vec![depth]),
+        }
+        Ok(self)
+    }
+
+    pub fn set_depths(&mut self, depths: &BTreeMap<u32, f64>) -> Result<&mut Self, HgridEditError> {
+        for (&node_id, &depth) in depths.iter() {
+            self.set_depth(node_id, depth)?;
+        }
+        Ok(self)
+    }
+
+    pub fn set_node_values(
+        &mut self,
+        node_id: u32,
+        values: Option<Vec<f64>>,
+    ) -> Result<&mut Self, HgridEditError> {
+        let (_coord, this_values) = self
+            .nodes
+            .get_mut(&node_id)
+            .ok_or(HgridEditError::UnknownNode(node_id))?;
+        *this_values = values;
+        Ok(self)
+    }
+
+    pub fn move_node(
+        &mut self,
+        node_id: u32,
+        coord: [f64; 2],
+    ) -> Result<&mut Self, HgridEditError> {
+        let (this_coord, _values) = self
+            .nodes
+            .get_mut(&node_id)
+            .ok_or(HgridEditError::UnknownNode(node_id))?;
+        *this_coord = coord.to_vec();
+        Ok(self)
+    }
+
+    pub fn next_node_id(&self) -> u32 {
+        self.nodes
+            .keys()
+            .next_back()
+            .map_or(1, |node_id| node_id + 1)
+    }
+
+    pub fn next_element_id(&self) -> u32 {
+        self.elements
+            .keys()
+            .next_back()
+            .map_or(1, |element_id| element_id + 1)
+    }
+
+    pub fn add_node(&mut self, coord: [f64; 2], values: Option<Vec<f64>>) -> u32 {
+        let node_id = self.next_node_id();
+        self.nodes.insert(node_id, (coord.to_vec(), values));
+        node_id
+    }
+
+    pub fn insert_node(
+        &mut self,
+        node_id: u32,
+        coord: [f64; 2],
+        values: Option<Vec<f64>>,
+    ) -> Result<&mut Self, HgridEditError> {
+        if self.nodes.contains_key(&node_id) {
+            return Err(HgridEditError::DuplicateNode(node_id));
+        }
+        self.nodes.insert(node_id, (coord.to_vec(), values));
+        Ok(self)
+    }
+
+    // Removes the node together with every element that uses it, and splits the boundary
+    // segments through it. Returns the ids of the removed elements.
+    pub fn remove_node(&mut self, node_id: u32) -> Result<Vec<u32>, HgridEditError> {
+        if self.nodes.remove(&node_id).is_none() {
+            return Err(HgridEditError::UnknownNode(node_id));
+        }
+        let removed_elements: Vec<u32> = self
+            .elements
+            .iter()
+            .filter(|(_, node_ids)| node_ids.contains(&node_id))
+            .map(|(&element_id, _)| element_id)
+            .collect();
+        for element_id in removed_elements.iter() {
+            self.elements.remove(element_id);
+        }
+        drop_boundary_nodes(&mut self.boundaries, &|this_node_id| {
+            this_node_id == node_id
+        });
+        self.drop_detached_boundary_sides();
+        Ok(removed_elements)
+    }
+
+    pub fn remove_orphan_nodes(&mut self) -> Vec<u32> {
+        let used: BTreeSet<u32> = self.elements.values().flatten().copied().collect();
+        let orphans: Vec<u32> = self
+            .nodes
+            .keys()
+            .filter(|node_id| !used.contains(node_id))
+            .copied()
+            .collect();
+        for node_id in orphans.iter() {
+            self.nodes.remove(node_id);
+        }
+        drop_boundary_nodes(&mut self.boundaries, &|node_id| !used.contains(&node_id));
+        orphans
+    }
+
+    pub fn add_element(&mut self, node_ids: Vec<u32>) -> Result<u32, HgridEditError> {
+        let element_id = self.next_element_id();
+        self.insert_element(element_id, node_ids)?;
+        Ok(element_id)
+    }
+
+    pub fn insert_element(
+        &mut self,
+        element_id: u32,
+        node_ids: Vec<u32>,
+    ) -> Result<&mut Self, HgridEditError> {
+        if self.elements.contains_key(&element_id) {
+            return Err(HgridEditError::DuplicateElement(element_id));
+        }
+        self.check_element(element_id, &node_ids)?;
+        self.elements.insert(element_id, node_ids);
+        Ok(self)
+    }
+
+    pub fn remove_element(&mut self, element_id: u32) -> Result<Vec<u32>, HgridEditError> {
+        let node_ids = self
+            .elements
+            .remove(&element_id)
+            .ok_or(HgridEditError::UnknownElement(element_id))?;
+        self.drop_detached_boundary_sides();
+        Ok(node_ids)
+    }
+
+    // Splits the boundary segments at the sides left without an element, e.g. the boundary sides
+    // of a removed element.
+    fn drop_detached_boundary_sides(&mut self) {
+        let sides: BTreeSet<(u32, u32)> = self
+            .elements
+            .values()
+            .flat_map(|node_ids| {
+                (0..node_ids.len()).map(|i| {
+                    let (a, b) = (node_ids[i], node_ids[(i + 1) % node_ids.len()]);
+                    (a.min(b), a.max(b))
+                })
+            })
+            .collect();
+        drop_boundary_sides(&mut self.boundaries, &|a, b| {
+            !sides.contains(&(a.min(b), a.max(b)))
+        });
+    }
+
+    fn check_element(&self, element_id: u32, node_ids: &[u32]) -> Result<(), HgridEditError> {
+        if node_ids.len() != 3 && node_ids.len() != 4 {
+            return Err(HgridEditError::InvalidElement(
+                element_id,
+                format!("expected 3 or 4 nodes but got {}", node_ids.len()),
+            ));
+        }
+        let distinct: BTreeSet<&u32> = node_ids.iter().collect();
+        if distinct.len() != node_ids.len() {
+            return Err(HgridEditError::InvalidElement(
+                element_id,
+                format!("repeated node ids in {:?}", node_ids),
+            ));
+        }
+        if let Some(node_id) = node_ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(HgridEditError::UnknownNode(*node_id));
+        }
+        Ok(())
+    }
+
+    fn segments_mut(
+        &mut self,
+        boundary_type: BoundaryType,
+        index: usize,
+    ) -> Result<&mut Vec<Vec<u32>>, HgridEditError> {
+        let segments = self.boundaries.entry(boundary_type).or_default();
+        if index >= segments.len() {
+            return Err(HgridEditError::BoundaryIndexOutOfRange(
+                boundary_type,
+                index,
+                segments.len(),
+            ));
+        }
+        Ok(segments)
+    }
+
+    pub fn add_boundary(
+        &mut self,
+        boundary_type: BoundaryType,
+        node_ids: Vec<u32>,
+    ) -> Result<usize, HgridEditError> {
+        if let Some(node_id) = node_ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(HgridEditError::UnknownNode(*node_id));
+        }
+        let segments = self.boundaries.entry(boundary_type).or_default();
+        if node_ids.len() < 2 && boundary_type != BoundaryType::Interior {
+            return Err(HgridEditError::DegenerateBoundary(
+                boundary_type,
+                segments.len(),
+            ));
+        }
+        segments.push(node_ids);
+        Ok(segments.len() - 1)
+    }
+
+    pub fn remove_boundary(
+        &mut self,
+        boundary_type: BoundaryType,
+        index: usize,
+    ) -> Result<Vec<u32>, HgridEditError> {
+        Ok(self.segments_mut(boundary_type, index)?.remove(index))
+    }
+
+    // Splits a segment at node_id, which ends the first part and starts the second one, as
+    // consecutive SCHISM boundary segments share their end nodes.
+    pub fn split_boundary(
+        &mut self,
+        boundary_type: BoundaryType,
+        index: usize,
+        node_id: u32,
+    ) -> Result<&mut Self, HgridEditError> {
+        let segments = self.segments_mut(boundary_type, index)?;
+        let position = segments[index]
+            .iter()
+            .position(|&this_node_id| this_node_id == node_id)
+            .ok_or(HgridEditError::NodeNotInBoundary(
+                node_id,
+                boundary_type,
+                index,
+            ))?;
+        if position == 0 || position == segments[index].len() - 1 {
+            return Err(HgridEditError::NodeNotInBoundary(
+                node_id,
+                boundary_type,
+                index,
+            ));
+        }
+        let tail = segments[index][position..].to_vec();
+        segments[index].truncate(position + 1);
+        segments.insert(index + 1, tail);
+        Ok(self)
+    }
+
+    // Appends segment `second` to segment `first`; the end of `first` must be the start of
+    // `second` or be connected to it by an element side.
+    pub fn merge_boundaries(
+        &mut self,
+        boundary_type: BoundaryType,
+        first: usize,
+        second: usize,
+    ) -> Result<&mut Self, HgridEditError> {
+        self.segments_mut(boundary_type, first)?;
+        let segments = self.segments_mut(boundary_type, second)?;
+        if first == second {
+            return Err(HgridEditError::NonAdjacentBoundaries(
+                boundary_type,
+                first,
+                second,
+            ));
+        }
+        let (Some(&last), Some(&head)) = (segments[first].last(), segments[second].first()) else {
+            let index = if segments[first].is_empty() {
+                first
+            } else {
+                second
+            };
+            return Err(HgridEditError::DegenerateBoundary(boundary_type, index));
+        };
+        let shares_node = last == head;
+        let shares_side = self.elements.values().any(|node_ids| {
+            (0..node_ids.len()).any(|i| {
+                let a = node_ids[i];
+                let b = node_ids[(i + 1) % node_ids.len()];
+                (a == last && b == head) || (a == head && b == last)
+            })
+        });
+        if !shares_node && !shares_side {
+            return Err(HgridEditError::NonAdjacentBoundaries(
+                boundary_type,
+                first,
+                second,
+            ));
+        }
+        let segments = self.boundaries.get_mut(&boundary_type).unwrap();
+        let mut tail = segments.remove(second);
+        if shares_node {
+            tail.remove(0);
+        }
+        let first = if second < first { first - 1 } else { first };
+        segments[first].extend(tail);
+        Ok(self)
+    }
+
+    pub fn retype_boundary(
+        &mut self,
+        boundary_type: BoundaryType,
+        index: usize,
+        new_boundary_type: BoundaryType,
+    ) -> Result<usize, HgridEditError> {
+        let length = self.segments_mut(boundary_type, index)?[index].len();
+        if length < 2 && new_boundary_type != BoundaryType::Interior {
+            return Err(HgridEditError::DegenerateBoundary(boundary_type, index));
+        }
+        let node_ids = self.remove_boundary(boundary_type, index)?;
+        self.add_boundary(new_boundary_type, node_ids)
+    }
+
+    pub fn reverse_boundary(
+        &mut self,
+        boundary_type: BoundaryType,
+        index: usize,
+    ) -> Result<&mut Self, HgridEditError> {
+        self.segments_mut(boundary_type, index)?[index].reverse();
+        Ok(self)
+    }
+
+    pub fn reorder_boundaries(
+        &mut self,
+        boundary_type: BoundaryType,
+        order: &[usize],
+    ) -> Result<&mut Self, HgridEditError> {
+        let segments = self.boundaries.entry(boundary_type).or_default();
+        let distinct: BTreeSet<&usize> = order.iter().collect();
+        if order.len() != segments.len()
+            || distinct.len() != order.len()
+            || order.iter().any(|&index| index >= segments.len())
+        {
+            return Err(HgridEditError::InvalidBoundaryOrder(
+                boundary_type,
+                order.to_vec(),
+            ));
+        }
+        let reordered = order.iter().map(|&index| segments[index].clone()).collect();
+        *segments = reordered;
+        Ok(self)
+    }
+
+    // Boundary rings traced from the sides that belong to a single element, each oriented so the
+    // domain lies on its left. The outer ring therefore runs counter-clockwise and comes first.
+    pub fn boundary_rings(&self) -> Vec<Vec<u32>> {
+        let mut side_count: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+        let mut oriented_sides = Vec::new();
+        for node_ids in self.elements.values() {
+            let coords: Vec<[f64; 2]> = node_ids
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &self.nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect();
+            let is_ccw = signed_polygon_area(&coords) > 0.;
+            for i in 0..node_ids.len() {
+                let a = node_ids[i];
+                let b = node_ids[(i + 1) % node_ids.len()];
+                *side_count.entry((a.min(b), a.max(b))).or_default() += 1;
+                oriented_sides.push(if is_ccw { (a, b) } else { (b, a) });
+            }
+        }
+        let mut next: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (a, b) in oriented_sides {
+            if side_count[&(a.min(b), a.max(b))] == 1 {
+                next.entry(a).or_default().push(b);
+            }
+        }
+        let mut rings = Vec::new();
+        while let Some((&start, _)) = next.iter().find(|(_, targets)| !targets.is_empty()) {
+            let mut ring = vec![start];
+            let mut current = start;
+            while let Some(following) = next.get_mut(&current).and_then(|targets| targets.pop()) {
+                if following == start {
+                    break;
+                }
+                ring.push(following);
+                current = following;
+            }
+            rings.push(ring);
+        }
+        let ring_area = |ring: &Vec<u32>| {
+            let coords: Vec<[f64; 2]> = ring
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &self.nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect();
+            signed_polygon_area(&coords)
+        };
+        rings.sort_by(|a, b| ring_area(b).total_cmp(&ring_area(a)));
+        rings
+    }
+
+    // Replaces all boundaries with ones traced from the mesh boundary: sides of the outer rings
+    // for which is_open holds form open segments, the rest land segments, and every inner ring
+    // an island.
+    pub fn rebuild_boundaries(
+        &mut self,
+        is_open: &dyn Fn(u32, u32) -> bool,
+    ) -> Result<&mut Self, HgridEditError> {
+        self.boundaries.clear();
+        for ring in self.boundary_rings() {
+            let coords: Vec<[f64; 2]> = ring
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &self.nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect();
+            if signed_polygon_area(&coords) > 0. {
+                for (boundary_type, segment) in split_ring(&ring, is_open) {
+                    self.add_boundary(boundary_type, segment)?;
+                }
+            } else {
+                self.add_boundary(BoundaryType::Interior, ring)?;
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn nearest_boundary_node(&self, coord: [f64; 2]) -> Option<u32> {
+        self.boundary_rings()
+            .into_iter()
+            .flatten()
+            .map(|node_id| {
+                let (this_coord, _) = &self.nodes[&node_id];
+                let distance =
+                    (this_coord[0] - coord[0]).powi(2) + (this_coord[1] - coord[1]).powi(2);
+                (node_id, distance)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(node_id, _)| node_id)
+    }
+
+    // Reassigns the stretch of boundary ring that runs from start_node to end_node with the domain
+    // on its left to a new open boundary segment, splitting the land segments that covered it. An
+    // island ring that the stretch runs along is left as a land segment around the rest of the
+    // island. Returns the index of the new open segment.
+    pub fn open_boundary_between(
+        &mut self,
+        start_node: u32,
+        end_node: u32,
+    ) -> Result<usize, HgridEditError> {
+        let rings = self.boundary_rings();
+        let ring = rings
+            .iter()
+            .find(|ring| ring.contains(&start_node) && ring.contains(&end_node))
+            .ok_or(HgridEditError::NotOnSameBoundaryRing(start_node, end_node))?;
+        if start_node == end_node {
+            return Err(HgridEditError::NotOnSameBoundaryRing(start_node, end_node));
+        }
+        let start = ring
+            .iter()
+            .position(|&node_id| node_id == start_node)
+            .unwrap();
+        let mut stretch = vec![start_node];
+        let mut position = start;
+        while stretch.last() != Some(&end_node) {
+            position = (position + 1) % ring.len();
+            stretch.push(ring[position]);
+        }
+        let stretch_sides: BTreeSet<(u32, u32)> = stretch
+            .windows(2)
+            .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+            .collect();
+        let overlaps_open = self.boundaries(BoundaryType::Open).iter().any(|segment| {
+            segment
+                .windows(2)
+                .any(|pair| stretch_sides.contains(&(pair[0].min(pair[1]), pair[0].max(pair[1]))))
+        });
+        if overlaps_open {
+            return Err(HgridEditError::OverlapsOpenBoundary(start_node, end_node));
+        }
+        drop_boundary_sides(&mut self.boundaries, &|a, b| {
+            stretch_sides.contains(&(a.min(b), a.max(b)))
+        });
+        self.add_boundary(BoundaryType::Open, stretch)
+    }
+
+    pub fn open_boundary_between_points(
+        &mut self,
+        start: [f64; 2],
+        end: [f64; 2],
+    ) -> Result<usize, HgridEditError> {
+        let start_node = self
+            .nearest_boundary_node(start)
+            .ok_or(HgridEditError::NoBoundaryRing)?;
+        let end_node = self
+            .nearest_boundary_node(end)
+            .ok_or(HgridEditError::NoBoundaryRing)?;
+        self.open_boundary_between(start_node, end_node)
+    }
+
+    pub fn commit(self) -> Result<Hgrid, HgridEditError> {
+        for (&element_id, node_ids) in self.elements.iter() {
+            self.check_element(element_id, node_ids)?;
+        }
+        for (&boundary_type, segments) in self.boundaries.iter() {
+            for (index, segment) in segments.iter().enumerate() {
+                if segment.len() < 2 && boundary_type != BoundaryType::Interior {
+                    return Err(HgridEditError::DegenerateBoundary(boundary_type, index));
+                }
+            }
+        }
+        let hgrid = Hgrid::from_parts(
+            self.nodes,
+            self.crs,
+            self.elements,
+            self.boundaries,
+            self.description,
+        )?;
+        if let Some(issue) = hgrid.misplaced_boundary_sides().into_iter().next() {
+            return Err(HgridEditError::InvalidBoundary(issue));
+        }
+        Ok(hgrid)
+    }
+}
+
+// Splits a counter-clockwise boundary ring into consecutive open and land segments sharing their
+// end nodes, or returns the whole ring closed on its first node when all sides are of one kind.
+fn split_ring(ring: &[u32], is_open: &dyn Fn(u32, u32) -> bool) -> Vec<(BoundaryType, Vec<u32>)> {
+    let n = ring.len();
+    let boundary_type = |open: bool| match open {
+        true => BoundaryType::Open,
+        false => BoundaryType::Land,
+    };
+    let side_is_open: Vec<bool> = (0..n)
+        .map(|i| is_open(ring[i], ring[(i + 1) % n]))
+        .collect();
+    let Some(start) = (0..n).find(|&i| side_is_open[i] != side_is_open[(i + n - 1) % n]) else {
+        let mut closed = ring.to_vec();
+        closed.push(ring[0]);
+        return vec![(boundary_type(side_is_open[0]), closed)];
+    };
+    let mut segments = Vec::new();
+    let mut segment = vec![ring[start]];
+    for offset in 0..n {
+        let i = (start + offset) % n;
+        let next_i = (i + 1) % n;
+        segment.push(ring[next_i]);
+        if offset == n - 1 || side_is_open[next_i] != side_is_open[i] {
+            segments.push((boundary_type(side_is_open[i]), segment));
+            segment = vec![ring[next_i]];
+        }
+    }
+    segments
+}
+
+// Splits the boundary segments at the removed nodes, since the nodes on either side of a removed
+// node do not share a mesh side, and drops the pieces left with fewer than 2 nodes. A broken
+// interior ring is no longer closed, so it is rotated to start at a removed node, keeping the
+// piece that wraps around its end in one piece, and its pieces become land boundaries.
+fn drop_boundary_nodes(
+    boundaries: &mut BTreeMap<BoundaryType, Vec<Vec<u32>>>,
+    is_removed: &dyn Fn(u32) -> bool,
+) {
+    let mut broken_rings = Vec::new();
+    for (&boundary_type, segments) in boundaries.iter_mut() {
+        let mut kept = Vec::new();
+        for mut segment in segments.drain(..) {
+            let Some(first_removed) = segment.iter().position(|&node_id| is_removed(node_id))
+            else {
+                kept.push(segment);
+                continue;
+            };
+            let pieces = match boundary_type {
+                BoundaryType::Interior => {
+                    if segment.len() > 1 && segment.first() == segment.last() {
+                        segment.pop();
+                    }
+                    segment.rotate_left(first_removed);
+                    &mut broken_rings
+                }
+                _ => &mut kept,
+            };
+            pieces.extend(
+                segment
+                    .split(|&node_id| is_removed(node_id))
+                    .filter(|piece| piece.len() >= 2)
+                    .map(<[u32]>::to_vec),
+            );
+        }
+        *segments = kept;
+    }
+    if !broken_rings.is_empty() {
+        boundaries
+            .entry(BoundaryType::Land)
+            .or_default()
+            .extend(broken_rings);
+    }
+    boundaries.retain(|_, segments| !segments.is_empty());
+}
+
+// Splits the boundary segments between the nodes of the sides for which is_detached holds,
+// dropping pieces left with fewer than 2 nodes. Interior rings and closed segments are opened at
+// a detached side first, so no piece ends at their old closing node. Interior rings broken this
+// way become land boundaries, as in drop_boundary_nodes.
+fn drop_boundary_sides(
+    boundaries: &mut BTreeMap<BoundaryType, Vec<Vec<u32>>>,
+    is_detached: &dyn Fn(u32, u32) -> bool,
+) {
+    let mut broken_rings = Vec::new();
+    for (&boundary_type, segments) in boundaries.iter_mut() {
+        let mut kept = Vec::new();
+        for segment in segments.drain(..) {
+            let mut nodes_ids = segment.clone();
+            let is_closed = nodes_ids.len() > 2 && nodes_ids.first() == nodes_ids.last();
+            if boundary_type == BoundaryType::Interior || is_closed {
+                if is_closed {
+                    nodes_ids.pop();
+                }
+                let n = nodes_ids.len();
+                let detached = match n > 1 {
+                    true => (0..n).find(|&i| is_detached(nodes_ids[i], nodes_ids[(i + 1) % n])),
+                    false => None,
+                };
+                match detached {
+                    Some(i) => {
+                        nodes_ids.rotate_left((i + 1) % n);
+                        let pieces = split_at_sides(&nodes_ids, is_detached);
+                        match boundary_type {
+                            BoundaryType::Interior => broken_rings.extend(pieces),
+                            _ => kept.extend(pieces),
+                        }
+                    }
+                    None => kept.push(segment),
+                }
+            } else if nodes_ids
+                .windows(2)
+                .any(|pair| is_detached(pair[0], pair[1]))
+            {
+                kept.extend(split_at_sides(&nodes_ids, is_detached));
+            } else {
+                kept.push(segment);
+            }
+        }
+        *segments = kept;
+    }
+    if !broken_rings.is_empty() {
+        boundaries
+            .entry(BoundaryType::Land)
+            .or_default()
+            .extend(broken_rings);
+    }
+    boundaries.retain(|_, segments| !segments.is_empty());
+}
+
+fn split_at_sides(nodes_ids: &[u32], is_split: &dyn Fn(u32, u32) -> bool) -> Vec<Vec<u32>> {
+    let mut pieces = Vec::new();
+    let mut piece = nodes_ids[..1].to_vec();
+    for pair in nodes_ids.windows(2) {
+        if is_split(pair[0], pair[1]) {
+            pieces.push(std::mem::replace(&mut piece, vec![pair[1]]));
+        } else {
+            piece.push(pair[1]);
+        }
+    }
+    pieces.push(piece);
+    pieces.retain(|piece| piece.len() >= 2);
+    pieces
+}
+
+impl Hgrid {
+    pub fn edit(&self) -> HgridEditor {
+        HgridEditor::new(self)
+    }
+
+    pub fn set_depths(&mut self, depths: &BTreeMap<u32, f64>) -> Result<(), HgridEditError> {
+        let mut editor = self.edit();
+        editor.set_depths(depths)?;
+        *self = editor.commit()?;
+        Ok(())
+    }
+
+    pub fn open_boundary_between(
+        &mut self,
+        start_node: u32,
+        end_node: u32,
+    ) -> Result<usize, HgridEditError> {
+        let mut editor = self.edit();
+        let index = editor.open_boundary_between(start_node, end_node)?;
+        *self = editor.commit()?;
+        Ok(index)
+    }
+
+    pub fn set_node_values(
+        &mut self,
+        node_id: u32,
+        values: Option<Vec<f64>>,
+    ) -> Result<(), HgridEditError> {
+        let mut editor = self.edit();
+        editor.set_node_values(node_id, values)?;
+        *self = editor.commit()?;
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HgridEditError {
+    #[error("Node {0} is not in the hgrid")]
+    UnknownNode(u32),
+    #[error("Element {0} is not in the hgrid")]
+    UnknownElement(u32),
+    #[error("Node {0} already exists")]
+    DuplicateNode(u32),
+    #[error("Element {0} already exists")]
+    DuplicateElement(u32),
+    #[error("Element {0} is invalid: {1}")]
+    InvalidElement(u32, String),
+    #[error("{0:?} boundary index {1} is out of range ({2} segments)")]
+    BoundaryIndexOutOfRange(BoundaryType, usize, usize),
+    #[error("Node {0} is not an interior node of {1:?} boundary {2}")]
+    NodeNotInBoundary(u32, BoundaryType, usize),
+    #[error("{0:?} boundaries {1} and {2} are not adjacent")]
+    NonAdjacentBoundaries(BoundaryType, usize, usize),
+    #[error("{1:?} is not a permutation of the {0:?} boundaries")]
+    InvalidBoundaryOrder(BoundaryType, Vec<usize>),
+    #[error("Nodes {0} and {1} are not two distinct nodes of the same boundary ring")]
+    NotOnSameBoundaryRing(u32, u32),
+    #[error("The boundary stretch from node {0} to node {1} overlaps an open boundary")]
+    OverlapsOpenBoundary(u32, u32),
+    #[error("The hgrid has no boundary ring")]
+    NoBoundaryRing,
+    #[error("{0:?} boundary {1} has less than 2 nodes")]
+    DegenerateBoundary(BoundaryType, usize),
+    #[error("{0}")]
+    InvalidBoundary(MeshIssue),
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+}
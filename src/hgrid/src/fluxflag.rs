@@ -0,0 +1,280 @@
+use super::distance::shortest_paths;
+use super::geometry::point_segment_distance;
+use super::hgrid::Hgrid;
+use super::prop::Prop;
+use derive_builder::Builder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Builder, Debug, Clone)]
+#[builder(setter(into), build_fn(validate = "Self::validate"))]
+pub struct FluxTransect {
+    name: String,
+    polyline: Vec<[f64; 2]>,
+}
+
+impl FluxTransectBuilder {
+    pub fn validate(&self) -> Result<(), FluxTransectBuilderError> {
+        if let Some(polyline) = &self.polyline {
+            if polyline.len() < 2 {
+                return Err(FluxTransectBuilderError::ValidationError(format!(
+                    "A flux transect needs at least 2 vertices but got {}",
+                    polyline.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FluxTransect {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn polyline(&self) -> &Vec<[f64; 2]> {
+        &self.polyline
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FluxTransectReport {
+    pub name: String,
+    pub upstream_flag: i32,
+    pub downstream_flag: i32,
+    pub node_path: Vec<u32>,
+    pub upstream_elements: Vec<u32>,
+    pub downstream_elements: Vec<u32>,
+    pub land_boundary_nodes: Vec<u32>,
+}
+
+impl FluxTransectReport {
+    pub fn crosses_land_boundary(&self) -> bool {
+        !self.land_boundary_nodes.is_empty()
+    }
+}
+
+// Elements left of each transect (walking along the polyline) are flagged as the upstream
+// region 2k and elements on its right as the downstream region 2k + 1. Unflagged elements are -1.
+// SCHISM writes one flux.out column per consecutive region pair (i, i + 1), so the left to right
+// flux through transect k is in column 2k + 1 after the time column; the columns in between
+// pair regions of different transects and are not meaningful.
+#[derive(Debug, Clone)]
+pub struct FluxFlags {
+    prop: Prop,
+    reports: Vec<FluxTransectReport>,
+}
+
+impl FluxFlags {
+    pub fn new(hgrid: &Hgrid, transects: &[FluxTransect]) -> Result<Self, FluxFlagsError> {
+        let nodes = hgrid.nodes().btree_map();
+        let neighbors = hgrid.node_neighbors();
+        let side_elements = hgrid.side_elements();
+        let centroids = hgrid.element_centroids();
+        let land_nodes = land_boundary_nodes(hgrid);
+        let mut flags: BTreeMap<u32, f64> = hgrid
+            .elements()
+            .btree_map()
+            .keys()
+            .map(|&element_id| (element_id, -1.))
+            .collect();
+        let mut owners: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut reports = Vec::with_capacity(transects.len());
+        for (transect_index, transect) in transects.iter().enumerate() {
+            let mut node_path: Vec<u32> = Vec::new();
+            let snapped: Vec<u32> = transect
+                .polyline
+                .iter()
+                .map(|&vertex| nearest_node(hgrid, vertex).ok_or(FluxFlagsError::EmptyHgrid))
+                .collect::<Result<_, _>>()?;
+            for (segment_index, pair) in snapped.windows(2).enumerate() {
+                let segment = [
+                    transect.polyline[segment_index],
+                    transect.polyline[segment_index + 1],
+                ];
+                let path = shortest_path(hgrid, &neighbors, pair[0], pair[1], segment)
+                    .ok_or_else(|| FluxFlagsError::DisconnectedTransect(transect.name.clone()))?;
+                for node_id in path {
+                    if node_path.last() != Some(&node_id) {
+                        node_path.push(node_id);
+                    }
+                }
+            }
+            if node_path.len() < 2 {
+                return Err(FluxFlagsError::DegenerateTransect(transect.name.clone()));
+            }
+            let upstream_flag = 2 * transect_index as i32;
+            let downstream_flag = upstream_flag + 1;
+            let mut upstream_elements = BTreeSet::new();
+            let mut downstream_elements = BTreeSet::new();
+            for pair in node_path.windows(2) {
+                let key = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+                let (start, _) = &nodes[&pair[0]];
+                let (end, _) = &nodes[&pair[1]];
+                for element_id in side_elements.get(&key).into_iter().flatten() {
+                    let centroid = centroids[element_id];
+                    let cross = (end[0] - start[0]) * (centroid[1] - start[1])
+                        - (end[1] - start[1]) * (centroid[0] - start[0]);
+                    if cross > 0. {
+                        upstream_elements.insert(*element_id);
+                    } else {
+                        downstream_elements.insert(*element_id);
+                    }
+                }
+            }
+            for (elements, flag) in [
+                (&upstream_elements, upstream_flag),
+                (&downstream_elements, downstream_flag),
+            ] {
+                for &element_id in elements.iter() {
+                    if let Some(&owner) = owners.get(&element_id) {
+                        return Err(FluxFlagsError::OverlappingTransects(
+                            transects[owner].name.clone(),
+                            transect.name.clone(),
+                            element_id,
+                        ));
+                    }
+                    owners.insert(element_id, transect_index);
+                    flags.insert(element_id, flag as f64);
+                }
+            }
+            // The end points of a transect are expected to sit on the banks, any other land
+            // node on the snapped path means the transect cuts across land.
+            let land_boundary_nodes = node_path[1..node_path.len() - 1]
+                .iter()
+                .filter(|node_id| land_nodes.contains(node_id))
+                .copied()
+                .collect();
+            reports.push(FluxTransectReport {
+                name: transect.name.clone(),
+                upstream_flag,
+                downstream_flag,
+                node_path,
+                upstream_elements: upstream_elements.into_iter().collect(),
+                downstream_elements: downstream_elements.into_iter().collect(),
+                land_boundary_nodes,
+            });
+        }
+        Ok(Self {
+            prop: Prop::from(flags),
+            reports,
+        })
+    }
+
+    pub fn prop(&self) -> &Prop {
+        &self.prop
+    }
+
+    pub fn reports(&self) -> &Vec<FluxTransectReport> {
+        &self.reports
+    }
+
+    pub fn land_crossings(&self) -> Vec<&FluxTransectReport> {
+        self.reports
+            .iter()
+            .filter(|report| report.crosses_land_boundary())
+            .collect()
+    }
+
+    // 1-based column of flux.out, not counting the time column, holding the flux of the k-th
+    // transect.
+    pub fn flux_out_column(&self, transect_index: usize) -> usize {
+        2 * transect_index + 1
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        self.prop.write(path)
+    }
+}
+
+pub fn write_fluxflag_prop(
+    hgrid: &Hgrid,
+    transects: &[FluxTransect],
+    path: &Path,
+) -> Result<FluxFlags, FluxFlagsError> {
+    let flux_flags = FluxFlags::new(hgrid, transects)?;
+    for report in flux_flags.land_crossings() {
+        log::warn!(
+            "Flux transect {} crosses a land boundary at nodes {:?}",
+            report.name,
+            report.land_boundary_nodes
+        );
+    }
+    for (transect_index, report) in flux_flags.reports().iter().enumerate() {
+        log::info!(
+            "Flux through transect {} will be in flux.out column {} after time",
+            report.name,
+            flux_flags.flux_out_column(transect_index)
+        );
+    }
+    flux_flags.write(path)?;
+    Ok(flux_flags)
+}
+
+fn land_boundary_nodes(hgrid: &Hgrid) -> BTreeSet<u32> {
+    let mut land_nodes = BTreeSet::new();
+    if let Some(boundaries) = hgrid.boundaries() {
+        if let Some(land) = boundaries.land() {
+            land_nodes.extend(land.nodes_ids().into_iter().flatten());
+        }
+        if let Some(interior) = boundaries.interior() {
+            land_nodes.extend(interior.nodes_ids().into_iter().flatten());
+        }
+    }
+    land_nodes
+}
+
+fn nearest_node(hgrid: &Hgrid, vertex: [f64; 2]) -> Option<u32> {
+    hgrid
+        .nodes()
+        .btree_map()
+        .iter()
+        .map(|(&node_id, (coord, _))| {
+            let distance = (coord[0] - vertex[0]).powi(2) + (coord[1] - vertex[1]).powi(2);
+            (node_id, distance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(node_id, _)| node_id)
+}
+
+// Dijkstra along element sides, penalizing sides that stray away from the polyline segment so
+// the path hugs the transect.
+fn shortest_path(
+    hgrid: &Hgrid,
+    neighbors: &BTreeMap<u32, BTreeSet<u32>>,
+    source: u32,
+    target: u32,
+    segment: [[f64; 2]; 2],
+) -> Option<Vec<u32>> {
+    let (costs, previous) = shortest_paths(hgrid, neighbors, &[source], Some(target), |a, b| {
+        let length = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let midpoint = [(a[0] + b[0]) / 2., (a[1] + b[1]) / 2.];
+        length + 4. * point_segment_distance(midpoint, segment[0], segment[1])
+    });
+    if !costs.contains_key(&target) {
+        return None;
+    }
+    let mut path = vec![target];
+    let mut current = target;
+    while let Some(&prev) = previous.get(&current) {
+        path.push(prev);
+        current = prev;
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[derive(Error, Debug)]
+pub enum FluxFlagsError {
+    #[error("Flux transect {0} could not be traced along the mesh sides")]
+    DisconnectedTransect(String),
+    #[error("Flux transects cannot be snapped to an hgrid without nodes")]
+    EmptyHgrid,
+    #[error("Flux transect {0} snaps to a single node")]
+    DegenerateTransect(String),
+    #[error("Flux transects {0} and {1} both flag element {2}")]
+    OverlappingTransects(String, String, u32),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,451 @@
+use super::hgrid::Hgrid;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+pub fn signed_polygon_area(coords: &[[f64; 2]]) -> f64 {
+    let mut area = 0.;
+    for (local_index, this_coord) in coords.iter().enumerate() {
+        let next_coord = coords[(local_index + 1) % coords.len()];
+        area += this_coord[0] * next_coord[1] - next_coord[0] * this_coord[1];
+    }
+    area / 2.
+}
+
+pub fn point_segment_distance(point: [f64; 2], start: [f64; 2], end: [f64; 2]) -> f64 {
+    let dx = end[0] - start[0];
+    let dy = end[1] - start[1];
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0. {
+        (((point[0] - start[0]) * dx + (point[1] - start[1]) * dy) / length_squared).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let closest = [start[0] + t * dx, start[1] + t * dy];
+    ((point[0] - closest[0]).powi(2) + (point[1] - closest[1]).powi(2)).sqrt()
+}
+
+// Mean Earth radius (IUGG), in meters.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+// Earth radius SCHISM uses for its CPP projection of geographic grids (ics = 2).
+pub const CPP_EARTH_RADIUS: f64 = 6_378_206.4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum GeometryMethod {
+    // Spherical for hgrids with a geographic CRS, planar otherwise.
+    #[default]
+    Auto,
+    Planar,
+    Spherical,
+    // SCHISM's Carte Parallelogrammatique Projection around a reference longitude and latitude
+    // in degrees: x = R (lon - lon0) cos(lat0), y = R lat.
+    Cpp {
+        lon0: f64,
+        lat0: f64,
+    },
+}
+
+fn unit_vector(coord: [f64; 2]) -> [f64; 3] {
+    let (lon, lat) = (coord[0].to_radians(), coord[1].to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn planar_angle(prev: [f64; 2], this: [f64; 2], next: [f64; 2]) -> f64 {
+    let u = [prev[0] - this[0], prev[1] - this[1]];
+    let v = [next[0] - this[0], next[1] - this[1]];
+    let cross = u[0] * v[1] - u[1] * v[0];
+    let dot = u[0] * v[0] + u[1] * v[1];
+    cross.atan2(dot).abs().to_degrees()
+}
+
+// Distances, areas and angles under a resolved GeometryMethod, never Auto. Spherical and CPP
+// metrics take longitude and latitude in degrees and return meters, square meters and degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metric {
+    method: GeometryMethod,
+}
+
+impl Metric {
+    pub fn new(method: GeometryMethod, is_geographic: bool) -> Self {
+        let method = match method {
+            GeometryMethod::Auto if is_geographic => GeometryMethod::Spherical,
+            GeometryMethod::Auto => GeometryMethod::Planar,
+            method => method,
+        };
+        Self { method }
+    }
+
+    pub fn method(&self) -> GeometryMethod {
+        self.method
+    }
+
+    pub fn cpp(lon0: f64, lat0: f64, coord: [f64; 2]) -> [f64; 2] {
+        [
+            CPP_EARTH_RADIUS * (coord[0] - lon0).to_radians() * lat0.to_radians().cos(),
+            CPP_EARTH_RADIUS * coord[1].to_radians(),
+        ]
+    }
+
+    // Haversine great-circle distance.
+    fn great_circle_distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+        let (lat_a, lat_b) = (a[1].to_radians(), b[1].to_radians());
+        let dlat = lat_b - lat_a;
+        let dlon = (b[0] - a[0]).to_radians();
+        let h = (dlat / 2.).sin().powi(2) + lat_a.cos() * lat_b.cos() * (dlon / 2.).sin().powi(2);
+        2. * EARTH_RADIUS * h.sqrt().min(1.).asin()
+    }
+
+    pub fn distance(&self, a: [f64; 2], b: [f64; 2]) -> f64 {
+        match self.method {
+            GeometryMethod::Spherical => Self::great_circle_distance(a, b),
+            GeometryMethod::Cpp { lon0, lat0 } => {
+                let (a, b) = (Self::cpp(lon0, lat0, a), Self::cpp(lon0, lat0, b));
+                (a[0] - b[0]).hypot(a[1] - b[1])
+            }
+            _ => (a[0] - b[0]).hypot(a[1] - b[1]),
+        }
+    }
+
+    // On the sphere the segment is measured in an equirectangular projection centered at the
+    // point, which is accurate for segments that are short compared to the Earth radius.
+    pub fn point_segment_distance(&self, point: [f64; 2], start: [f64; 2], end: [f64; 2]) -> f64 {
+        match self.method {
+            GeometryMethod::Spherical => {
+                let project = |coord: [f64; 2]| {
+                    [
+                        EARTH_RADIUS
+                            * (coord[0] - point[0]).to_radians()
+                            * point[1].to_radians().cos(),
+                        EARTH_RADIUS * (coord[1] - point[1]).to_radians(),
+                    ]
+                };
+                point_segment_distance([0., 0.], project(start), project(end))
+            }
+            GeometryMethod::Cpp { lon0, lat0 } => point_segment_distance(
+                Self::cpp(lon0, lat0, point),
+                Self::cpp(lon0, lat0, start),
+                Self::cpp(lon0, lat0, end),
+            ),
+            _ => point_segment_distance(point, start, end),
+        }
+    }
+
+    // Positive for counter-clockwise rings. On the sphere this is the sum of the signed
+    // spherical excesses of the triangles fanning out from the first vertex.
+    pub fn signed_polygon_area(&self, coords: &[[f64; 2]]) -> f64 {
+        match self.method {
+            GeometryMethod::Spherical => {
+                let vectors: Vec<[f64; 3]> = coords.iter().map(|&c| unit_vector(c)).collect();
+                let mut excess = 0.;
+                for i in 1..vectors.len().saturating_sub(1) {
+                    let (a, b, c) = (vectors[0], vectors[i], vectors[i + 1]);
+                    let numerator = dot(a, cross(b, c));
+                    let denominator = 1. + dot(a, b) + dot(b, c) + dot(c, a);
+                    excess += 2. * numerator.atan2(denominator);
+                }
+                EARTH_RADIUS * EARTH_RADIUS * excess
+            }
+            GeometryMethod::Cpp { lon0, lat0 } => {
+                let projected: Vec<[f64; 2]> = coords
+                    .iter()
+                    .map(|&coord| Self::cpp(lon0, lat0, coord))
+                    .collect();
+                signed_polygon_area(&projected)
+            }
+            _ => signed_polygon_area(coords),
+        }
+    }
+
+    // Angle at `this` between the directions to `prev` and `next`, in degrees.
+    pub fn angle(&self, prev: [f64; 2], this: [f64; 2], next: [f64; 2]) -> f64 {
+        match self.method {
+            GeometryMethod::Spherical => {
+                let (p, t, n) = (unit_vector(prev), unit_vector(this), unit_vector(next));
+                // Tangents at `this` of the great circles towards both neighbors.
+                let u = cross(cross(t, p), t);
+                let v = cross(cross(t, n), t);
+                let sin = dot(cross(u, v), t);
+                sin.atan2(dot(u, v)).abs().to_degrees()
+            }
+            GeometryMethod::Cpp { lon0, lat0 } => planar_angle(
+                Self::cpp(lon0, lat0, prev),
+                Self::cpp(lon0, lat0, this),
+                Self::cpp(lon0, lat0, next),
+            ),
+            _ => planar_angle(prev, this, next),
+        }
+    }
+}
+
+impl Hgrid {
+    pub fn is_geographic(&self) -> bool {
+        self.crs().is_some_and(|crs| crs.is_geographic())
+    }
+
+    pub fn metric(&self, method: GeometryMethod) -> Metric {
+        Metric::new(method, self.is_geographic())
+    }
+
+    fn element_coords(&self) -> BTreeMap<u32, Vec<[f64; 2]>> {
+        let nodes = self.nodes().btree_map();
+        self.elements()
+            .btree_map()
+            .into_iter()
+            .map(|(element_id, node_ids)| {
+                let coords = node_ids
+                    .iter()
+                    .map(|node_id| {
+                        let (coord, _values) = &nodes[node_id];
+                        [coord[0], coord[1]]
+                    })
+                    .collect();
+                (element_id, coords)
+            })
+            .collect()
+    }
+
+    pub fn element_areas_with(&self, method: GeometryMethod) -> BTreeMap<u32, f64> {
+        let metric = self.metric(method);
+        self.element_coords()
+            .into_iter()
+            .map(|(element_id, coords)| (element_id, metric.signed_polygon_area(&coords).abs()))
+            .collect()
+    }
+
+    pub fn side_lengths(&self, method: GeometryMethod) -> BTreeMap<(u32, u32), f64> {
+        let metric = self.metric(method);
+        let nodes = self.nodes().btree_map();
+        self.side_elements()
+            .into_keys()
+            .map(|(a, b)| {
+                let (coord_a, _) = &nodes[&a];
+                let (coord_b, _) = &nodes[&b];
+                let length = metric.distance([coord_a[0], coord_a[1]], [coord_b[0], coord_b[1]]);
+                ((a, b), length)
+            })
+            .collect()
+    }
+
+    // Interior angles of every element, in the order of its nodes.
+    pub fn element_angles(&self, method: GeometryMethod) -> BTreeMap<u32, Vec<f64>> {
+        let metric = self.metric(method);
+        self.element_coords()
+            .into_iter()
+            .map(|(element_id, coords)| {
+                let n = coords.len();
+                let angles = (0..n)
+                    .map(|i| metric.angle(coords[(i + n - 1) % n], coords[i], coords[(i + 1) % n]))
+                    .collect();
+                (element_id, angles)
+            })
+            .collect()
+    }
+}
+
+fn ring_distance(ring: &[[f64; 2]], x: f64, y: f64) -> f64 {
+    (0..ring.len())
+        .map(|i| point_segment_distance([x, y], ring[i], ring[(i + 1) % ring.len()]))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn ring_contains(ring: &[[f64; 2]], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let [xi, yi] = ring[i];
+        let [xj, yj] = ring[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    exterior: Vec<[f64; 2]>,
+    interiors: Vec<Vec<[f64; 2]>>,
+}
+
+impl Polygon {
+    pub fn new(
+        exterior: Vec<[f64; 2]>,
+        interiors: Vec<Vec<[f64; 2]>>,
+    ) -> Result<Self, PolygonError> {
+        if exterior.len() < 3 {
+            return Err(PolygonError::NotEnoughVertices(exterior.len()));
+        }
+        for interior in interiors.iter() {
+            if interior.len() < 3 {
+                return Err(PolygonError::NotEnoughVertices(interior.len()));
+            }
+        }
+        Ok(Self {
+            exterior,
+            interiors,
+        })
+    }
+
+    pub fn exterior(&self) -> &Vec<[f64; 2]> {
+        &self.exterior
+    }
+
+    pub fn interiors(&self) -> &Vec<Vec<[f64; 2]>> {
+        &self.interiors
+    }
+
+    pub fn distance_to_boundary(&self, x: f64, y: f64) -> f64 {
+        self.interiors
+            .iter()
+            .map(|interior| ring_distance(interior, x, y))
+            .fold(ring_distance(&self.exterior, x, y), f64::min)
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        ring_contains(&self.exterior, x, y)
+            && !self
+                .interiors
+                .iter()
+                .any(|interior| ring_contains(interior, x, y))
+    }
+}
+
+fn ring_from_geojson(value: &Value) -> Result<Vec<[f64; 2]>, PolygonError> {
+    let positions = value
+        .as_array()
+        .ok_or_else(|| PolygonError::GeoJsonError("Expected an array of positions".to_string()))?;
+    let mut ring = Vec::with_capacity(positions.len());
+    for position in positions {
+        let x = position.get(0).and_then(Value::as_f64);
+        let y = position.get(1).and_then(Value::as_f64);
+        match (x, y) {
+            (Some(x), Some(y)) => ring.push([x, y]),
+            _ => {
+                return Err(PolygonError::GeoJsonError(format!(
+                    "Expected a position of at least two numbers but found {}",
+                    position
+                )))
+            }
+        }
+    }
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    Ok(ring)
+}
+
+fn polygon_from_geojson(value: &Value) -> Result<Polygon, PolygonError> {
+    let rings = value
+        .as_array()
+        .ok_or_else(|| PolygonError::GeoJsonError("Expected an array of rings".to_string()))?;
+    let mut rings = rings
+        .iter()
+        .map(ring_from_geojson)
+        .collect::<Result<Vec<_>, _>>()?;
+    if rings.is_empty() {
+        return Err(PolygonError::GeoJsonError(
+            "Found a polygon without rings".to_string(),
+        ));
+    }
+    let exterior = rings.remove(0);
+    Polygon::new(exterior, rings)
+}
+
+fn collect_geojson_polygons(
+    value: &Value,
+    polygons: &mut Vec<(Option<String>, Polygon)>,
+    name: Option<String>,
+) -> Result<(), PolygonError> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => {
+            let features = value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    PolygonError::GeoJsonError("FeatureCollection without features".to_string())
+                })?;
+            for feature in features {
+                collect_geojson_polygons(feature, polygons, None)?;
+            }
+        }
+        Some("Feature") => {
+            let name = value
+                .get("properties")
+                .and_then(|properties| properties.get("name"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if let Some(geometry) = value.get("geometry") {
+                collect_geojson_polygons(geometry, polygons, name)?;
+            }
+        }
+        Some("Polygon") => {
+            let coordinates = value.get("coordinates").ok_or_else(|| {
+                PolygonError::GeoJsonError("Polygon without coordinates".to_string())
+            })?;
+            polygons.push((name, polygon_from_geojson(coordinates)?));
+        }
+        Some("MultiPolygon") => {
+            let coordinates = value
+                .get("coordinates")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    PolygonError::GeoJsonError("MultiPolygon without coordinates".to_string())
+                })?;
+            for coordinates in coordinates {
+                polygons.push((name.clone(), polygon_from_geojson(coordinates)?));
+            }
+        }
+        Some(other) => {
+            log::warn!("Ignoring unsupported GeoJSON type {}", other);
+        }
+        None => {
+            return Err(PolygonError::GeoJsonError(
+                "Found a GeoJSON object without a type".to_string(),
+            ))
+        }
+    }
+    Ok(())
+}
+
+pub fn polygons_from_geojson_str(
+    geojson: &str,
+) -> Result<Vec<(Option<String>, Polygon)>, PolygonError> {
+    let value: Value =
+        serde_json::from_str(geojson).map_err(|e| PolygonError::GeoJsonError(e.to_string()))?;
+    let mut polygons = Vec::new();
+    collect_geojson_polygons(&value, &mut polygons, None)?;
+    Ok(polygons)
+}
+
+pub fn polygons_from_geojson_path(
+    path: &Path,
+) -> Result<Vec<(Option<String>, Polygon)>, PolygonError> {
+    let geojson = fs::read_to_string(path)
+        .map_err(|e| PolygonError::IoError(path.display().to_string(), e.to_string()))?;
+    polygons_from_geojson_str(&geojson)
+}
+
+#[derive(Error, Debug)]
+pub enum PolygonError {
+    #[error("A polygon ring requires at least 3 vertices but got {0}")]
+    NotEnoughVertices(usize),
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Invalid GeoJSON: {0}")]
+    GeoJsonError(String),
+}
@@ -1,3 +1,4 @@
+use super::crs::Crs;
 use derive_builder::Builder;
 use log;
 use proj::Proj;
@@ -15,11 +16,14 @@ use url::Url;
 #[builder(setter(into))]
 pub struct Gr3ParserOutput {
     description: Option<String>,
-    crs: Option<Arc<Proj>>,
+    crs: Option<Arc<Crs>>,
     nodes: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
     elements: BTreeMap<u32, Vec<u32>>, // elements
+    #[builder(default)]
     open_boundaries: Option<Vec<Vec<u32>>>,
+    #[builder(default)]
     land_boundaries: Option<Vec<Vec<u32>>>,
+    #[builder(default)]
     interior_boundaries: Option<Vec<Vec<u32>>>,
 }
 
@@ -41,7 +45,7 @@ impl Gr3ParserOutput {
     pub fn elements(&self) -> BTreeMap<u32, Vec<u32>> {
         self.elements.clone()
     }
-    pub fn crs(&self) -> Option<Arc<Proj>> {
+    pub fn crs(&self) -> Option<Arc<Crs>> {
         self.crs.clone()
     }
     pub fn description(&self) -> Option<String> {
@@ -73,7 +77,7 @@ impl fmt::Display for Gr3ParserOutput {
         let crs_str: String = self
             .crs
             .as_ref()
-            .map(|proj| proj.proj_info().definition.clone().unwrap_or_default())
+            .map(|crs| crs.definition().to_string())
             .unwrap_or_default();
 
         let desc_str = self.description.as_ref().map_or("", String::as_str);
@@ -85,7 +89,8 @@ impl fmt::Display for Gr3ParserOutput {
         } else if desc_str.is_empty() {
             lines.push(crs_str.to_string());
         } else {
-            lines.push(format!("{} {}", crs_str, desc_str));
+            // The parser reads the CRS from the end of the first line.
+            lines.push(format!("{} {}", desc_str, crs_str));
         };
 
         lines.push(format!("{} {}", self.elements.len(), self.nodes.len()));
@@ -176,7 +181,7 @@ impl fmt::Display for Gr3ParserOutput {
             if let Some(interior) = &self.interior_boundaries {
                 for interior_bnd in interior.iter() {
                     total_number_of_non_ocean_boundaries += 1;
-                    total_number_of_non_ocean_boundaries += interior_bnd.len();
+                    total_number_of_non_ocean_boundaries_nodes += interior_bnd.len();
                 }
             }
             lines.push(format!(
@@ -192,7 +197,7 @@ impl fmt::Display for Gr3ParserOutput {
                 for (local_index, this_land_bound) in land.iter().enumerate() {
                     let fortran_index = local_index + 1;
                     lines.push(format!(
-                        "{} ! number of nodes for land_boundary_{}",
+                        "{} 0 ! number of nodes for land_boundary_{}",
                         this_land_bound.len(),
                         fortran_index
                     ));
@@ -208,7 +213,7 @@ impl fmt::Display for Gr3ParserOutput {
                 for (local_index, this_interior_bound) in interior.iter().enumerate() {
                     let fortran_index = local_index + 1;
                     lines.push(format!(
-                        "{} ! number of nodes for interior_boundary_{}",
+                        "{} 1 ! number of nodes for interior_boundary_{}",
                         this_interior_bound.len(),
                         fortran_index
                     ));
@@ -268,16 +273,16 @@ pub fn parse_from_url(url: &Url) -> Result<Gr3ParserOutput, Gr3ParserError> {
     parse_from_reader(reader, &url.to_string())
 }
 
-fn get_proj_from_description(description: &str) -> Option<Proj> {
-    if let Ok(proj) = Proj::new(description) {
-        return Some(proj);
+fn get_proj_from_description(description: &str) -> Option<Crs> {
+    if let Ok(crs) = Crs::new(description) {
+        return Some(crs);
     }
 
     let words: Vec<&str> = description.split_whitespace().collect();
     for i in 0..words.len() {
         let substr = words[i..].join(" ");
-        if let Ok(proj) = Proj::new(&substr) {
-            return Some(proj);
+        if let Ok(crs) = Crs::new(&substr) {
+            return Some(crs);
         }
     }
 
@@ -581,6 +586,7 @@ fn parse_from_reader<R: Read>(
             let mut parsed_gr3_builder = Gr3ParserOutputBuilder::default();
             parsed_gr3_builder.description(description);
             parsed_gr3_builder.nodes(nodemap);
+            parsed_gr3_builder.crs(crs);
             if !elemmap.is_empty() {
                 parsed_gr3_builder.elements(elemmap);
             }
@@ -5,14 +5,19 @@ use super::{
         InteriorBoundariesBuilder, InteriorBoundariesBuilderError, LandBoundariesBuilder,
         LandBoundariesBuilderError, OpenBoundariesBuilder, OpenBoundariesBuilderError,
     },
+    crs::Crs,
+    distance::{
+        along_mesh_distance, straight_line_distance, BoundarySelection, DistanceError,
+        DistanceMethod,
+    },
     elements::{Elements, ElementsBuilder, ElementsBuilderError},
+    geometry::GeometryMethod,
     gr3::{write_to_path, Gr3ParserOutput},
     nodes::{Nodes, NodesBuilder, NodesBuilderError},
 };
 use derive_builder::Builder;
 use ndarray::{Array1, Array2};
-use proj::Proj;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -70,7 +75,7 @@ impl Hgrid {
         self.nodes.xy()
     }
 
-    pub fn crs(&self) -> Option<Arc<Proj>> {
+    pub fn crs(&self) -> Option<Arc<Crs>> {
         self.nodes.crs()
     }
 
@@ -92,10 +97,12 @@ impl Hgrid {
         gr3_parser_output_builder.crs(self.crs().clone());
         if let Some(boundaries) = &self.boundaries {
             let the_type_map = boundaries.to_boundary_type_map();
-            gr3_parser_output_builder.open_boundaries(the_type_map[&BoundaryType::Open].clone());
-            gr3_parser_output_builder.land_boundaries(the_type_map[&BoundaryType::Land].clone());
             gr3_parser_output_builder
-                .interior_boundaries(the_type_map[&BoundaryType::Interior].clone());
+                .open_boundaries(the_type_map.get(&BoundaryType::Open).cloned());
+            gr3_parser_output_builder
+                .land_boundaries(the_type_map.get(&BoundaryType::Land).cloned());
+            gr3_parser_output_builder
+                .interior_boundaries(the_type_map.get(&BoundaryType::Interior).cloned());
         } else {
             gr3_parser_output_builder.open_boundaries(Vec::new());
             gr3_parser_output_builder.land_boundaries(Vec::new());
@@ -105,6 +112,209 @@ impl Hgrid {
         write_to_path(path, &gr3_parser_output)
     }
 
+    pub fn write_node_values(
+        &self,
+        path: &Path,
+        description: &str,
+        values: &BTreeMap<u32, f64>,
+    ) -> std::io::Result<()> {
+        let nodes: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)> = self
+            .nodes
+            .btree_map()
+            .into_iter()
+            .map(|(node_id, (coord, _values))| {
+                (
+                    node_id,
+                    (coord, values.get(&node_id).map(|&value| vec![value])),
+                )
+            })
+            .collect();
+        let gr3_parser_output = Gr3ParserOutputBuilder::default()
+            .description(Some(description.to_string()))
+            .nodes(nodes)
+            .elements(self.elements.btree_map())
+            .crs(None::<Arc<Crs>>)
+            .open_boundaries(None::<Vec<Vec<u32>>>)
+            .land_boundaries(None::<Vec<Vec<u32>>>)
+            .interior_boundaries(None::<Vec<Vec<u32>>>)
+            .build()
+            .unwrap();
+        write_to_path(path, &gr3_parser_output)
+    }
+
+    pub fn node_neighbors(&self) -> BTreeMap<u32, BTreeSet<u32>> {
+        let mut neighbors: BTreeMap<u32, BTreeSet<u32>> = self
+            .nodes
+            .btree_map()
+            .keys()
+            .map(|&node_id| (node_id, BTreeSet::new()))
+            .collect();
+        for (_element_id, node_ids) in self.elements.btree_map().iter() {
+            for (local_index, &node_id) in node_ids.iter().enumerate() {
+                let next_node_id = node_ids[(local_index + 1) % node_ids.len()];
+                neighbors.entry(node_id).or_default().insert(next_node_id);
+                neighbors.entry(next_node_id).or_default().insert(node_id);
+            }
+        }
+        neighbors
+    }
+
+    pub fn side_elements(&self) -> BTreeMap<(u32, u32), Vec<u32>> {
+        let mut side_elements: BTreeMap<(u32, u32), Vec<u32>> = BTreeMap::new();
+        for (&element_id, node_ids) in self.elements.btree_map().iter() {
+            for (local_index, &node_id) in node_ids.iter().enumerate() {
+                let next_node_id = node_ids[(local_index + 1) % node_ids.len()];
+                let key = (node_id.min(next_node_id), node_id.max(next_node_id));
+                side_elements.entry(key).or_default().push(element_id);
+            }
+        }
+        side_elements
+    }
+
+    pub fn node_elements(&self) -> BTreeMap<u32, Vec<u32>> {
+        let mut node_elements: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (&element_id, node_ids) in self.elements.btree_map().iter() {
+            for &node_id in node_ids {
+                node_elements.entry(node_id).or_default().push(element_id);
+            }
+        }
+        node_elements
+    }
+
+    pub fn element_areas(&self) -> BTreeMap<u32, f64> {
+        self.element_areas_with(GeometryMethod::Auto)
+    }
+
+    pub fn element_centroids(&self) -> BTreeMap<u32, [f64; 2]> {
+        let nodes = self.nodes.btree_map();
+        self.elements
+            .btree_map()
+            .iter()
+            .map(|(&element_id, node_ids)| {
+                let mut centroid = [0., 0.];
+                for node_id in node_ids {
+                    let (coord, _values) = &nodes[node_id];
+                    centroid[0] += coord[0];
+                    centroid[1] += coord[1];
+                }
+                centroid[0] /= node_ids.len() as f64;
+                centroid[1] /= node_ids.len() as f64;
+                (element_id, centroid)
+            })
+            .collect()
+    }
+
+    pub fn depths_btree_map(&self) -> BTreeMap<u32, f64> {
+        self.nodes
+            .btree_map()
+            .iter()
+            .filter_map(|(&node_id, (_coord, values))| {
+                values
+                    .as_ref()
+                    .and_then(|values| values.first().copied())
+                    .map(|depth| (node_id, depth))
+            })
+            .collect()
+    }
+
+    pub fn distance_to_boundary(
+        &self,
+        selection: &BoundarySelection,
+        method: DistanceMethod,
+    ) -> Result<BTreeMap<u32, f64>, DistanceError> {
+        let segments = selection.segments(self)?;
+        Ok(match method {
+            DistanceMethod::AlongMesh => {
+                let sources: Vec<u32> = segments.into_iter().flatten().collect();
+                along_mesh_distance(self, &sources)
+            }
+            DistanceMethod::StraightLine => straight_line_distance(self, &segments),
+        })
+    }
+
+    pub(crate) fn replace_nodes_and_crs(
+        &mut self,
+        node_btree_map: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+        crs: Option<Arc<Crs>>,
+    ) -> Result<(), HgridTryFromError> {
+        let boundary_type_map = self
+            .boundaries
+            .as_ref()
+            .map(|boundaries| boundaries.to_boundary_type_map())
+            .unwrap_or_default();
+        *self = Self::from_parts(
+            node_btree_map,
+            crs,
+            self.elements.btree_map(),
+            boundary_type_map,
+            self.description.clone(),
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn from_parts(
+        node_btree_map: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
+        crs: Option<Arc<Crs>>,
+        element_btree_map: BTreeMap<u32, Vec<u32>>,
+        mut boundary_type_map: BTreeMap<BoundaryType, Vec<Vec<u32>>>,
+        description: Option<String>,
+    ) -> Result<Self, HgridTryFromError> {
+        let nodes = NodesBuilder::default()
+            .btree_map(node_btree_map)
+            .crs(crs)
+            .build()
+            .map(Arc::new)?;
+        let elements = ElementsBuilder::default()
+            .nodes(nodes.clone())
+            .btree_map(element_btree_map)
+            .build()?;
+        boundary_type_map.retain(|_, nodes_ids| !nodes_ids.is_empty());
+        let boundaries = if boundary_type_map.is_empty() {
+            None
+        } else {
+            let open = match boundary_type_map.remove(&BoundaryType::Open) {
+                Some(nodes_ids) => Some(
+                    OpenBoundariesBuilder::default()
+                        .nodes_ids(nodes_ids)
+                        .nodes(nodes.clone())
+                        .build()?,
+                ),
+                None => None,
+            };
+            let land = match boundary_type_map.remove(&BoundaryType::Land) {
+                Some(nodes_ids) => Some(
+                    LandBoundariesBuilder::default()
+                        .nodes_ids(nodes_ids)
+                        .nodes(nodes.clone())
+                        .build()?,
+                ),
+                None => None,
+            };
+            let interior = match boundary_type_map.remove(&BoundaryType::Interior) {
+                Some(nodes_ids) => Some(
+                    InteriorBoundariesBuilder::default()
+                        .nodes_ids(nodes_ids)
+                        .nodes(nodes.clone())
+                        .build()?,
+                ),
+                None => None,
+            };
+            Some(
+                BoundariesBuilder::default()
+                    .open(open)
+                    .land(land)
+                    .interior(interior)
+                    .build()?,
+            )
+        };
+        Ok(Self {
+            nodes,
+            elements,
+            boundaries,
+            description,
+        })
+    }
+
     pub fn get_number_of_elements_connected_to_each_node(&self) -> Array1<usize> {
         let mut counts = vec![0; self.nodes.len() + 1];
         for (_element, node_ids) in self.elements.btree_map().iter() {
@@ -243,111 +453,1606 @@ impl TryFrom<&Gr3ParserOutput> for Hgrid {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::setup_simple_logger;
-    use delaunator::{triangulate, Point};
+    use crate::bathymetry::{
+        BathymetryEditError, DepthClampBuilder, DepthSmoothingBuilder, SlopeLimiterBuilder,
+        SmoothingMethod,
+    };
+    use crate::cfl::{CflCheckBuilder, CharacteristicLength};
+    use crate::clip::{ClipError, ClipMode};
+    use crate::crs::CrsError;
+    use crate::distance::{BoundarySelection, DistanceError, DistanceMethod};
+    use crate::editing::HgridEditError;
+    use crate::fluxflag::{FluxFlags, FluxFlagsError, FluxTransect, FluxTransectBuilder};
+    use crate::geometry::{GeometryMethod, Polygon, EARTH_RADIUS};
+    use crate::meshgen::{MeshGeneratorBuilder, SizeFunction, SpatialFunction};
+    use crate::nudging::NudgingRelaxationBuilder;
+    use crate::partition::{PartitionError, PartitionGraph, PartitionerBuilder};
+    use crate::prop::{Prop, PropError};
+    use crate::properties::{PropertyRuleBuilder, PropertyRulesBuilder, RuleRegion, RuleValue};
+    use crate::reproject::{
+        reproject, to_hgrid_ll, HgridPairCheckBuilder, HgridPairMismatch, ReprojectError,
+    };
+    use crate::sms2dm::Sms2dmError;
+    use crate::source_sink::{RiverBuilder, SourceSink, SourceSinkError, AMBIENT};
+    use crate::stations::{Station, StationFileBuilder, StationIssue};
+    use crate::synthetic::{
+        AnnulusBuilder, ElementType, IslandBasinBuilder, RectangularChannelBuilder,
+        SlopingBeachBuilder,
+    };
+    use crate::th::{parse_datetime, ThError, TimeHistory};
+    use crate::validation::MeshIssue;
+    use crate::windrot::{windrot_geo2proj, WindrotError};
     use log;
-    use ndarray::Array1;
-    use proj::Proj;
-    use std::collections::BTreeMap;
-    use std::path::Path;
+    use std::io::Write;
     use std::sync::Arc;
     use std::time::Instant;
     use tempfile::NamedTempFile;
+
     #[test]
     fn test_read_gr3() {
-        let path = Path::new("tests/fixtures/hgrid.ll");
-        gr3::parse_from_path_ref(&path).unwrap();
+        let temp_file = NamedTempFile::new().unwrap();
+        let hgrid = IslandBasinBuilder::default()
+            .length(1.)
+            .width(1.)
+            .origin([-75., 35.])
+            .crs(Crs::new("epsg:4326").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        hgrid.write(temp_file.path()).unwrap();
+        gr3::parse_from_path_ref(&temp_file.path()).unwrap();
+        let parsed = Hgrid::try_from(&temp_file.path().to_path_buf()).unwrap();
+        assert_eq!(parsed.crs().unwrap().definition(), "epsg:4326");
+        assert!(parsed.is_geographic());
+        assert_eq!(parsed.nodes().len(), hgrid.nodes().len());
+        assert_eq!(
+            parsed.elements().btree_map().len(),
+            hgrid.elements().btree_map().len()
+        );
+        assert_eq!(
+            parsed.boundaries().unwrap().to_boundary_type_map(),
+            hgrid.boundaries().unwrap().to_boundary_type_map()
+        );
     }
 
     #[test]
-    fn test_write_sample_nwatl_hgrid() {
-        setup_simple_logger();
-        let temp_file = NamedTempFile::new().unwrap();
-        let temp_path = temp_file.path();
-        let xmin = -98.00556;
-        let ymin = 8.534422;
-        let xmax = -60.040005;
-        let ymax = 45.831431;
-        let num_samples = 3000000;
-        let num_x = (num_samples as f64 * ((xmax - xmin) / (ymax - ymin))).sqrt() as usize;
-        let num_y = num_samples / num_x;
-        let x_coords: Array1<f64> = Array1::linspace(xmin, xmax, num_x);
-        let y_coords: Array1<f64> = Array1::linspace(ymin, ymax, num_y);
-        let mut points: Vec<Point> = Vec::new();
-        for x in x_coords.iter() {
-            for y in y_coords.iter() {
-                points.push(Point { x: *x, y: *y });
+    fn test_synthetic_boundaries() {
+        for element_type in [ElementType::Triangle, ElementType::Quad] {
+            let channel = RectangularChannelBuilder::default()
+                .element_type(element_type)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+                .boundaries()
+                .unwrap()
+                .to_boundary_type_map();
+            assert_eq!(channel[&BoundaryType::Open].len(), 2);
+            assert_eq!(channel[&BoundaryType::Land].len(), 2);
+            let beach = SlopingBeachBuilder::default()
+                .element_type(element_type)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+                .boundaries()
+                .unwrap()
+                .to_boundary_type_map();
+            assert_eq!(beach[&BoundaryType::Open].len(), 1);
+            assert_eq!(beach[&BoundaryType::Land].len(), 1);
+            let basin = IslandBasinBuilder::default()
+                .element_type(element_type)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+                .boundaries()
+                .unwrap()
+                .to_boundary_type_map();
+            assert_eq!(basin[&BoundaryType::Open].len(), 1);
+            assert_eq!(basin[&BoundaryType::Interior].len(), 1);
+            let annulus = AnnulusBuilder::default()
+                .element_type(element_type)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+                .boundaries()
+                .unwrap()
+                .to_boundary_type_map();
+            assert_eq!(annulus[&BoundaryType::Land].len(), 1);
+            assert_eq!(annulus[&BoundaryType::Interior].len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_synthetic_orientation() {
+        for element_type in [ElementType::Triangle, ElementType::Quad] {
+            let hgrids = [
+                RectangularChannelBuilder::default()
+                    .element_type(element_type)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+                SlopingBeachBuilder::default()
+                    .element_type(element_type)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+                IslandBasinBuilder::default()
+                    .element_type(element_type)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+                AnnulusBuilder::default()
+                    .element_type(element_type)
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap(),
+            ];
+            for hgrid in hgrids {
+                assert!(hgrid.element_areas().values().all(|&area| area > 0.));
+                assert_eq!(hgrid.mesh_issues(), vec![]);
             }
         }
+    }
 
-        log::info!("Begin making nodes hash map.");
-        let start = Instant::now();
-        let nodes_btree_map: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)> = points
+    #[test]
+    fn test_synthetic_builder_validation() {
+        assert!(IslandBasinBuilder::default().nx(2).build().is_err());
+        assert!(IslandBasinBuilder::default().nx(3).ny(3).build().is_ok());
+        assert!(RectangularChannelBuilder::default().ny(0).build().is_err());
+        assert!(SlopingBeachBuilder::default().nx(0).build().is_err());
+        assert!(AnnulusBuilder::default().ntheta(2).build().is_err());
+        assert!(AnnulusBuilder::default().nr(0).build().is_err());
+        assert!(AnnulusBuilder::default().inner_radius(0.).build().is_err());
+        assert!(AnnulusBuilder::default()
+            .inner_radius(6_000.)
+            .build()
+            .is_err());
+        let basin = IslandBasinBuilder::default()
+            .nx(3)
+            .ny(3)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(basin.elements().btree_map().len(), 16);
+    }
+
+    #[test]
+    fn test_sms2dm_round_trip() {
+        for element_type in [ElementType::Triangle, ElementType::Quad] {
+            let hgrid = IslandBasinBuilder::default()
+                .element_type(element_type)
+                .nx(8)
+                .ny(6)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap();
+            let parsed = Hgrid::from_2dm_str(&hgrid.to_2dm_string()).unwrap();
+            assert_eq!(parsed.nodes().btree_map(), hgrid.nodes().btree_map());
+            assert_eq!(parsed.elements().btree_map(), hgrid.elements().btree_map());
+            assert_eq!(
+                parsed.boundaries().unwrap().to_boundary_type_map(),
+                hgrid.boundaries().unwrap().to_boundary_type_map()
+            );
+            assert_eq!(parsed.description(), hgrid.description());
+            assert_eq!(parsed.mesh_issues(), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_sms2dm_nodestrings() {
+        let contents = "MESH2D\n\
+                        MESHNAME \"square\"\n\
+                        E3T 1 1 2 3 1\n\
+                        E3T 2 1 3 4 1\n\
+                        ND 1 0 0 -5\n\
+                        ND 2 1 0 -5\n\
+                        ND 3 1 1 -2\n\
+                        ND 4 0 1 -2\n\
+                        NS 1 -2 mouth\n";
+        let hgrid = Hgrid::from_2dm_str(contents).unwrap();
+        assert_eq!(hgrid.description().map(String::as_str), Some("square"));
+        assert_eq!(hgrid.depths_btree_map()[&3], -2.);
+        let boundaries = hgrid.boundaries().unwrap().to_boundary_type_map();
+        assert_eq!(boundaries[&BoundaryType::Open], vec![vec![1, 2]]);
+        assert_eq!(boundaries[&BoundaryType::Land], vec![vec![2, 3, 4, 1]]);
+        assert_eq!(hgrid.mesh_issues(), vec![]);
+        assert!(matches!(
+            Hgrid::from_2dm_str("MESH2D\nND 1 0 0\n"),
+            Err(Sms2dmError::ParseError(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_clip() {
+        // 50 x 5 quads of 200 m along a 10 km channel open at x = 0 and x = 10 km.
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let bbox = [-100., -100., 5_050., 1_100.];
+        for (mode, columns) in [
+            (ClipMode::Centroid, 25),
+            (ClipMode::AllNodes, 25),
+            (ClipMode::AnyNode, 26),
+        ] {
+            let clipped = channel.clip_to_bbox(bbox, mode).unwrap();
+            assert_eq!(clipped.elements().btree_map().len(), columns * 5);
+            assert_eq!(clipped.nodes().len(), (columns + 1) * 6);
+            let boundaries = clipped.boundaries().unwrap().to_boundary_type_map();
+            assert_eq!(boundaries[&BoundaryType::Open].len(), 1);
+            assert_eq!(boundaries[&BoundaryType::Land].len(), 1);
+            assert_eq!(clipped.mesh_issues(), vec![]);
+        }
+        // A polygon with a hole leaves an island in the middle of the channel.
+        let polygon = Polygon::new(
+            vec![
+                [-100., -100.],
+                [10_100., -100.],
+                [10_100., 1_100.],
+                [-100., 1_100.],
+            ],
+            vec![vec![
+                [4_000., 300.],
+                [6_000., 300.],
+                [6_000., 700.],
+                [4_000., 700.],
+            ]],
+        )
+        .unwrap();
+        let clipped = channel.clip(&[polygon], ClipMode::Centroid).unwrap();
+        let boundaries = clipped.boundaries().unwrap().to_boundary_type_map();
+        assert_eq!(boundaries[&BoundaryType::Open].len(), 2);
+        assert_eq!(boundaries[&BoundaryType::Interior].len(), 1);
+        assert_eq!(clipped.mesh_issues(), vec![]);
+        assert!(matches!(
+            channel.clip_to_bbox([20_000., 0., 30_000., 1_000.], ClipMode::Centroid),
+            Err(ClipError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_mesh_issues() {
+        let hgrid = RectangularChannelBuilder::default()
+            .nx(4)
+            .ny(2)
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(hgrid.mesh_issues(), vec![]);
+        let mut nodes = hgrid.nodes().btree_map();
+        nodes.insert(100, (vec![50_000., 0.], Some(vec![-1.])));
+        let mut elements = hgrid.elements().btree_map();
+        elements.get_mut(&1).unwrap().reverse();
+        let mut boundaries = hgrid.boundaries().unwrap().to_boundary_type_map();
+        let land = boundaries.remove(&BoundaryType::Land).unwrap();
+        let broken = Hgrid::from_parts(nodes, None, elements, boundaries, None).unwrap();
+        let issues = broken.mesh_issues();
+        assert!(issues.contains(&MeshIssue::OrphanNode(100)));
+        assert!(issues.contains(&MeshIssue::ClockwiseElement(1)));
+        let land_sides: Vec<MeshIssue> = land
             .iter()
-            .enumerate()
-            .map(|(index, point)| (index as u32, (vec![point.x, point.y], None)))
+            .flat_map(|segment| segment.windows(2))
+            .map(|pair| {
+                MeshIssue::UnassignedBoundarySide(pair[0].min(pair[1]), pair[0].max(pair[1]))
+            })
             .collect();
-        log::debug!(
-            "Begin making nodes hash map took {:?} seconds.",
-            start.elapsed()
+        assert_eq!(land_sides.len(), 8);
+        assert!(land_sides.iter().all(|issue| issues.contains(issue)));
+        assert_eq!(issues.len(), 2 + land_sides.len());
+    }
+
+    fn node_at(hgrid: &Hgrid, x: f64, y: f64) -> u32 {
+        hgrid
+            .nodes()
+            .btree_map()
+            .iter()
+            .find(|(_node_id, (coord, _values))| {
+                (coord[0] - x).abs() < 1e-6 && (coord[1] - y).abs() < 1e-6
+            })
+            .map(|(&node_id, _)| node_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bathymetry_clamp() {
+        // Parabolic cross-section: 2 m at the banks, 7.12 m and 9.68 m in between.
+        let mut channel = RectangularChannelBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let original = channel.depths_btree_map();
+        let western_half = Polygon::new(
+            vec![
+                [-100., -100.],
+                [4_900., -100.],
+                [4_900., 1_100.],
+                [-100., 1_100.],
+            ],
+            vec![],
+        )
+        .unwrap();
+        let report = DepthClampBuilder::default()
+            .hmin(4.)
+            .hmax(8.)
+            .polygon(western_half)
+            .build()
+            .unwrap()
+            .apply(&mut channel)
+            .unwrap();
+        // 25 columns inside the polygon, each with two bank and two central nodes clamped.
+        assert_eq!(report.nodes_changed(), 100);
+        assert!((report.max_abs_change() - 2.).abs() < 1e-9);
+        let nodes = channel.nodes().btree_map();
+        for (node_id, value) in channel.depths_btree_map() {
+            if nodes[&node_id].0[0] < 4_900. {
+                assert!((-8. ..=-4.).contains(&value));
+            } else {
+                assert_eq!(value, original[&node_id]);
+            }
+        }
+        assert!(DepthClampBuilder::default().build().is_err());
+        assert!(DepthClampBuilder::default()
+            .hmin(5.)
+            .hmax(1.)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_bathymetry_smoothing() {
+        let mut channel = RectangularChannelBuilder::default()
+            .depth(Arc::new(|x: f64, y: f64| {
+                if (x - 5_000.).abs() < 1. && (y - 600.).abs() < 1. {
+                    20.
+                } else {
+                    10.
+                }
+            }))
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut flat = channel.clone();
+        let spike = node_at(&channel, 5_000., 600.);
+        let report = DepthSmoothingBuilder::default()
+            .build()
+            .unwrap()
+            .apply(&mut channel)
+            .unwrap();
+        // Half of the spike goes towards the flat mean of its neighbors, which in turn deepen.
+        let depths = channel.depths_btree_map();
+        assert!((depths[&spike] + 15.).abs() < 1e-9);
+        let neighbors = channel.node_neighbors()[&spike].clone();
+        assert_eq!(report.nodes_changed(), 1 + neighbors.len());
+        assert!(neighbors
+            .iter()
+            .all(|neighbor_id| depths[neighbor_id] < -10.));
+        flat.set_depths(
+            &flat
+                .depths_btree_map()
+                .keys()
+                .map(|&node_id| (node_id, -10.))
+                .collect(),
+        )
+        .unwrap();
+        let report = DepthSmoothingBuilder::default()
+            .method(SmoothingMethod::AreaWeighted)
+            .iterations(3)
+            .build()
+            .unwrap()
+            .apply(&mut flat)
+            .unwrap();
+        assert!(report.max_abs_change() < 1e-9);
+        assert!(DepthSmoothingBuilder::default().weight(0.).build().is_err());
+    }
+
+    #[test]
+    fn test_bathymetry_slope_limiter() {
+        // A 5 m shelf dropping to 50 m halfway along the channel.
+        let mut channel = RectangularChannelBuilder::default()
+            .depth(Arc::new(
+                |x: f64, _y: f64| if x < 5_000. { 5. } else { 50. },
+            ))
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let dry = node_at(&channel, 4_800., 400.);
+        channel.set_node_values(dry, None).unwrap();
+        let original = channel.depths_btree_map();
+        assert!(matches!(
+            SlopeLimiterBuilder::default()
+                .rx0_max(0.2)
+                .max_iterations(1)
+                .build()
+                .unwrap()
+                .apply(&mut channel.clone()),
+            Err(BathymetryEditError::SlopeLimiterDidNotConverge(_, 1))
+        ));
+        let report = SlopeLimiterBuilder::default()
+            .rx0_max(0.2)
+            .build()
+            .unwrap()
+            .apply(&mut channel)
+            .unwrap();
+        assert!(report.nodes_changed() > 0);
+        // Only the shelf is deepened and the node without a value is left alone.
+        assert!(report.changes().values().all(|&change| change > 0.));
+        assert!(report
+            .changes()
+            .keys()
+            .all(|node_id| original[node_id] == -5.));
+        assert_eq!(channel.nodes().btree_map()[&dry].1, None);
+        let depths = channel.depths_btree_map();
+        for (node_id, neighbors) in channel.node_neighbors() {
+            for neighbor_id in neighbors {
+                if let (Some(&a), Some(&b)) = (depths.get(&node_id), depths.get(&neighbor_id)) {
+                    assert!((a - b).abs() / (a + b).abs() <= 0.2 + 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_rules() {
+        let table = RuleValue::PiecewiseLinear(vec![[0., 1.], [10., 3.], [20., 4.]]);
+        assert_eq!(table.evaluate(-5.), 1.);
+        assert_eq!(table.evaluate(5.), 2.);
+        assert_eq!(table.evaluate(15.), 3.5);
+        assert_eq!(table.evaluate(25.), 4.);
+        let linear = RuleValue::Linear {
+            intercept: 1.,
+            slope: 0.5,
+        };
+        assert_eq!(linear.evaluate(4.), 3.);
+        let square = Polygon::new(
+            vec![
+                [-1_000., -1_000.],
+                [5_000., -1_000.],
+                [5_000., 2_000.],
+                [-1_000., 2_000.],
+            ],
+            vec![],
+        )
+        .unwrap();
+        let shallow = PropertyRuleBuilder::default()
+            .region(RuleRegion::DepthRange(0., 5.))
+            .value(RuleValue::Constant(2.))
+            .build()
+            .unwrap();
+        let west = PropertyRuleBuilder::default()
+            .region(RuleRegion::Polygon(square.clone()))
+            .value(RuleValue::Constant(3.))
+            .blend_width(400.)
+            .build()
+            .unwrap();
+        let rules = PropertyRulesBuilder::default()
+            .rules(vec![shallow, west])
+            .default_value(1.)
+            .build()
+            .unwrap();
+        // Later rules take precedence over earlier ones and over the default.
+        assert_eq!(rules.evaluate(8_000., 500., 8.), 1.);
+        assert_eq!(rules.evaluate(8_000., 500., 3.), 2.);
+        assert_eq!(rules.evaluate(1_000., 500., 3.), 3.);
+        assert_eq!(rules.evaluate(1_000., 500., 8.), 3.);
+        // 200 m inside a 400 m blend band weighs the polygon value and the previous one equally.
+        assert!((rules.evaluate(4_800., 500., 8.) - 2.).abs() < 1e-9);
+        assert!((rules.evaluate(4_800., 500., 3.) - 2.5).abs() < 1e-9);
+        let channel = RectangularChannelBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let node_values = rules.node_values(&channel);
+        assert_eq!(node_values.len(), channel.nodes().len());
+        // The channel is 2 m deep at its banks and at least 7 m deep elsewhere.
+        let nodes = channel.nodes().btree_map();
+        for (node_id, value) in node_values {
+            let (coord, _values) = &nodes[&node_id];
+            if coord[0] > 5_000. {
+                let bank = coord[1] < 1. || coord[1] > 999.;
+                assert_eq!(value, if bank { 2. } else { 1. });
+            }
+        }
+        assert_eq!(
+            rules.element_prop(&channel).len(),
+            channel.elements().btree_map().len()
         );
-        let transformer = Proj::new("epsg:4326").map(Arc::new).unwrap();
-        log::info!("Begin making nodes struct.");
-        let start = Instant::now();
-        let nodes = NodesBuilder::default()
-            .btree_map(nodes_btree_map)
-            .crs(transformer)
+        assert!(PropertyRuleBuilder::default()
+            .region(RuleRegion::DepthRange(5., 5.))
+            .value(RuleValue::Constant(1.))
+            .build()
+            .is_err());
+        assert!(PropertyRuleBuilder::default()
+            .region(RuleRegion::DepthRange(0., 5.))
+            .value(RuleValue::Constant(1.))
+            .blend_width(10.)
+            .build()
+            .is_err());
+        assert!(PropertyRuleBuilder::default()
+            .region(RuleRegion::Polygon(square))
+            .value(RuleValue::PiecewiseLinear(vec![[10., 1.], [5., 2.]]))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_prop_round_trip() {
+        let channel = RectangularChannelBuilder::default()
+            .nx(4)
+            .ny(2)
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let prop = Prop::from_element_centroids(&channel, |_element_id, centroid| {
+            (centroid[0] / 1_000.).floor()
+        });
+        let temp_file = NamedTempFile::new().unwrap();
+        prop.write(temp_file.path()).unwrap();
+        let parsed = Prop::from_path(temp_file.path(), &channel).unwrap();
+        assert_eq!(parsed, prop);
+        assert!(parsed.validate(&channel).is_ok());
+        let mut truncated = prop.btree_map().clone();
+        truncated.pop_last();
+        assert!(matches!(
+            Prop::new(&channel, truncated.clone()),
+            Err(PropError::ElementCountMismatch(8, 7))
+        ));
+        let mut renumbered = truncated;
+        renumbered.insert(100, 0.);
+        assert!(matches!(
+            Prop::new(&channel, renumbered),
+            Err(PropError::ElementIdMismatch(8, _, 100))
+        ));
+        std::fs::write(temp_file.path(), "1 0.\n3 0.\n").unwrap();
+        assert!(matches!(
+            Prop::from_path(temp_file.path(), &channel),
+            Err(PropError::ElementCountMismatch(8, 2))
+        ));
+        let shuffled: String = (1..=8)
+            .map(|index| format!("{} 0.\n", if index == 2 { 3 } else { index }))
+            .collect();
+        std::fs::write(temp_file.path(), shuffled).unwrap();
+        assert!(matches!(
+            Prop::from_path(temp_file.path(), &channel),
+            Err(PropError::ElementOrderMismatch(2, 3))
+        ));
+        std::fs::write(temp_file.path(), "1\n").unwrap();
+        assert!(matches!(
+            Prop::from_path(temp_file.path(), &channel),
+            Err(PropError::LineReadError(_, _))
+        ));
+    }
+
+    fn transect(name: &str, polyline: Vec<[f64; 2]>) -> FluxTransect {
+        FluxTransectBuilder::default()
+            .name(name)
+            .polyline(polyline)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fluxflag() {
+        // 50 x 5 quads of 200 m, so a transect across the channel splits one column on each side.
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let across = transect("across", vec![[5_000., -10.], [5_000., 1_010.]]);
+        let upstream = transect("upstream", vec![[1_000., -10.], [1_000., 1_010.]]);
+        let flux_flags = FluxFlags::new(&channel, &[across.clone(), upstream]).unwrap();
+        let reports = flux_flags.reports();
+        assert_eq!(reports[0].node_path.len(), 6);
+        assert!(reports.iter().all(|report| !report.crosses_land_boundary()));
+        let centroids = channel.element_centroids();
+        let flags = flux_flags.prop().btree_map();
+        for (element_id, centroid) in centroids.iter() {
+            let expected = match centroid[0] {
+                x if (x - 4_900.).abs() < 1. => 0.,
+                x if (x - 5_100.).abs() < 1. => 1.,
+                x if (x - 900.).abs() < 1. => 2.,
+                x if (x - 1_100.).abs() < 1. => 3.,
+                _ => -1.,
+            };
+            assert_eq!(flags[element_id], expected);
+        }
+        assert_eq!(reports[1].upstream_elements.len(), 5);
+        assert_eq!(flux_flags.flux_out_column(1), 3);
+        // Walking along the southern bank cuts across land nodes.
+        let bank = transect("bank", vec![[1_000., 0.], [3_000., 0.]]);
+        let flux_flags = FluxFlags::new(&channel, &[bank]).unwrap();
+        assert_eq!(flux_flags.land_crossings()[0].land_boundary_nodes.len(), 9);
+        assert!(matches!(
+            FluxFlags::new(&channel, &[across.clone(), across]),
+            Err(FluxFlagsError::OverlappingTransects(_, _, _))
+        ));
+        assert!(matches!(
+            FluxFlags::new(&channel, &[transect("dot", vec![[0., 0.], [10., 10.]])]),
+            Err(FluxFlagsError::DegenerateTransect(_))
+        ));
+        let empty = Hgrid::from_parts(
+            BTreeMap::new(),
+            None,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            FluxFlags::new(&empty, &[transect("empty", vec![[0., 0.], [1., 1.]])]),
+            Err(FluxFlagsError::EmptyHgrid)
+        ));
+        assert!(FluxTransectBuilder::default()
+            .name("single")
+            .polyline(vec![[0., 0.]])
             .build()
-            .map(Arc::new)
+            .is_err());
+    }
+
+    #[test]
+    fn test_distance_and_nudging() {
+        // Open at x = 0 and x = 10 km, so quads give exact along-mesh distances.
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
             .unwrap();
-        log::debug!(
-            "Making nodes struct data took {:?} seconds.",
-            start.elapsed()
+        let nodes = channel.nodes().btree_map();
+        let nearest_open = |node_id: &u32| {
+            let x = nodes[node_id].0[0];
+            f64::min(x, 10_000. - x)
+        };
+        for method in [DistanceMethod::AlongMesh, DistanceMethod::StraightLine] {
+            let distances = channel
+                .distance_to_boundary(&BoundarySelection::AllOpen, method)
+                .unwrap();
+            assert_eq!(distances.len(), nodes.len());
+            for (node_id, distance) in distances.iter() {
+                assert!((distance - nearest_open(node_id)).abs() < 1e-6);
+            }
+        }
+        let distances = channel
+            .distance_to_boundary(&BoundarySelection::Open(0), DistanceMethod::AlongMesh)
+            .unwrap();
+        assert!(distances
+            .values()
+            .all(|distance| *distance <= 10_000. + 1e-6));
+        assert_eq!(
+            distances
+                .values()
+                .filter(|distance| **distance == 0.)
+                .count(),
+            6
         );
-        log::info!("Begin Triangulation on mock data ({} nodes).", points.len());
-        let start = Instant::now();
-        let triangulation = triangulate(&points);
-        log::debug!(
-            "Triangulation of mock data took {:?} seconds.",
-            start.elapsed()
+        assert!(matches!(
+            channel.distance_to_boundary(&BoundarySelection::Open(2), DistanceMethod::AlongMesh),
+            Err(DistanceError::OpenBoundaryIndexOutOfRange(2, 2))
+        ));
+        assert!(matches!(
+            channel.distance_to_boundary(
+                &BoundarySelection::Nodes(vec![1_000_000]),
+                DistanceMethod::StraightLine
+            ),
+            Err(DistanceError::UnknownNode(1_000_000))
+        ));
+        let relaxation = NudgingRelaxationBuilder::default()
+            .max_relaxation_rate(2.)
+            .ramp_width(1_000.)
+            .build()
+            .unwrap();
+        for (node_id, value) in relaxation.node_values(&channel).unwrap() {
+            let expected = 2. * (1. - nearest_open(&node_id) / 1_000.).max(0.);
+            assert!((value - expected).abs() < 1e-9);
+        }
+        assert!(NudgingRelaxationBuilder::default()
+            .max_relaxation_rate(1.)
+            .ramp_width(0.)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_crs() {
+        for definition in ["EPSG:4326", "+proj=longlat +datum=WGS84 +no_defs"] {
+            let crs = Crs::new(definition).unwrap();
+            assert!(crs.is_geographic());
+            assert_eq!(crs.definition(), definition);
+        }
+        let mercator = Crs::new("EPSG:3857").unwrap();
+        assert!(!mercator.is_geographic());
+        let (x, y): (f64, f64) = mercator
+            .from_geographic()
+            .unwrap()
+            .convert((-75., 35.))
+            .unwrap();
+        assert!((x + 8_348_961.8).abs() < 1.);
+        let (lon, lat): (f64, f64) = mercator.to_geographic().unwrap().convert((x, y)).unwrap();
+        assert!((lon + 75.).abs() < 1e-9 && (lat - 35.).abs() < 1e-9);
+        assert!(matches!(
+            Crs::new("not a crs"),
+            Err(CrsError::InvalidCrs(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_windrot() {
+        // Web mercator keeps north up everywhere, so no rotation is needed.
+        let projected = RectangularChannelBuilder::default()
+            .origin([-8_348_961.8, 4_163_881.1])
+            .crs(Crs::new("EPSG:3857").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let angles = windrot_geo2proj(&projected).unwrap();
+        assert_eq!(angles.len(), projected.nodes().len());
+        assert!(angles.values().all(|angle| angle.abs() < 1e-6));
+        let geographic = RectangularChannelBuilder::default()
+            .length(1.)
+            .width(0.1)
+            .origin([-75., 35.])
+            .crs(Crs::new("EPSG:4326").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert!(windrot_geo2proj(&geographic)
+            .unwrap()
+            .values()
+            .all(|angle| *angle == 0.));
+        let unreferenced = RectangularChannelBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert!(matches!(
+            windrot_geo2proj(&unreferenced),
+            Err(WindrotError::CrsError(CrsError::MissingCrs))
+        ));
+    }
+
+    #[test]
+    fn test_reproject_round_trip() {
+        let gr3 = RectangularChannelBuilder::default()
+            .origin([-8_348_961.8, 4_163_881.1])
+            .crs(Crs::new("EPSG:3857").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let ll = to_hgrid_ll(&gr3).unwrap();
+        assert!(ll.is_geographic());
+        let (origin, _values) = &ll.nodes().btree_map()[&node_at(&gr3, -8_348_961.8, 4_163_881.1)];
+        assert!((origin[0] + 75.).abs() < 1e-6 && (origin[1] - 35.).abs() < 1e-6);
+        // The EPSG code survives the header of the written hgrid.ll.
+        let temp_file = NamedTempFile::new().unwrap();
+        ll.write(temp_file.path()).unwrap();
+        let parsed = Hgrid::try_from(&temp_file.path().to_path_buf()).unwrap();
+        assert_eq!(parsed.crs().unwrap().definition(), "EPSG:4326");
+        let report = HgridPairCheckBuilder::default()
+            .build()
+            .unwrap()
+            .check(&gr3, &parsed)
+            .unwrap();
+        assert!(report.is_consistent(), "{}", report);
+        let back = reproject(&parsed, "EPSG:3857").unwrap();
+        for (node_id, (coord, values)) in gr3.nodes().btree_map() {
+            let (back_coord, back_values) = &back.nodes().btree_map()[&node_id];
+            assert!((coord[0] - back_coord[0]).abs() < 1e-3);
+            assert!((coord[1] - back_coord[1]).abs() < 1e-3);
+            assert_eq!(&values, back_values);
+        }
+        assert_eq!(
+            back.boundaries().unwrap().to_boundary_type_map(),
+            gr3.boundaries().unwrap().to_boundary_type_map()
         );
-        log::info!("Begin making Elements btree_map.");
-        let start = Instant::now();
-        let elements_btree_map: BTreeMap<u32, Vec<u32>> = triangulation
-            .triangles
-            .chunks(3)
-            .enumerate()
-            .map(|(index, triangle)| {
-                let triangle_u32: Vec<u32> = triangle.iter().map(|&vert| vert as u32).collect();
-                (index as u32, triangle_u32)
+        // A node moved by 1 km in hgrid.ll is reported.
+        let mut nodes = ll.nodes().btree_map();
+        nodes.get_mut(&1).unwrap().0[0] += 0.01;
+        let mut moved = ll.clone();
+        moved.replace_nodes_and_crs(nodes, ll.crs()).unwrap();
+        let report = HgridPairCheckBuilder::default()
+            .build()
+            .unwrap()
+            .check(&gr3, &moved)
+            .unwrap();
+        assert_eq!(
+            report.mismatches(),
+            &vec![HgridPairMismatch::Coordinates(vec![1])]
+        );
+        assert!(matches!(
+            to_hgrid_ll(
+                &RectangularChannelBuilder::default()
+                    .build()
+                    .unwrap()
+                    .generate()
+                    .unwrap()
+            ),
+            Err(ReprojectError::CrsError(CrsError::MissingCrs))
+        ));
+    }
+
+    #[test]
+    fn test_hgrid_editor() {
+        // 4 x 2 quads of 2500 m x 500 m, open at x = 0 and x = 10 km.
+        let channel = RectangularChannelBuilder::default()
+            .nx(4)
+            .ny(2)
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let corner = node_at(&channel, 0., 0.);
+        let west = [node_at(&channel, 0., 500.), node_at(&channel, 0., 1_000.)];
+        let corner_element = *channel
+            .elements()
+            .btree_map()
+            .iter()
+            .find(|(_, node_ids)| node_ids.contains(&corner))
+            .unwrap()
+            .0;
+        // Removing the corner element takes its open and land sides out of the boundaries.
+        let mut editor = channel.edit();
+        editor.remove_element(corner_element).unwrap();
+        editor.remove_orphan_nodes();
+        let edited = editor.commit().unwrap();
+        assert_eq!(edited.misplaced_boundary_sides(), vec![]);
+        let boundaries = edited.boundaries().unwrap().to_boundary_type_map();
+        assert!(boundaries[&BoundaryType::Open]
+            .iter()
+            .any(|segment| segment.len() == 2 && segment.iter().all(|id| west.contains(id))));
+        assert!(!boundaries
+            .values()
+            .flatten()
+            .flatten()
+            .any(|&id| id == corner));
+        // Removing a node also detaches the far sides of the elements around it.
+        let mut editor = channel.edit();
+        editor.remove_node(node_at(&channel, 2_500., 0.)).unwrap();
+        editor.remove_orphan_nodes();
+        assert_eq!(editor.commit().unwrap().misplaced_boundary_sides(), vec![]);
+        // Boundaries across the mesh are rejected on commit.
+        let inner = [
+            node_at(&channel, 2_500., 0.),
+            node_at(&channel, 2_500., 500.),
+        ];
+        let mut editor = channel.edit();
+        let index = editor
+            .add_boundary(BoundaryType::Land, inner.to_vec())
+            .unwrap();
+        assert!(matches!(
+            editor.commit(),
+            Err(HgridEditError::InvalidBoundary(
+                MeshIssue::BoundarySideNotOnMeshBoundary(BoundaryType::Land, i, _, _)
+            )) if i == index
+        ));
+        let mut editor = channel.edit();
+        assert!(matches!(
+            editor.add_boundary(BoundaryType::Open, vec![corner]),
+            Err(HgridEditError::DegenerateBoundary(BoundaryType::Open, _))
+        ));
+        editor.add_boundary(BoundaryType::Interior, vec![]).unwrap();
+        editor.add_boundary(BoundaryType::Interior, vec![]).unwrap();
+        assert!(matches!(
+            editor.merge_boundaries(BoundaryType::Interior, 0, 1),
+            Err(HgridEditError::DegenerateBoundary(
+                BoundaryType::Interior,
+                0
+            ))
+        ));
+        assert!(matches!(
+            editor.retype_boundary(BoundaryType::Interior, 0, BoundaryType::Land),
+            Err(HgridEditError::DegenerateBoundary(
+                BoundaryType::Interior,
+                0
+            ))
+        ));
+        assert_eq!(editor.boundaries(BoundaryType::Interior).len(), 2);
+        // Splitting an open boundary and merging it back restores it.
+        let mut editor = channel.edit();
+        let open = editor.boundaries(BoundaryType::Open).to_vec();
+        editor
+            .split_boundary(BoundaryType::Open, 0, open[0][1])
+            .unwrap();
+        assert_eq!(editor.boundaries(BoundaryType::Open).len(), open.len() + 1);
+        editor.merge_boundaries(BoundaryType::Open, 0, 1).unwrap();
+        assert_eq!(editor.boundaries(BoundaryType::Open), open.as_slice());
+        assert!(editor.commit().is_ok());
+    }
+
+    #[test]
+    fn test_open_boundary_between() {
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        // A stretch of the southern bank splits its land segment in two.
+        let mut opened = channel.clone();
+        let start = node_at(&channel, 4_000., 0.);
+        let end = node_at(&channel, 6_000., 0.);
+        let index = opened.open_boundary_between(start, end).unwrap();
+        let boundaries = opened.boundaries().unwrap().to_boundary_type_map();
+        assert_eq!(boundaries[&BoundaryType::Open][index].len(), 11);
+        assert_eq!(boundaries[&BoundaryType::Land].len(), 3);
+        assert_eq!(opened.mesh_issues(), vec![]);
+        assert!(matches!(
+            opened.open_boundary_between(start, end),
+            Err(HgridEditError::OverlapsOpenBoundary(_, _))
+        ));
+        // A closed land ring is opened at the stretch rather than at its old closing node.
+        let mut editor = channel.edit();
+        editor.rebuild_boundaries(&|_, _| false).unwrap();
+        let ring = editor.boundary_rings().remove(0);
+        editor.open_boundary_between(ring[3], ring[6]).unwrap();
+        assert_eq!(editor.boundaries(BoundaryType::Land).len(), 1);
+        let land = &editor.boundaries(BoundaryType::Land)[0];
+        assert_eq!((land[0], land[land.len() - 1]), (ring[6], ring[3]));
+        assert_eq!(editor.commit().unwrap().mesh_issues(), vec![]);
+        // Opening part of an island leaves the rest of the island as a land segment.
+        let basin = IslandBasinBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut editor = basin.edit();
+        let island = editor.boundary_rings().remove(1);
+        let land_count = editor.boundaries(BoundaryType::Land).len();
+        editor.open_boundary_between(island[0], island[2]).unwrap();
+        assert!(editor.boundaries(BoundaryType::Interior).is_empty());
+        assert_eq!(editor.boundaries(BoundaryType::Land).len(), land_count + 1);
+        let island_land = editor.boundaries(BoundaryType::Land).last().unwrap();
+        assert_eq!(island_land.len(), island.len() - 1);
+        assert_eq!(editor.commit().unwrap().mesh_issues(), vec![]);
+        assert!(matches!(
+            channel.clone().open_boundary_between(start, start),
+            Err(HgridEditError::NotOnSameBoundaryRing(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_refinement() {
+        let quads = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let boundary_lengths = |hgrid: &Hgrid| -> Vec<usize> {
+            let boundaries = hgrid.boundaries().unwrap().to_boundary_type_map();
+            boundaries.values().flatten().map(Vec::len).collect()
+        };
+        assert_eq!(boundary_lengths(&quads), vec![6, 6, 51, 51]);
+        // Every quad gets a center node and every side a midpoint with interpolated values.
+        let refined = quads.refine_uniform().unwrap();
+        assert_eq!(refined.elements().btree_map().len(), 4 * 250);
+        assert_eq!(
+            refined.nodes().btree_map().len(),
+            quads.nodes().btree_map().len() + quads.side_elements().len() + 250
+        );
+        assert_eq!(boundary_lengths(&refined), vec![11, 11, 101, 101]);
+        assert_eq!(refined.mesh_issues(), vec![]);
+        let depths = refined.depths_btree_map();
+        let mean = (depths[&node_at(&refined, 0., 0.)] + depths[&node_at(&refined, 0., 200.)]) / 2.;
+        assert!((depths[&node_at(&refined, 0., 100.)] - mean).abs() < 1e-12);
+        let triangles = quads.split_quads().unwrap();
+        assert_eq!(triangles.elements().btree_map().len(), 2 * 250);
+        assert!(triangles
+            .elements()
+            .btree_map()
+            .values()
+            .all(|node_ids| node_ids.len() == 3));
+        assert_eq!(boundary_lengths(&triangles), vec![6, 6, 51, 51]);
+        assert_eq!(triangles.mesh_issues(), vec![]);
+        let refined = triangles.refine_uniform().unwrap();
+        assert_eq!(refined.elements().btree_map().len(), 4 * 500);
+        assert_eq!(refined.mesh_issues(), vec![]);
+        // Only the pairs forming the original squares reach the quality bound; pairs across cells
+        // make parallelograms with 45 degree corners.
+        let merged = triangles.merge_triangles(0.9).unwrap();
+        assert_eq!(merged.elements().btree_map().len(), 250);
+        assert!(merged
+            .elements()
+            .btree_map()
+            .values()
+            .all(|node_ids| node_ids.len() == 4));
+        assert_eq!(boundary_lengths(&merged), vec![6, 6, 51, 51]);
+        assert_eq!(merged.mesh_issues(), vec![]);
+        assert_eq!(
+            triangles
+                .merge_triangles(1.01)
+                .unwrap()
+                .elements()
+                .btree_map()
+                .len(),
+            500
+        );
+        // At 60N a degree of longitude is half a degree of latitude, so this rhombus in degrees is
+        // a square on the sphere and only merges when the quality is measured there.
+        let e = 0.01;
+        let nodes = BTreeMap::from([
+            (1, (vec![-2. * e, 60.], Some(vec![-5.]))),
+            (2, (vec![0., 60. - e], Some(vec![-5.]))),
+            (3, (vec![2. * e, 60.], Some(vec![-5.]))),
+            (4, (vec![0., 60. + e], Some(vec![-5.]))),
+        ]);
+        let elements = BTreeMap::from([(1, vec![1, 2, 3]), (2, vec![1, 3, 4])]);
+        let diamond = |crs: Option<Arc<Crs>>| {
+            Hgrid::from_parts(nodes.clone(), crs, elements.clone(), BTreeMap::new(), None).unwrap()
+        };
+        let geographic = diamond(Crs::new("EPSG:4326").map(Arc::new).ok());
+        let merged = geographic.merge_triangles(0.9).unwrap();
+        assert_eq!(merged.elements().btree_map()[&1].len(), 4);
+        assert!(merged
+            .mesh_issues()
+            .iter()
+            .all(|issue| matches!(issue, MeshIssue::UnassignedBoundarySide(_, _))));
+        let unreferenced = diamond(None);
+        assert_eq!(
+            unreferenced
+                .merge_triangles(0.9)
+                .unwrap()
+                .elements()
+                .btree_map()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_meshgen() {
+        let domain = Polygon::new(
+            vec![[0., 0.], [2_000., 0.], [2_000., 1_000.], [0., 1_000.]],
+            vec![vec![
+                [800., 400.],
+                [800., 600.],
+                [1_200., 600.],
+                [1_200., 400.],
+            ]],
+        )
+        .unwrap();
+        let generator = |size: SizeFunction| {
+            MeshGeneratorBuilder::default()
+                .domain(domain.clone())
+                .size(size)
+                .open_boundaries(vec![
+                    vec![[0., 1_000.], [0., 0.]],
+                    vec![[2_000., 0.], [2_000., 1_000.]],
+                ])
+                .depth(Arc::new(|_x: f64, _y: f64| 5.) as SpatialFunction)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+        };
+        let hgrid = generator(SizeFunction::Constant(100.));
+        assert_eq!(hgrid.mesh_issues(), vec![]);
+        let min_angle = hgrid
+            .element_angles(GeometryMethod::Planar)
+            .values()
+            .flatten()
+            .fold(180., |acc: f64, &angle| acc.min(angle));
+        assert!(min_angle >= 28.);
+        // Right triangles with legs of the target size are the largest elements left.
+        let lengths = hgrid.side_lengths(GeometryMethod::Planar);
+        assert!(lengths.values().all(|&length| length < 100. * 2f64.sqrt()));
+        assert!(hgrid.depths_btree_map().values().all(|&value| value == -5.));
+        let boundaries = hgrid.boundaries().unwrap().to_boundary_type_map();
+        assert_eq!(boundaries[&BoundaryType::Land].len(), 2);
+        assert_eq!(boundaries[&BoundaryType::Interior].len(), 1);
+        let nodes = hgrid.nodes().btree_map();
+        let open = &boundaries[&BoundaryType::Open];
+        assert_eq!(open.len(), 2);
+        for segment in open {
+            assert_eq!(segment.len(), 11);
+            let x = nodes[&segment[0]].0[0];
+            assert!(segment.iter().all(|node_id| nodes[node_id].0[0] == x));
+        }
+        // Elements grow away from the coast.
+        let graded = generator(SizeFunction::DistanceFromCoast {
+            min_size: 25.,
+            max_size: 200.,
+            growth_rate: 0.5,
+        });
+        assert_eq!(graded.mesh_issues(), vec![]);
+        let nodes = graded.nodes().btree_map();
+        let (mut coastal, mut offshore) = (Vec::new(), Vec::new());
+        for ((a, b), length) in graded.side_lengths(GeometryMethod::Planar) {
+            let (a, b) = (&nodes[&a].0, &nodes[&b].0);
+            let distance = domain.distance_to_boundary((a[0] + b[0]) / 2., (a[1] + b[1]) / 2.);
+            if distance < 50. {
+                coastal.push(length);
+            } else if distance > 150. {
+                offshore.push(length);
+            }
+        }
+        let mean = |lengths: &[f64]| lengths.iter().sum::<f64>() / lengths.len() as f64;
+        assert!(mean(&coastal) < 50.);
+        assert!(mean(&offshore) > 2. * mean(&coastal));
+        assert!(MeshGeneratorBuilder::default()
+            .domain(domain.clone())
+            .size(SizeFunction::Constant(100.))
+            .open_boundaries(vec![vec![[0., 0.]]])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_partition() {
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let partition = |hgrid: &Hgrid, nparts: usize, graph: PartitionGraph| {
+            PartitionerBuilder::default()
+                .nparts(nparts)
+                .graph(graph)
+                .build()
+                .unwrap()
+                .partition(hgrid)
+        };
+        // Three straight cuts across the 50 x 5 channel cut 15 sides; the bisection is allowed
+        // a few steps along them.
+        let parts = partition(&channel, 4, PartitionGraph::Elements).unwrap();
+        assert_eq!(parts.parts().len(), 250);
+        assert!(parts.edge_cut() >= 15 && parts.edge_cut_fraction() < 0.15);
+        assert!(parts.imbalance() <= 1.03);
+        assert!(parts.part_sizes().iter().all(|&size| size > 0));
+        let parts = partition(&channel, 3, PartitionGraph::Nodes).unwrap();
+        assert_eq!(parts.parts().len(), 306);
+        assert!(parts.edge_cut_fraction() < 0.1);
+        assert!(parts.imbalance() <= 1.03);
+        // On a strip of 6 triangles the bisections become one-sided, which must not leave any
+        // of the parts empty.
+        let strip = RectangularChannelBuilder::default()
+            .nx(3)
+            .ny(1)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        for nparts in 1..=6 {
+            let parts = partition(&strip, nparts, PartitionGraph::Elements).unwrap();
+            assert_eq!(parts.nparts(), nparts);
+            assert!(parts.part_sizes().iter().all(|&size| size > 0));
+        }
+        assert!(matches!(
+            partition(&strip, 7, PartitionGraph::Elements),
+            Err(PartitionError::TooManyParts(7, 6))
+        ));
+    }
+
+    #[test]
+    fn test_cfl() {
+        let flat = |length: f64, width: f64, crs: Option<Arc<Crs>>| {
+            let mut builder = RectangularChannelBuilder::default();
+            builder
+                .element_type(ElementType::Quad)
+                .length(length)
+                .width(width)
+                .depth(Arc::new(|_x: f64, _y: f64| 10.) as SpatialFunction);
+            if let Some(crs) = crs {
+                builder.crs(crs);
+            }
+            builder.build().unwrap().generate().unwrap()
+        };
+        let check = |hgrid: &Hgrid, dt: f64, characteristic_length: CharacteristicLength| {
+            CflCheckBuilder::default()
+                .dt(dt)
+                .velocity_scale(1.)
+                .characteristic_length(characteristic_length)
+                .build()
+                .unwrap()
+                .check(hgrid)
+        };
+        // 200 m squares, 10 m deep: sqrt(9.81 * 10) * 15 / 200 = 0.743.
+        let projected = flat(10_000., 1_000., None);
+        let report = check(&projected, 15., CharacteristicLength::SquareRootArea);
+        let expected = (9.81f64 * 10.).sqrt() * 15. / 200.;
+        assert!(report
+            .barotropic()
+            .values()
+            .all(|&cfl| (cfl - expected).abs() < 1e-9));
+        assert!(report
+            .advective()
+            .unwrap()
+            .values()
+            .all(|&cfl| (cfl - 15. / 200.).abs() < 1e-9));
+        assert!(report.flags().values().all(|&flag| flag == 0));
+        let report = check(&projected, 15., CharacteristicLength::EquivalentDiameter);
+        let diameter = 2. * (200f64 * 200. / std::f64::consts::PI).sqrt();
+        assert!(report
+            .lengths()
+            .values()
+            .all(|&length| (length - diameter).abs() < 1e-9));
+        let report = check(&projected, 30., CharacteristicLength::SquareRootArea);
+        assert_eq!(report.above_range().len(), 250);
+        let report = check(&projected, 5., CharacteristicLength::SquareRootArea);
+        assert_eq!(report.below_range().len(), 250);
+        // On EPSG:4326 the element lengths are in meters: 0.002 degree squares at the equator.
+        let geographic = flat(0.1, 0.01, Crs::new("EPSG:4326").map(Arc::new).ok());
+        let dx = EARTH_RADIUS * 0.002f64.to_radians();
+        let report = check(&geographic, 15., CharacteristicLength::ShortestSide);
+        assert!(report
+            .lengths()
+            .values()
+            .all(|&length| (length - dx).abs() < 1e-6 * dx));
+        let report = check(&geographic, 15., CharacteristicLength::SquareRootArea);
+        let expected = (9.81f64 * 10.).sqrt() * 15. / dx;
+        assert!(report
+            .barotropic()
+            .values()
+            .all(|&cfl| (cfl - expected).abs() < 1e-6 * expected));
+    }
+
+    #[test]
+    fn test_geographic_metric() {
+        // A 0.1 degree box at 40N split into 0.01 degree quads.
+        let hgrid = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .length(0.1)
+            .width(0.1)
+            .nx(10)
+            .ny(10)
+            .origin([-70., 40.])
+            .crs(Crs::new("EPSG:4326").map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert!(hgrid.is_geographic());
+        let expected = EARTH_RADIUS.powi(2)
+            * 0.1f64.to_radians()
+            * (40.1f64.to_radians().sin() - 40f64.to_radians().sin());
+        let total: f64 = hgrid.element_areas().values().sum();
+        assert!((total - expected).abs() < 1e-6 * expected);
+        let cpp: f64 = hgrid
+            .element_areas_with(GeometryMethod::Cpp {
+                lon0: -69.95,
+                lat0: 40.05,
             })
-            .collect();
-        log::debug!(
-            "making elements hash map took {:?} seconds.",
-            start.elapsed()
+            .values()
+            .sum();
+        assert!((cpp - expected).abs() < 1e-2 * expected);
+        let planar: f64 = hgrid
+            .element_areas_with(GeometryMethod::Planar)
+            .values()
+            .sum();
+        assert!((planar - 0.01).abs() < 1e-9);
+        let dy = EARTH_RADIUS * 0.01f64.to_radians();
+        let nodes = hgrid.nodes().btree_map();
+        for ((a, b), length) in hgrid.side_lengths(GeometryMethod::Auto) {
+            if nodes[&a].0[0] == nodes[&b].0[0] {
+                assert!((length - dy).abs() < 1e-6 * dy);
+            } else {
+                let dx = dy * nodes[&a].0[1].to_radians().cos();
+                assert!((length - dx).abs() < 1e-6 * dx);
+            }
+        }
+        assert!(hgrid
+            .element_angles(GeometryMethod::Auto)
+            .values()
+            .flatten()
+            .all(|&angle| (angle - 90.).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_element_locator() {
+        for element_type in [ElementType::Triangle, ElementType::Quad] {
+            let channel = RectangularChannelBuilder::default()
+                .element_type(element_type)
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap();
+            let locator = channel.element_locator();
+            let nodes = channel.nodes().btree_map();
+            let elements = channel.elements().btree_map();
+            // Linear interpolation reproduces the coordinates, also on sides and at nodes.
+            for point in [
+                [110., 70.],
+                [4_321., 987.],
+                [200., 100.],
+                [5_000., 600.],
+                [0., 0.],
+                [10_000., 1_000.],
+            ] {
+                let (element_id, weights) = locator.locate_with_weights(point).unwrap();
+                assert_eq!(
+                    weights
+                        .iter()
+                        .map(|(node_id, _)| *node_id)
+                        .collect::<Vec<_>>(),
+                    elements[&element_id]
+                );
+                assert!(weights.iter().all(|(_, weight)| *weight >= -1e-9));
+                for (axis, coordinate) in point.iter().enumerate() {
+                    let interpolated: f64 = weights
+                        .iter()
+                        .map(|(node_id, weight)| weight * nodes[node_id].0[axis])
+                        .sum();
+                    assert!((interpolated - coordinate).abs() < 1e-6);
+                }
+            }
+            // A point on a side between two elements only weighs the nodes of that side.
+            let (_, weights) = locator.locate_with_weights([200., 100.]).unwrap();
+            let side = [node_at(&channel, 200., 0.), node_at(&channel, 200., 200.)];
+            assert!(weights
+                .iter()
+                .all(|(node_id, weight)| side.contains(node_id) || weight.abs() < 1e-9));
+            for point in [
+                [-1., 500.],
+                [10_001., 500.],
+                [5_000., 1_000.5],
+                [5_000., -0.5],
+            ] {
+                assert_eq!(locator.locate(point), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_station_checks() {
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let stations = vec![
+            Station::new("mid", 5_100., 500., None),
+            Station::new("bank", 5_100., 100., None),
+            Station::new("offshore", -100., 420., Some(-1.)),
+        ];
+        let check = |hgrid: &Hgrid, snap: bool| {
+            StationFileBuilder::default()
+                .stations(stations.clone())
+                .min_depth(5.)
+                .snap(snap)
+                .build()
+                .unwrap()
+                .check(hgrid)
+        };
+        let report = check(&channel, false);
+        let checks = report.checks();
+        assert!(checks[0].is_valid());
+        // Halfway between the 9.68 m deep rows of nodes at y = 400 m and y = 600 m.
+        assert!((checks[0].depth().unwrap() - 9.68).abs() < 1e-9);
+        // The bank element touches the land boundary and is 2 to 4.88 m deep.
+        assert!(checks[1].issues().contains(&StationIssue::OnLandBoundary));
+        assert!(matches!(checks[1].issues()[1], StationIssue::Shallow(depth) if depth < 5.));
+        assert_eq!(checks[2].issues(), &vec![StationIssue::Outside]);
+        assert_eq!(checks[2].depth(), None);
+        assert_eq!(report.output_stations(), vec![stations[0].clone()]);
+        // The bank station moves to the nearest deep node off the land boundary and the offshore
+        // one to the nearest node on the open boundary.
+        let report = check(&channel, true);
+        assert_eq!(
+            report.checks()[1].snapped_to(),
+            Some(node_at(&channel, 5_000., 400.))
         );
-        log::info!("Begin making Elements object.");
-        let start = Instant::now();
-        let elements = ElementsBuilder::default()
-            .btree_map(elements_btree_map)
-            .nodes(nodes.clone())
+        assert_eq!(
+            report.checks()[2].snapped_to(),
+            Some(node_at(&channel, 0., 400.))
+        );
+        let output = report.output_stations();
+        assert_eq!(output[2].coord(), [0., 400.]);
+        assert_eq!(output[2].z(), -1.);
+        // Nodes without a depth are reported rather than taken as dry.
+        let mut nodes = channel.nodes().btree_map();
+        let middle = node_at(&channel, 5_000., 400.);
+        nodes.get_mut(&middle).unwrap().1 = None;
+        let patchy = Hgrid::from_parts(
+            nodes,
+            None,
+            channel.elements().btree_map(),
+            channel.boundaries().unwrap().to_boundary_type_map(),
+            None,
+        )
+        .unwrap();
+        let report = check(&patchy, false);
+        assert_eq!(
+            report.checks()[0].issues(),
+            &vec![StationIssue::MissingDepth]
+        );
+        assert_eq!(report.checks()[0].depth(), None);
+        let report = check(&patchy, true);
+        assert!(report.checks()[0]
+            .snapped_to()
+            .is_some_and(|node_id| node_id != middle));
+    }
+
+    #[test]
+    fn test_source_sink() {
+        let channel = RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let north = RiverBuilder::default()
+            .name("north")
+            .coord([3_100., 1_050.])
+            .discharge(vec![(0., 10.), (3_600., 20.), (7_200., 30.)])
             .build()
             .unwrap();
-        log::debug!(
-            "Making elements object data took {:?} seconds.",
-            start.elapsed()
+        let south = RiverBuilder::default()
+            .name("south")
+            .coord([7_100., -50.])
+            .discharge(vec![(0., 5.), (9_000., 5.)])
+            .temperature(vec![(0., 15.), (7_200., 17.)])
+            .build()
+            .unwrap();
+        let source_sink = SourceSink::new(&channel, &[north.clone(), south], 1_800., None).unwrap();
+        // Each river goes into the bank element 150 m from it; the series end with the shorter
+        // discharge series.
+        let locator = channel.element_locator();
+        let placements = source_sink.placements();
+        assert_eq!(
+            placements[0].element_id,
+            locator.locate([3_100., 900.]).unwrap()
         );
-        log::info!("Begin making Hgrid");
-        let hgrid = HgridBuilder::default()
-            .nodes(nodes)
-            .elements(elements)
-            .boundaries(None)
-            .description("mock hgrid grid NW ATL".to_owned())
+        assert_eq!(
+            placements[1].element_id,
+            locator.locate([7_100., 100.]).unwrap()
+        );
+        assert!(placements
+            .iter()
+            .all(|placement| (placement.distance - 150.).abs() < 1e-9));
+        assert_eq!(
+            source_sink.times(),
+            &vec![0., 1_800., 3_600., 5_400., 7_200.]
+        );
+        let temp_dir = tempfile::tempdir().unwrap();
+        source_sink.write(temp_dir.path()).unwrap();
+        let source_sink_in =
+            std::fs::read_to_string(temp_dir.path().join("source_sink.in")).unwrap();
+        let lines: Vec<&str> = source_sink_in.lines().collect();
+        assert_eq!(lines[0], "2 !total # of elements with sources");
+        assert_eq!(lines[1], format!("{} !north", placements[0].element_id));
+        assert_eq!(lines[2], format!("{} !south", placements[1].element_id));
+        assert_eq!(lines[4], "0 !total # of elements with sinks");
+        // vsource.th has one column per source, msource.th the temperatures of all sources
+        // followed by their salinities.
+        let vsource = TimeHistory::from_path(&temp_dir.path().join("vsource.th")).unwrap();
+        assert_eq!(vsource.rows()[1], vec![15., 5.]);
+        let msource = TimeHistory::from_path(&temp_dir.path().join("msource.th")).unwrap();
+        assert_eq!(msource.times(), source_sink.times());
+        assert_eq!(msource.rows()[1], vec![AMBIENT, 15.5, AMBIENT, AMBIENT]);
+        let neighbor = RiverBuilder::default()
+            .name("neighbor")
+            .coord([3_150., 1_020.])
+            .discharge(vec![(0., 1.), (7_200., 1.)])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            SourceSink::new(&channel, &[north.clone(), neighbor], 1_800., None),
+            Err(SourceSinkError::DuplicateElement(element_id, names))
+                if element_id == placements[0].element_id && names == vec!["north", "neighbor"]
+        ));
+        assert!(matches!(
+            SourceSink::new(&channel, &[north], 0., None),
+            Err(SourceSinkError::InvalidTimeStep(_))
+        ));
+        assert!(RiverBuilder::default()
+            .name("dry")
+            .coord([0., 0.])
+            .discharge(vec![(0., -1.)])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_time_history() {
+        let mut csv = NamedTempFile::new().unwrap();
+        writeln!(csv, "time, west, east").unwrap();
+        writeln!(csv, "2024-01-01 00:00:00, 0.0, 1.0").unwrap();
+        writeln!(csv, "2024-01-01T01:00:00, 1.0, 2.0").unwrap();
+        writeln!(csv, "2024-01-01 03:00, 3.0, 0.0").unwrap();
+        let th = TimeHistory::from_csv_path(csv.path(), None).unwrap();
+        assert_eq!(th.times(), &vec![0., 3_600., 10_800.]);
+        assert_eq!(th.rows()[2], vec![3., 0.]);
+        // Times count from the given start, here half an hour before the first row.
+        let start = parse_datetime("2023-12-31T23:30:00Z").unwrap();
+        let shifted = TimeHistory::from_csv_path(csv.path(), Some(start)).unwrap();
+        assert_eq!(shifted.times()[0], 1_800.);
+        // Resampling interpolates linearly and holds the last row past the end.
+        let resampled = th.resample(1_800., Some(12_600.)).unwrap();
+        assert_eq!(resampled.times().len(), 8);
+        assert_eq!(resampled.dt(), Some(1_800.));
+        assert_eq!(resampled.rows()[1], vec![0.5, 1.5]);
+        assert_eq!(resampled.rows()[3], vec![1.5, 1.5]);
+        assert_eq!(resampled.rows()[7], vec![3., 0.]);
+        assert_eq!(th.resample(3_600., None).unwrap().times().len(), 4);
+        assert!(matches!(
+            th.resample(0., None),
+            Err(ThError::InvalidTimeStep(_))
+        ));
+        let temp_file = NamedTempFile::new().unwrap();
+        resampled.write(temp_file.path()).unwrap();
+        assert_eq!(TimeHistory::from_path(temp_file.path()).unwrap(), resampled);
+        let mut bad = NamedTempFile::new().unwrap();
+        writeln!(bad, "time,west").unwrap();
+        writeln!(bad, "yesterday,1.0").unwrap();
+        assert!(matches!(
+            TimeHistory::from_csv_path(bad.path(), None),
+            Err(ThError::ParseError(_))
+        ));
+        // The channel has two open boundaries: both forced, or only the second one.
+        let channel = RectangularChannelBuilder::default()
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert!(th.validate(&channel).is_ok());
+        assert!(th.validate_with_mask(&channel, &[true, true]).is_ok());
+        let east = TimeHistory::from_columns(th.times().clone(), &[vec![1., 2., 0.]]).unwrap();
+        assert!(east.validate_with_mask(&channel, &[false, true]).is_ok());
+        assert!(matches!(
+            east.validate(&channel),
+            Err(ThError::ColumnCountMismatch(2, 1))
+        ));
+        assert!(matches!(
+            th.validate_with_mask(&channel, &[false, true]),
+            Err(ThError::ColumnCountMismatch(1, 2))
+        ));
+        assert!(matches!(
+            th.validate_with_mask(&channel, &[true]),
+            Err(ThError::BoundaryMaskMismatch(2, 1))
+        ));
+    }
+
+    #[test]
+    fn test_write_sample_nwatl_hgrid() {
+        let _ = pretty_env_logger::try_init();
+        let temp_file = NamedTempFile::new().unwrap();
+        let temp_path = temp_file.path();
+        let xmin = -98.00556;
+        let ymin = 8.534422;
+        let xmax = -60.040005;
+        let ymax = 45.831431;
+        log::info!("Begin making mock hgrid.");
+        let start = Instant::now();
+        let hgrid = RectangularChannelBuilder::default()
+            .length(xmax - xmin)
+            .width(ymax - ymin)
+            .nx(400)
+            .ny(400)
+            .origin([xmin, ymin])
+            .crs(Crs::new("epsg:4326").map(Arc::new).unwrap())
             .build()
+            .unwrap()
+            .generate()
             .unwrap();
-        log::debug!("Done making Hgrid!");
+        log::debug!("Making mock hgrid took {:?} seconds.", start.elapsed());
         log::info!("Begin writting Hgrid to {}", temp_path.display());
-        let _result = hgrid.write(temp_path);
+        hgrid.write(temp_path).unwrap();
         log::debug!("Done writting hgrid to {}", temp_path.display());
     }
 }
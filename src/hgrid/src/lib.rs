@@ -1,8 +1,32 @@
 pub use hgrid::Hgrid;
 pub use hgrid::HgridBuilder;
 
+pub mod bathymetry;
 pub mod boundaries;
+pub mod cfl;
+pub mod clip;
+pub mod crs;
+pub mod distance;
+pub mod editing;
 pub mod elements;
+pub mod fluxflag;
+pub mod geometry;
 pub mod gr3;
 pub mod hgrid;
+pub mod locate;
+pub mod meshgen;
 pub mod nodes;
+pub mod nudging;
+pub mod partition;
+pub mod prop;
+pub mod properties;
+pub mod quality;
+pub mod refinement;
+pub mod reproject;
+pub mod sms2dm;
+pub mod source_sink;
+pub mod stations;
+pub mod synthetic;
+pub mod th;
+pub mod validation;
+pub mod windrot;
@@ -0,0 +1,142 @@
+use super::hgrid::Hgrid;
+use std::collections::BTreeMap;
+
+// Relative tolerance for points that fall on element sides.
+const EDGE_TOLERANCE: f64 = 1e-9;
+
+fn barycentric(triangle: [[f64; 2]; 3], point: [f64; 2]) -> Option<[f64; 3]> {
+    let [a, b, c] = triangle;
+    let det = (b[1] - c[1]) * (a[0] - c[0]) + (c[0] - b[0]) * (a[1] - c[1]);
+    if det == 0. {
+        return None;
+    }
+    let wa = ((b[1] - c[1]) * (point[0] - c[0]) + (c[0] - b[0]) * (point[1] - c[1])) / det;
+    let wb = ((c[1] - a[1]) * (point[0] - c[0]) + (a[0] - c[0]) * (point[1] - c[1])) / det;
+    let wc = 1. - wa - wb;
+    [wa, wb, wc]
+        .iter()
+        .all(|&w| w >= -EDGE_TOLERANCE)
+        .then_some([wa, wb, wc])
+}
+
+// Uniform bucket grid over the element bounding boxes, to find the element that contains a
+// point without scanning the whole mesh.
+#[derive(Debug, Clone)]
+pub struct ElementLocator {
+    origin: [f64; 2],
+    cell_size: [f64; 2],
+    shape: [usize; 2],
+    buckets: Vec<Vec<u32>>,
+    elements: BTreeMap<u32, Vec<(u32, [f64; 2])>>,
+}
+
+impl ElementLocator {
+    pub fn new(hgrid: &Hgrid) -> Self {
+        let nodes = hgrid.nodes().btree_map();
+        let elements: BTreeMap<u32, Vec<(u32, [f64; 2])>> = hgrid
+            .elements()
+            .btree_map()
+            .into_iter()
+            .map(|(element_id, node_ids)| {
+                let vertices = node_ids
+                    .into_iter()
+                    .map(|node_id| {
+                        let (coord, _) = &nodes[&node_id];
+                        (node_id, [coord[0], coord[1]])
+                    })
+                    .collect();
+                (element_id, vertices)
+            })
+            .collect();
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for (coord, _) in nodes.values() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(coord[axis]);
+                max[axis] = max[axis].max(coord[axis]);
+            }
+        }
+        let side = (elements.len() as f64).sqrt().ceil().max(1.) as usize;
+        let shape = [side, side];
+        let cell_size =
+            [0, 1].map(|axis| ((max[axis] - min[axis]) / side as f64).max(f64::EPSILON));
+        let mut locator = Self {
+            origin: min,
+            cell_size,
+            shape,
+            buckets: vec![Vec::new(); side * side],
+            elements: BTreeMap::new(),
+        };
+        for (element_id, vertices) in elements.iter() {
+            let mut low = [usize::MAX; 2];
+            let mut high = [0; 2];
+            for (_, coord) in vertices {
+                let cell = locator.cell(*coord);
+                for axis in 0..2 {
+                    low[axis] = low[axis].min(cell[axis]);
+                    high[axis] = high[axis].max(cell[axis]);
+                }
+            }
+            for j in low[1]..=high[1] {
+                for i in low[0]..=high[0] {
+                    locator.buckets[j * shape[0] + i].push(*element_id);
+                }
+            }
+        }
+        locator.elements = elements;
+        locator
+    }
+
+    fn cell(&self, coord: [f64; 2]) -> [usize; 2] {
+        [0, 1].map(|axis| {
+            let index = ((coord[axis] - self.origin[axis]) / self.cell_size[axis]).floor();
+            (index.max(0.) as usize).min(self.shape[axis] - 1)
+        })
+    }
+
+    // Element containing the point together with the linear interpolation weight of each of
+    // its nodes. Quads are interpolated over the triangle of the (0, 2) diagonal that holds the
+    // point.
+    pub fn locate_with_weights(&self, point: [f64; 2]) -> Option<(u32, Vec<(u32, f64)>)> {
+        let outside = [0, 1].iter().any(|&axis| {
+            let extent = self.cell_size[axis] * self.shape[axis] as f64;
+            let margin = EDGE_TOLERANCE * extent.max(1.);
+            point[axis] < self.origin[axis] - margin
+                || point[axis] > self.origin[axis] + extent + margin
+        });
+        if outside {
+            return None;
+        }
+        let [i, j] = self.cell(point);
+        for element_id in &self.buckets[j * self.shape[0] + i] {
+            let vertices = &self.elements[element_id];
+            let triangles: &[[usize; 3]] = if vertices.len() == 3 {
+                &[[0, 1, 2]]
+            } else {
+                &[[0, 1, 2], [0, 2, 3]]
+            };
+            for triangle in triangles {
+                if let Some(weights) = barycentric(triangle.map(|k| vertices[k].1), point) {
+                    let mut node_weights: Vec<(u32, f64)> =
+                        vertices.iter().map(|(node_id, _)| (*node_id, 0.)).collect();
+                    for (&k, weight) in triangle.iter().zip(weights) {
+                        node_weights[k].1 = weight;
+                    }
+                    return Some((*element_id, node_weights));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn locate(&self, point: [f64; 2]) -> Option<u32> {
+        self.locate_with_weights(point)
+            .map(|(element_id, _)| element_id)
+    }
+}
+
+impl Hgrid {
+    pub fn element_locator(&self) -> ElementLocator {
+        ElementLocator::new(self)
+    }
+}
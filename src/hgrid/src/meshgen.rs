@@ -0,0 +1,314 @@
+use super::crs::Crs;
+use super::editing::HgridEditError;
+use super::geometry::{point_segment_distance, Polygon};
+use super::hgrid::{Hgrid, HgridTryFromError};
+use derive_builder::Builder;
+use spade::{
+    AngleLimit, ConstrainedDelaunayTriangulation, InsertionError, Point2, PositionInTriangulation,
+    RefinementParameters, Triangulation,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub type SpatialFunction = Arc<dyn Fn(f64, f64) -> f64 + Send + Sync>;
+
+#[derive(Clone)]
+pub enum SizeFunction {
+    Constant(f64),
+    // h = min_size + growth_rate * distance to the polygon boundary, capped at max_size.
+    DistanceFromCoast {
+        min_size: f64,
+        max_size: f64,
+        growth_rate: f64,
+    },
+    // h = scale * sqrt(depth), clamped to [min_size, max_size], so that the gravity wave
+    // resolution is uniform across the domain.
+    Depth {
+        scale: f64,
+        min_size: f64,
+        max_size: f64,
+        depth: SpatialFunction,
+    },
+    Custom(SpatialFunction),
+}
+
+impl fmt::Debug for SizeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeFunction::Constant(size) => write!(f, "Constant({})", size),
+            SizeFunction::DistanceFromCoast {
+                min_size,
+                max_size,
+                growth_rate,
+            } => write!(
+                f,
+                "DistanceFromCoast {{ min_size: {}, max_size: {}, growth_rate: {} }}",
+                min_size, max_size, growth_rate
+            ),
+            SizeFunction::Depth {
+                scale,
+                min_size,
+                max_size,
+                ..
+            } => write!(
+                f,
+                "Depth {{ scale: {}, min_size: {}, max_size: {} }}",
+                scale, min_size, max_size
+            ),
+            SizeFunction::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl SizeFunction {
+    pub fn evaluate(&self, domain: &Polygon, x: f64, y: f64) -> f64 {
+        match self {
+            SizeFunction::Constant(size) => *size,
+            SizeFunction::DistanceFromCoast {
+                min_size,
+                max_size,
+                growth_rate,
+            } => (min_size + growth_rate * domain.distance_to_boundary(x, y)).min(*max_size),
+            SizeFunction::Depth {
+                scale,
+                min_size,
+                max_size,
+                depth,
+            } => (scale * depth(x, y).max(0.).sqrt()).clamp(*min_size, *max_size),
+            SizeFunction::Custom(size) => size(x, y),
+        }
+    }
+}
+
+#[derive(Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct MeshGenerator {
+    domain: Polygon,
+    size: SizeFunction,
+    #[builder(default = "28.")]
+    min_angle: f64,
+    #[builder(default = "20")]
+    max_sizing_passes: usize,
+    #[builder(default = "1_000_000")]
+    max_additional_vertices: usize,
+    #[builder(setter(strip_option), default)]
+    depth: Option<SpatialFunction>,
+    // Polylines along the domain boundary that become open boundary segments; the rest of the
+    // exterior is land and every interior an island.
+    #[builder(default)]
+    open_boundaries: Vec<Vec<[f64; 2]>>,
+    #[builder(setter(strip_option), default)]
+    crs: Option<Arc<Crs>>,
+    #[builder(setter(into, strip_option), default)]
+    description: Option<String>,
+}
+
+impl MeshGeneratorBuilder {
+    pub fn validate(&self) -> Result<(), MeshGeneratorBuilderError> {
+        if let Some(min_angle) = self.min_angle {
+            if min_angle <= 0. || min_angle > 33. {
+                return Err(MeshGeneratorBuilderError::ValidationError(format!(
+                    "min_angle must be in (0, 33] degrees for the refinement to terminate but got {}",
+                    min_angle
+                )));
+            }
+        }
+        if let Some(SizeFunction::Constant(size)) = &self.size {
+            if *size <= 0. {
+                return Err(MeshGeneratorBuilderError::ValidationError(format!(
+                    "Constant size must be > 0. but got {}",
+                    size
+                )));
+            }
+        }
+        if let Some(SizeFunction::DistanceFromCoast {
+            min_size, max_size, ..
+        })
+        | Some(SizeFunction::Depth {
+            min_size, max_size, ..
+        }) = &self.size
+        {
+            if *min_size <= 0. || min_size > max_size {
+                return Err(MeshGeneratorBuilderError::ValidationError(format!(
+                    "Size bounds must satisfy 0 < min_size <= max_size but got [{}, {}]",
+                    min_size, max_size
+                )));
+            }
+        }
+        if let Some(open_boundaries) = &self.open_boundaries {
+            if let Some(polyline) = open_boundaries.iter().find(|polyline| polyline.len() < 2) {
+                return Err(MeshGeneratorBuilderError::ValidationError(format!(
+                    "Open boundary polylines need at least 2 points but got {}",
+                    polyline.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MeshGenerator {
+    fn size_at(&self, x: f64, y: f64) -> f64 {
+        self.size.evaluate(&self.domain, x, y)
+    }
+
+    // A side is open when both of its ends and its midpoint lie on the same open boundary
+    // polyline, to within a small fraction of the local element size.
+    fn is_open_side(&self, a: [f64; 2], b: [f64; 2]) -> bool {
+        let midpoint = [(a[0] + b[0]) / 2., (a[1] + b[1]) / 2.];
+        let tolerance = 1e-6 * self.size_at(midpoint[0], midpoint[1]).max(f64::EPSILON);
+        self.open_boundaries.iter().any(|polyline| {
+            [a, b, midpoint].iter().all(|&point| {
+                polyline
+                    .windows(2)
+                    .any(|pair| point_segment_distance(point, pair[0], pair[1]) <= tolerance)
+            })
+        })
+    }
+
+    // Splits every ring edge that is longer than the local element size so that the boundary
+    // resolution follows the size function.
+    fn densify(&self, ring: &[[f64; 2]]) -> Vec<Point2<f64>> {
+        let mut points = Vec::new();
+        for (local_index, start) in ring.iter().enumerate() {
+            let end = ring[(local_index + 1) % ring.len()];
+            let length = (end[0] - start[0]).hypot(end[1] - start[1]);
+            let midpoint = [(start[0] + end[0]) / 2., (start[1] + end[1]) / 2.];
+            let size = self.size_at(midpoint[0], midpoint[1]).max(f64::EPSILON);
+            let pieces = (length / size).ceil().max(1.) as usize;
+            for piece in 0..pieces {
+                let t = piece as f64 / pieces as f64;
+                points.push(Point2::new(
+                    start[0] + t * (end[0] - start[0]),
+                    start[1] + t * (end[1] - start[1]),
+                ));
+            }
+        }
+        points
+    }
+
+    pub fn generate(&self) -> Result<Hgrid, MeshGeneratorError> {
+        let mut cdt = ConstrainedDelaunayTriangulation::<Point2<f64>>::new();
+        cdt.add_constraint_edges(self.densify(self.domain.exterior()), true)?;
+        for interior in self.domain.interiors() {
+            cdt.add_constraint_edges(self.densify(interior), true)?;
+        }
+        // Chew-style sizing passes: split oversized faces at their circumcenter, skipping points
+        // that would land too close to the boundary or to an existing vertex so the angle
+        // refinement below is not driven by tiny features.
+        for _ in 0..self.max_sizing_passes {
+            let mut candidates: Vec<(f64, Point2<f64>)> = cdt
+                .inner_faces()
+                .filter_map(|face| {
+                    let [a, b, c] = face.positions();
+                    let centroid = Point2::new((a.x + b.x + c.x) / 3., (a.y + b.y + c.y) / 3.);
+                    if !self.domain.contains(centroid.x, centroid.y) {
+                        return None;
+                    }
+                    let longest = [(a, b), (b, c), (c, a)]
+                        .iter()
+                        .map(|(p, q)| (p.x - q.x).hypot(p.y - q.y))
+                        .fold(0., f64::max);
+                    (longest > self.size_at(centroid.x, centroid.y))
+                        .then(|| (longest, face.circumcenter()))
+                })
+                .collect();
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let mut inserted = 0;
+            for (_, point) in candidates {
+                if !self.domain.contains(point.x, point.y) {
+                    continue;
+                }
+                let clearance = 0.5 * self.size_at(point.x, point.y);
+                if self.domain.distance_to_boundary(point.x, point.y) < clearance {
+                    continue;
+                }
+                let PositionInTriangulation::OnFace(face) = cdt.locate(point) else {
+                    continue;
+                };
+                let too_close = cdt
+                    .face(face)
+                    .positions()
+                    .iter()
+                    .any(|vertex| (vertex.x - point.x).hypot(vertex.y - point.y) < clearance);
+                if too_close {
+                    continue;
+                }
+                cdt.insert(point)?;
+                inserted += 1;
+            }
+            if inserted == 0 {
+                break;
+            }
+        }
+        let result = cdt.refine(
+            RefinementParameters::<f64>::new()
+                .with_angle_limit(AngleLimit::from_deg(self.min_angle))
+                .with_max_additional_vertices(self.max_additional_vertices)
+                .exclude_outer_faces(true),
+        );
+        if !result.refinement_complete {
+            log::warn!(
+                "Mesh refinement stopped after {} additional vertices before reaching the angle limit",
+                self.max_additional_vertices
+            );
+        }
+        let excluded: HashSet<_> = result.excluded_faces.into_iter().collect();
+        let mut node_ids: BTreeMap<usize, u32> = BTreeMap::new();
+        let mut nodes = BTreeMap::new();
+        let mut elements = BTreeMap::new();
+        for face in cdt.inner_faces() {
+            if excluded.contains(&face.fix()) {
+                continue;
+            }
+            let mut element = Vec::with_capacity(3);
+            for vertex in face.vertices() {
+                let next_node_id = node_ids.len() as u32 + 1;
+                let node_id = *node_ids.entry(vertex.fix().index()).or_insert(next_node_id);
+                nodes.entry(node_id).or_insert_with(|| {
+                    let position = vertex.position();
+                    let values = self
+                        .depth
+                        .as_ref()
+                        .map(|depth| vec![-depth(position.x, position.y)]);
+                    (vec![position.x, position.y], values)
+                });
+                element.push(node_id);
+            }
+            elements.insert(elements.len() as u32 + 1, element);
+        }
+        let hgrid = Hgrid::from_parts(
+            nodes,
+            self.crs.clone(),
+            elements,
+            BTreeMap::new(),
+            self.description.clone(),
+        )?;
+        let nodes = hgrid.nodes().btree_map();
+        let coord = |node_id: u32| {
+            let (coord, _) = &nodes[&node_id];
+            [coord[0], coord[1]]
+        };
+        let mut editor = hgrid.edit();
+        editor.rebuild_boundaries(&|a, b| self.is_open_side(coord(a), coord(b)))?;
+        Ok(editor.commit()?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MeshGeneratorError {
+    #[error("Triangulation error: {0:?}")]
+    InsertionError(InsertionError),
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+    #[error(transparent)]
+    HgridEditError(#[from] HgridEditError),
+}
+
+impl From<InsertionError> for MeshGeneratorError {
+    fn from(error: InsertionError) -> Self {
+        MeshGeneratorError::InsertionError(error)
+    }
+}
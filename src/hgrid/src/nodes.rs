@@ -1,6 +1,6 @@
+use super::crs::Crs;
 use derive_builder::Builder;
 use ndarray::prelude::*;
-use proj::Proj;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
@@ -8,7 +8,7 @@ use std::sync::Arc;
 #[builder(setter(into))]
 pub struct Nodes {
     btree_map: BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>,
-    crs: Option<Arc<Proj>>,
+    crs: Option<Arc<Crs>>,
 }
 
 impl Nodes {
@@ -16,7 +16,7 @@ impl Nodes {
         self.btree_map.clone()
     }
 
-    pub fn crs(&self) -> Option<Arc<Proj>> {
+    pub fn crs(&self) -> Option<Arc<Crs>> {
         self.crs.clone()
     }
 
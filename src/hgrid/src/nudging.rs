@@ -0,0 +1,88 @@
+use super::distance::{BoundarySelection, DistanceError, DistanceMethod};
+use super::hgrid::Hgrid;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+// Relaxation rate decays linearly from max_relaxation_rate on the selected boundary to zero at
+// ramp_width, the layout expected for TEM_nudge.gr3/SAL_nudge.gr3 and sponge layers.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct NudgingRelaxation {
+    max_relaxation_rate: f64,
+    ramp_width: f64,
+    #[builder(default)]
+    boundary: BoundarySelection,
+    #[builder(default)]
+    method: DistanceMethod,
+}
+
+impl NudgingRelaxationBuilder {
+    pub fn validate(&self) -> Result<(), NudgingRelaxationBuilderError> {
+        if let Some(max_relaxation_rate) = self.max_relaxation_rate {
+            if max_relaxation_rate < 0. {
+                return Err(NudgingRelaxationBuilderError::ValidationError(format!(
+                    "max_relaxation_rate must be >= 0. but got {}",
+                    max_relaxation_rate
+                )));
+            }
+        }
+        if let Some(ramp_width) = self.ramp_width {
+            if ramp_width <= 0. {
+                return Err(NudgingRelaxationBuilderError::ValidationError(format!(
+                    "ramp_width must be > 0. but got {}",
+                    ramp_width
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NudgingRelaxation {
+    pub fn node_values(&self, hgrid: &Hgrid) -> Result<BTreeMap<u32, f64>, DistanceError> {
+        let distances = hgrid.distance_to_boundary(&self.boundary, self.method)?;
+        Ok(distances
+            .into_iter()
+            .map(|(node_id, distance)| {
+                let ramp = (1. - distance / self.ramp_width).clamp(0., 1.);
+                (node_id, self.max_relaxation_rate * ramp)
+            })
+            .collect())
+    }
+
+    pub fn write_gr3(
+        &self,
+        hgrid: &Hgrid,
+        path: &Path,
+        description: &str,
+    ) -> Result<(), NudgingRelaxationError> {
+        let values = self.node_values(hgrid)?;
+        hgrid.write_node_values(path, description, &values)?;
+        Ok(())
+    }
+
+    // Writes one <TRACER>_nudge.gr3 per tracer name (e.g. TEM, SAL) into directory.
+    pub fn write_nudge_files(
+        &self,
+        hgrid: &Hgrid,
+        directory: &Path,
+        tracers: &[&str],
+    ) -> Result<(), NudgingRelaxationError> {
+        let values = self.node_values(hgrid)?;
+        for tracer in tracers {
+            let filename = format!("{}_nudge.gr3", tracer);
+            hgrid.write_node_values(&directory.join(&filename), &filename, &values)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NudgingRelaxationError {
+    #[error(transparent)]
+    DistanceError(#[from] DistanceError),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
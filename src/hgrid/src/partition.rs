@@ -0,0 +1,678 @@
+use super::hgrid::Hgrid;
+use super::prop::Prop;
+use derive_builder::Builder;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PartitionGraph {
+    // Elements connected through shared sides, as SCHISM decomposes the domain.
+    #[default]
+    Elements,
+    // Nodes connected through element sides.
+    Nodes,
+}
+
+// Weighted undirected graph with vertices numbered 0..n.
+#[derive(Debug, Clone)]
+struct Graph {
+    vertex_weights: Vec<f64>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+}
+
+impl Graph {
+    fn len(&self) -> usize {
+        self.vertex_weights.len()
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.vertex_weights.iter().sum()
+    }
+
+    fn cut(&self, sides: &[bool]) -> f64 {
+        let mut cut = 0.;
+        for (vertex, neighbors) in self.adjacency.iter().enumerate() {
+            for &(neighbor, weight) in neighbors {
+                if vertex < neighbor && sides[vertex] != sides[neighbor] {
+                    cut += weight;
+                }
+            }
+        }
+        cut
+    }
+
+    // Heavy-edge matching: every vertex is paired with the unmatched neighbor it shares the
+    // heaviest edge with, visiting light-degree vertices first.
+    fn coarsen(&self) -> (Graph, Vec<usize>) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by_key(|&vertex| self.adjacency[vertex].len());
+        let mut coarse_index = vec![usize::MAX; self.len()];
+        let mut coarse_len = 0;
+        for vertex in order {
+            if coarse_index[vertex] != usize::MAX {
+                continue;
+            }
+            coarse_index[vertex] = coarse_len;
+            let partner = self.adjacency[vertex]
+                .iter()
+                .filter(|(neighbor, _)| coarse_index[*neighbor] == usize::MAX)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some(&(partner, _)) = partner {
+                coarse_index[partner] = coarse_len;
+            }
+            coarse_len += 1;
+        }
+        let mut vertex_weights = vec![0.; coarse_len];
+        let mut edges: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); coarse_len];
+        for (vertex, neighbors) in self.adjacency.iter().enumerate() {
+            let coarse_vertex = coarse_index[vertex];
+            vertex_weights[coarse_vertex] += self.vertex_weights[vertex];
+            for &(neighbor, weight) in neighbors {
+                let coarse_neighbor = coarse_index[neighbor];
+                if coarse_neighbor != coarse_vertex {
+                    *edges[coarse_vertex].entry(coarse_neighbor).or_default() += weight;
+                }
+            }
+        }
+        let adjacency = edges
+            .into_iter()
+            .map(|edges| edges.into_iter().collect())
+            .collect();
+        (
+            Graph {
+                vertex_weights,
+                adjacency,
+            },
+            coarse_index,
+        )
+    }
+
+    fn subgraph(&self, vertices: &[usize]) -> Graph {
+        let mut local_index = vec![usize::MAX; self.len()];
+        for (index, &vertex) in vertices.iter().enumerate() {
+            local_index[vertex] = index;
+        }
+        Graph {
+            vertex_weights: vertices
+                .iter()
+                .map(|&vertex| self.vertex_weights[vertex])
+                .collect(),
+            adjacency: vertices
+                .iter()
+                .map(|&vertex| {
+                    self.adjacency[vertex]
+                        .iter()
+                        .filter(|(neighbor, _)| local_index[*neighbor] != usize::MAX)
+                        .map(|&(neighbor, weight)| (local_index[neighbor], weight))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    // Vertex reached last by a breadth-first search from start, used to seed region growing from
+    // the periphery of the graph.
+    fn farthest_from(&self, start: usize) -> usize {
+        let mut visited = vec![false; self.len()];
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+        let mut last = start;
+        while let Some(vertex) = queue.pop_front() {
+            last = vertex;
+            for &(neighbor, _) in &self.adjacency[vertex] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        last
+    }
+}
+
+#[derive(PartialEq)]
+struct Gain(f64, usize);
+
+impl Eq for Gain {}
+
+impl PartialOrd for Gain {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Gain {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+// Splits a graph in two so that the weight of the `true` side is close to target_fraction of the
+// total.
+struct Bisection<'a> {
+    target_fraction: f64,
+    tolerance: f64,
+    partitioner: &'a Partitioner,
+}
+
+impl Bisection<'_> {
+    fn target(&self, graph: &Graph) -> (f64, f64) {
+        let total = graph.total_weight();
+        let target = self.target_fraction * total;
+        let max_vertex_weight = graph.vertex_weights.iter().copied().fold(0., f64::max);
+        let slack = ((self.tolerance - 1.) * target.min(total - target)).max(max_vertex_weight);
+        (target, slack)
+    }
+
+    fn bisect(&self, graph: &Graph) -> Vec<bool> {
+        if graph.len() <= COARSEST_GRAPH_SIZE {
+            return self.initial_bisection(graph);
+        }
+        let (coarse, coarse_index) = graph.coarsen();
+        if coarse.len() as f64 > 0.95 * graph.len() as f64 {
+            return self.initial_bisection(graph);
+        }
+        let coarse_sides = self.bisect(&coarse);
+        let mut sides: Vec<bool> = coarse_index
+            .iter()
+            .map(|&coarse_vertex| coarse_sides[coarse_vertex])
+            .collect();
+        self.refine(graph, &mut sides);
+        sides
+    }
+
+    // Greedy graph growing: the `true` region grows from a seed vertex, always absorbing the
+    // frontier vertex that adds the least to the cut, until it holds its share of the weight.
+    fn initial_bisection(&self, graph: &Graph) -> Vec<bool> {
+        let (target, _) = self.target(graph);
+        let mut best: Option<(f64, Vec<bool>)> = None;
+        let mut seed = graph.farthest_from(0);
+        for _ in 0..self.partitioner.initial_trials.max(1) {
+            let mut sides = vec![false; graph.len()];
+            let mut weight = 0.;
+            let mut gains: Vec<f64> = graph
+                .adjacency
+                .iter()
+                .map(|neighbors| -neighbors.iter().map(|(_, weight)| weight).sum::<f64>())
+                .collect();
+            let mut heap = BinaryHeap::from([Gain(gains[seed], seed)]);
+            let mut next_unvisited = 0;
+            while weight < target {
+                let vertex = match heap.pop() {
+                    Some(Gain(gain, vertex)) => {
+                        if sides[vertex] || gain != gains[vertex] {
+                            continue;
+                        }
+                        vertex
+                    }
+                    None => {
+                        while next_unvisited < graph.len() && sides[next_unvisited] {
+                            next_unvisited += 1;
+                        }
+                        if next_unvisited == graph.len() {
+                            break;
+                        }
+                        next_unvisited
+                    }
+                };
+                if weight + graph.vertex_weights[vertex] - target > target - weight {
+                    break;
+                }
+                sides[vertex] = true;
+                weight += graph.vertex_weights[vertex];
+                for &(neighbor, edge_weight) in &graph.adjacency[vertex] {
+                    if !sides[neighbor] {
+                        gains[neighbor] += 2. * edge_weight;
+                        heap.push(Gain(gains[neighbor], neighbor));
+                    }
+                }
+            }
+            self.refine(graph, &mut sides);
+            let cut = graph.cut(&sides);
+            if best.as_ref().is_none_or(|(best_cut, _)| cut < *best_cut) {
+                best = Some((cut, sides));
+            }
+            seed = graph.farthest_from(seed);
+        }
+        best.map(|(_, sides)| sides).unwrap_or_default()
+    }
+
+    // Fiduccia-Mattheyses passes: vertices move one at a time to the other side in order of
+    // decreasing cut reduction while the balance constraint holds, and every pass is rolled back
+    // to its best prefix.
+    fn refine(&self, graph: &Graph, sides: &mut [bool]) {
+        let (target, slack) = self.target(graph);
+        let gain_of = |sides: &[bool], vertex: usize| {
+            graph.adjacency[vertex]
+                .iter()
+                .map(|&(neighbor, weight)| {
+                    if sides[neighbor] == sides[vertex] {
+                        -weight
+                    } else {
+                        weight
+                    }
+                })
+                .sum::<f64>()
+        };
+        let imbalance = |weight: f64| ((weight - target).abs() - slack).max(0.);
+        let mut weight: f64 = (0..graph.len())
+            .filter(|&vertex| sides[vertex])
+            .map(|vertex| graph.vertex_weights[vertex])
+            .sum();
+        for _ in 0..self.partitioner.refinement_passes {
+            let mut gains: Vec<f64> = (0..graph.len()).map(|v| gain_of(sides, v)).collect();
+            let mut heap: BinaryHeap<Gain> = (0..graph.len())
+                .filter(|&vertex| {
+                    graph.adjacency[vertex]
+                        .iter()
+                        .any(|&(neighbor, _)| sides[neighbor] != sides[vertex])
+                })
+                .map(|vertex| Gain(gains[vertex], vertex))
+                .collect();
+            let mut locked = vec![false; graph.len()];
+            let mut moves = Vec::new();
+            let mut cut_change = 0.;
+            let mut best = (imbalance(weight), 0., 0);
+            while let Some(Gain(gain, vertex)) = heap.pop() {
+                if locked[vertex] || gain != gains[vertex] {
+                    continue;
+                }
+                let vertex_weight = graph.vertex_weights[vertex];
+                let new_weight = if sides[vertex] {
+                    weight - vertex_weight
+                } else {
+                    weight + vertex_weight
+                };
+                if imbalance(new_weight) > imbalance(weight) && imbalance(new_weight) > 0. {
+                    continue;
+                }
+                locked[vertex] = true;
+                sides[vertex] = !sides[vertex];
+                weight = new_weight;
+                cut_change -= gain;
+                moves.push(vertex);
+                for &(neighbor, edge_weight) in &graph.adjacency[vertex] {
+                    if locked[neighbor] {
+                        continue;
+                    }
+                    gains[neighbor] += if sides[neighbor] == sides[vertex] {
+                        -2. * edge_weight
+                    } else {
+                        2. * edge_weight
+                    };
+                    heap.push(Gain(gains[neighbor], neighbor));
+                }
+                let score = (imbalance(weight), cut_change, moves.len());
+                if score.0 < best.0 || (score.0 == best.0 && score.1 < best.1) {
+                    best = score;
+                }
+                if moves.len() - best.2 > MAX_MOVES_WITHOUT_IMPROVEMENT {
+                    break;
+                }
+            }
+            for &vertex in &moves[best.2..] {
+                sides[vertex] = !sides[vertex];
+                if sides[vertex] {
+                    weight += graph.vertex_weights[vertex];
+                } else {
+                    weight -= graph.vertex_weights[vertex];
+                }
+            }
+            if best.2 == 0 {
+                break;
+            }
+        }
+    }
+}
+
+const COARSEST_GRAPH_SIZE: usize = 100;
+const MAX_MOVES_WITHOUT_IMPROVEMENT: usize = 100;
+
+// Multilevel recursive bisection in the spirit of METIS: the graph is coarsened by heavy-edge
+// matching, bisected by greedy graph growing, and refined with Fiduccia-Mattheyses passes while
+// it is projected back. Every bisection splits the weight in proportion to the number of parts
+// on either side, so nparts need not be a power of two.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct Partitioner {
+    nparts: usize,
+    #[builder(default)]
+    graph: PartitionGraph,
+    // Per-node computational weights, e.g. the number of vertical levels at each node. Element
+    // vertices take the mean over their nodes. Nodes missing from the map weigh 1.
+    #[builder(setter(strip_option), default)]
+    node_weights: Option<BTreeMap<u32, f64>>,
+    // Largest acceptable ratio between the heaviest part and the average part.
+    #[builder(default = "1.03")]
+    imbalance_tolerance: f64,
+    #[builder(default = "4")]
+    initial_trials: usize,
+    #[builder(default = "8")]
+    refinement_passes: usize,
+}
+
+impl PartitionerBuilder {
+    pub fn validate(&self) -> Result<(), PartitionerBuilderError> {
+        if let Some(nparts) = self.nparts {
+            if nparts == 0 {
+                return Err(PartitionerBuilderError::ValidationError(
+                    "nparts must be > 0".to_string(),
+                ));
+            }
+        }
+        if let Some(imbalance_tolerance) = self.imbalance_tolerance {
+            if imbalance_tolerance < 1. {
+                return Err(PartitionerBuilderError::ValidationError(format!(
+                    "imbalance_tolerance must be >= 1. but got {}",
+                    imbalance_tolerance
+                )));
+            }
+        }
+        if let Some(Some(node_weights)) = &self.node_weights {
+            if let Some((node_id, weight)) = node_weights
+                .iter()
+                .find(|(_, weight)| !weight.is_finite() || **weight < 0.)
+            {
+                return Err(PartitionerBuilderError::ValidationError(format!(
+                    "Node weights must be finite and >= 0. but node {} has weight {}",
+                    node_id, weight
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Partitioner {
+    fn node_weight(&self, node_id: &u32) -> f64 {
+        self.node_weights
+            .as_ref()
+            .and_then(|node_weights| node_weights.get(node_id).copied())
+            .unwrap_or(1.)
+    }
+
+    fn build_graph(&self, hgrid: &Hgrid) -> (Vec<u32>, Graph) {
+        match self.graph {
+            PartitionGraph::Elements => {
+                let elements = hgrid.elements().btree_map();
+                let ids: Vec<u32> = elements.keys().copied().collect();
+                let index: BTreeMap<u32, usize> = ids
+                    .iter()
+                    .enumerate()
+                    .map(|(local_index, &element_id)| (element_id, local_index))
+                    .collect();
+                let vertex_weights = elements
+                    .values()
+                    .map(|node_ids| {
+                        node_ids
+                            .iter()
+                            .map(|node_id| self.node_weight(node_id))
+                            .sum::<f64>()
+                            / node_ids.len() as f64
+                    })
+                    .collect();
+                let mut adjacency = vec![Vec::new(); ids.len()];
+                for element_ids in hgrid.side_elements().values() {
+                    if let [first, second] = element_ids[..] {
+                        adjacency[index[&first]].push((index[&second], 1.));
+                        adjacency[index[&second]].push((index[&first], 1.));
+                    }
+                }
+                (
+                    ids,
+                    Graph {
+                        vertex_weights,
+                        adjacency,
+                    },
+                )
+            }
+            PartitionGraph::Nodes => {
+                let neighbors = hgrid.node_neighbors();
+                let ids: Vec<u32> = neighbors.keys().copied().collect();
+                let index: BTreeMap<u32, usize> = ids
+                    .iter()
+                    .enumerate()
+                    .map(|(local_index, &node_id)| (node_id, local_index))
+                    .collect();
+                let vertex_weights = ids
+                    .iter()
+                    .map(|node_id| self.node_weight(node_id))
+                    .collect();
+                let adjacency = neighbors
+                    .values()
+                    .map(|node_ids| {
+                        node_ids
+                            .iter()
+                            .map(|node_id| (index[node_id], 1.))
+                            .collect()
+                    })
+                    .collect();
+                (
+                    ids,
+                    Graph {
+                        vertex_weights,
+                        adjacency,
+                    },
+                )
+            }
+        }
+    }
+
+    fn recursive_bisection(
+        &self,
+        graph: &Graph,
+        vertices: Vec<usize>,
+        nparts: usize,
+        first_part: usize,
+        parts: &mut [usize],
+    ) {
+        if nparts == 1 || vertices.len() <= 1 {
+            for vertex in vertices {
+                parts[vertex] = first_part;
+            }
+            return;
+        }
+        let left_nparts = nparts / 2;
+        let subgraph = graph.subgraph(&vertices);
+        // Imbalances compound through the levels of recursion, so each level gets its share of
+        // the overall tolerance.
+        let levels = (self.nparts as f64).log2().ceil().max(1.);
+        let bisection = Bisection {
+            target_fraction: left_nparts as f64 / nparts as f64,
+            tolerance: self.imbalance_tolerance.powf(1. / levels),
+            partitioner: self,
+        };
+        let sides = bisection.bisect(&subgraph);
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for (vertex, side) in vertices.into_iter().zip(sides) {
+            if side {
+                left.push(vertex);
+            } else {
+                right.push(vertex);
+            }
+        }
+        // A one-sided bisection of a small or disconnected subgraph would leave parts empty, so
+        // each side takes vertices from the other until it has one per part it is split into.
+        Self::fill_side(graph, &mut left, &mut right, left_nparts);
+        Self::fill_side(graph, &mut right, &mut left, nparts - left_nparts);
+        self.recursive_bisection(graph, left, left_nparts, first_part, parts);
+        self.recursive_bisection(
+            graph,
+            right,
+            nparts - left_nparts,
+            first_part + left_nparts,
+            parts,
+        );
+    }
+
+    // Moves vertices from `other` to `side` until `side` has `count` of them, taking those with the
+    // most edges into `side` first.
+    fn fill_side(graph: &Graph, side: &mut Vec<usize>, other: &mut Vec<usize>, count: usize) {
+        while side.len() < count {
+            let Some((local_index, _)) = other.iter().enumerate().max_by_key(|(_, &vertex)| {
+                graph.adjacency[vertex]
+                    .iter()
+                    .filter(|(neighbor, _)| side.contains(neighbor))
+                    .count()
+            }) else {
+                return;
+            };
+            side.push(other.swap_remove(local_index));
+        }
+    }
+
+    pub fn partition(&self, hgrid: &Hgrid) -> Result<Partition, PartitionError> {
+        let (ids, graph) = self.build_graph(hgrid);
+        if self.nparts > graph.len() {
+            return Err(PartitionError::TooManyParts(self.nparts, graph.len()));
+        }
+        let mut parts = vec![0; graph.len()];
+        self.recursive_bisection(
+            &graph,
+            (0..graph.len()).collect(),
+            self.nparts,
+            0,
+            &mut parts,
+        );
+        let mut part_weights = vec![0.; self.nparts];
+        let mut part_sizes = vec![0; self.nparts];
+        for (vertex, &part) in parts.iter().enumerate() {
+            part_weights[part] += graph.vertex_weights[vertex];
+            part_sizes[part] += 1;
+        }
+        let mut edge_cut = 0;
+        let mut edges = 0;
+        for (vertex, neighbors) in graph.adjacency.iter().enumerate() {
+            for &(neighbor, _) in neighbors {
+                if vertex < neighbor {
+                    edges += 1;
+                    if parts[vertex] != parts[neighbor] {
+                        edge_cut += 1;
+                    }
+                }
+            }
+        }
+        Ok(Partition {
+            graph: self.graph,
+            parts: ids.into_iter().zip(parts).collect(),
+            part_weights,
+            part_sizes,
+            edge_cut,
+            edges,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Partition {
+    graph: PartitionGraph,
+    parts: BTreeMap<u32, usize>,
+    part_weights: Vec<f64>,
+    part_sizes: Vec<usize>,
+    edge_cut: usize,
+    edges: usize,
+}
+
+impl Partition {
+    pub fn graph(&self) -> PartitionGraph {
+        self.graph
+    }
+
+    // Zero-based part (MPI rank) of every element or node id.
+    pub fn parts(&self) -> &BTreeMap<u32, usize> {
+        &self.parts
+    }
+
+    pub fn nparts(&self) -> usize {
+        self.part_weights.len()
+    }
+
+    pub fn part_weights(&self) -> &Vec<f64> {
+        &self.part_weights
+    }
+
+    pub fn part_sizes(&self) -> &Vec<usize> {
+        &self.part_sizes
+    }
+
+    // Number of graph edges (shared sides for elements, sides for nodes) between different parts.
+    pub fn edge_cut(&self) -> usize {
+        self.edge_cut
+    }
+
+    pub fn edge_cut_fraction(&self) -> f64 {
+        if self.edges == 0 {
+            return 0.;
+        }
+        self.edge_cut as f64 / self.edges as f64
+    }
+
+    // Heaviest part weight over the average part weight; 1 is a perfect balance.
+    pub fn imbalance(&self) -> f64 {
+        let total: f64 = self.part_weights.iter().sum();
+        if total == 0. {
+            return 1.;
+        }
+        let max = self.part_weights.iter().copied().fold(0., f64::max);
+        max * self.nparts() as f64 / total
+    }
+
+    // Writes the part of each element as a .prop file, or of each node as a .gr3 file.
+    pub fn write(&self, hgrid: &Hgrid, path: &Path) -> std::io::Result<()> {
+        let values = self
+            .parts
+            .iter()
+            .map(|(&id, &part)| (id, part as f64))
+            .collect();
+        match self.graph {
+            PartitionGraph::Elements => Prop::from(values).write(path),
+            PartitionGraph::Nodes => hgrid.write_node_values(path, "partition", &values),
+        }
+    }
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let graph = match self.graph {
+            PartitionGraph::Elements => "elements",
+            PartitionGraph::Nodes => "nodes",
+        };
+        writeln!(
+            f,
+            "{} parts over {} {}",
+            self.nparts(),
+            self.parts.len(),
+            graph
+        )?;
+        writeln!(
+            f,
+            "edge cut: {} of {} edges ({:.2}%)",
+            self.edge_cut,
+            self.edges,
+            100. * self.edge_cut_fraction()
+        )?;
+        writeln!(f, "imbalance: {:.4}", self.imbalance())?;
+        write!(f, "{:>6} {:>10} {:>14}", "part", graph, "weight")?;
+        for (part, (size, weight)) in self
+            .part_sizes
+            .iter()
+            .zip(self.part_weights.iter())
+            .enumerate()
+        {
+            write!(f, "\n{:>6} {:>10} {:>14.2}", part, size, weight)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PartitionError {
+    #[error("Cannot split {1} graph vertices into {0} parts")]
+    TooManyParts(usize, usize),
+}
@@ -0,0 +1,171 @@
+use super::hgrid::Hgrid;
+use ndarray::Array1;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::Path;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prop {
+    btree_map: BTreeMap<u32, f64>,
+}
+
+impl Prop {
+    pub fn new(hgrid: &Hgrid, btree_map: BTreeMap<u32, f64>) -> Result<Self, PropError> {
+        let prop = Self { btree_map };
+        prop.validate(hgrid)?;
+        Ok(prop)
+    }
+
+    pub fn from_element_centroids<F>(hgrid: &Hgrid, f: F) -> Self
+    where
+        F: Fn(u32, [f64; 2]) -> f64,
+    {
+        let btree_map = hgrid
+            .element_centroids()
+            .into_iter()
+            .map(|(element_id, centroid)| (element_id, f(element_id, centroid)))
+            .collect();
+        Self { btree_map }
+    }
+
+    pub fn from_path(path: &Path, hgrid: &Hgrid) -> Result<Self, PropError> {
+        let rows = parse_from_path_ref(path)?;
+        let element_ids: Vec<u32> = hgrid.elements().btree_map().keys().copied().collect();
+        if rows.len() != element_ids.len() {
+            return Err(PropError::ElementCountMismatch(
+                element_ids.len(),
+                rows.len(),
+            ));
+        }
+        let mut btree_map = BTreeMap::new();
+        for (local_index, ((fortran_index, value), element_id)) in
+            rows.into_iter().zip(element_ids).enumerate()
+        {
+            if fortran_index as usize != local_index + 1 {
+                return Err(PropError::ElementOrderMismatch(
+                    local_index + 1,
+                    fortran_index,
+                ));
+            }
+            btree_map.insert(element_id, value);
+        }
+        Ok(Self { btree_map })
+    }
+
+    pub fn validate(&self, hgrid: &Hgrid) -> Result<(), PropError> {
+        let elements = hgrid.elements().btree_map();
+        if elements.len() != self.btree_map.len() {
+            return Err(PropError::ElementCountMismatch(
+                elements.len(),
+                self.btree_map.len(),
+            ));
+        }
+        for (local_index, (element_id, prop_element_id)) in
+            elements.keys().zip(self.btree_map.keys()).enumerate()
+        {
+            if element_id != prop_element_id {
+                return Err(PropError::ElementIdMismatch(
+                    local_index + 1,
+                    *element_id,
+                    *prop_element_id,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn btree_map(&self) -> &BTreeMap<u32, f64> {
+        &self.btree_map
+    }
+
+    pub fn values(&self) -> Array1<f64> {
+        self.btree_map.values().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.btree_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.btree_map.is_empty()
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut tmpfile = NamedTempFile::new()?;
+        for (local_index, value) in self.btree_map.values().enumerate() {
+            writeln!(tmpfile, "{} {}", local_index + 1, value)?;
+        }
+        tmpfile.persist(path)?;
+        Ok(())
+    }
+}
+
+impl From<BTreeMap<u32, f64>> for Prop {
+    fn from(btree_map: BTreeMap<u32, f64>) -> Self {
+        Self { btree_map }
+    }
+}
+
+pub fn parse_from_path_ref(path: &Path) -> Result<Vec<(u32, f64)>, PropError> {
+    let fname = path.display().to_string();
+    let file = File::open(path).map_err(|e| PropError::IoError(fname.clone(), e.to_string()))?;
+    let reader = BufReader::new(file);
+    let mut rows = Vec::new();
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| PropError::LineReadError(fname.clone(), e.to_string()))?;
+        let mut split_line = line.split_whitespace();
+        let fortran_index = match split_line.next() {
+            Some(value) => value.parse::<u32>().map_err(|_| {
+                PropError::LineReadError(
+                    fname.clone(),
+                    format!(
+                        "Expected first item in line {} (element id) to be castable to an u32 but found {}.",
+                        line_index + 1,
+                        value
+                    ),
+                )
+            })?,
+            None => continue,
+        };
+        let value = match split_line.next() {
+            Some(value) => value.parse::<f64>().map_err(|_| {
+                PropError::LineReadError(
+                    fname.clone(),
+                    format!(
+                        "Expected second item in line {} (element value) to be castable to an f64 but found {}.",
+                        line_index + 1,
+                        value
+                    ),
+                )
+            })?,
+            None => {
+                return Err(PropError::LineReadError(
+                    fname.clone(),
+                    format!(
+                        "Expected line {} to contain two items but found only one.",
+                        line_index + 1
+                    ),
+                ))
+            }
+        };
+        rows.push((fortran_index, value));
+    }
+    Ok(rows)
+}
+
+#[derive(Error, Debug)]
+pub enum PropError {
+    #[error("Error opening {0}: {1}")]
+    IoError(String, String),
+    #[error("Line read error: file {0}, error: {1}")]
+    LineReadError(String, String),
+    #[error("Expected {0} elements but the prop has {1}")]
+    ElementCountMismatch(usize, usize),
+    #[error("Expected element id {0} in prop file but found {1}")]
+    ElementOrderMismatch(usize, u32),
+    #[error("Element {0} of the hgrid has id {1} but the prop has id {2}")]
+    ElementIdMismatch(usize, u32, u32),
+}
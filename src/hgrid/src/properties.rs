@@ -0,0 +1,193 @@
+use super::geometry::Polygon;
+use super::hgrid::Hgrid;
+use super::prop::Prop;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub enum RuleRegion {
+    Polygon(Polygon),
+    DepthRange(f64, f64),
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleValue {
+    Constant(f64),
+    Linear { intercept: f64, slope: f64 },
+    PiecewiseLinear(Vec<[f64; 2]>),
+}
+
+impl RuleValue {
+    pub fn evaluate(&self, depth: f64) -> f64 {
+        match self {
+            RuleValue::Constant(value) => *value,
+            RuleValue::Linear { intercept, slope } => intercept + slope * depth,
+            RuleValue::PiecewiseLinear(table) => {
+                // PropertyRuleBuilder rejects empty tables, but the enum can be built without it.
+                let (Some(&first), Some(&last)) = (table.first(), table.last()) else {
+                    return f64::NAN;
+                };
+                if depth <= first[0] {
+                    return first[1];
+                }
+                if depth >= last[0] {
+                    return last[1];
+                }
+                for pair in table.windows(2) {
+                    let [depth_a, value_a] = pair[0];
+                    let [depth_b, value_b] = pair[1];
+                    if depth >= depth_a && depth <= depth_b {
+                        let zrat = (depth - depth_a) / (depth_b - depth_a);
+                        return value_a + (value_b - value_a) * zrat;
+                    }
+                }
+                last[1]
+            }
+        }
+    }
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct PropertyRule {
+    region: RuleRegion,
+    value: RuleValue,
+    #[builder(setter(strip_option), default)]
+    blend_width: Option<f64>,
+}
+
+impl PropertyRuleBuilder {
+    pub fn validate(&self) -> Result<(), PropertyRuleBuilderError> {
+        if let Some(RuleRegion::DepthRange(min_depth, max_depth)) = &self.region {
+            if min_depth >= max_depth {
+                return Err(PropertyRuleBuilderError::ValidationError(format!(
+                    "Depth range minimum must be smaller than its maximum but got [{}, {})",
+                    min_depth, max_depth
+                )));
+            }
+        }
+        if let Some(RuleValue::PiecewiseLinear(table)) = &self.value {
+            if table.is_empty() {
+                return Err(PropertyRuleBuilderError::ValidationError(
+                    "Piecewise linear depth table must not be empty".to_string(),
+                ));
+            }
+            if !table.windows(2).all(|pair| pair[0][0] < pair[1][0]) {
+                return Err(PropertyRuleBuilderError::ValidationError(
+                    "Piecewise linear depth table must be strictly increasing in depth".to_string(),
+                ));
+            }
+        }
+        if let Some(Some(blend_width)) = self.blend_width {
+            if blend_width <= 0. {
+                return Err(PropertyRuleBuilderError::ValidationError(format!(
+                    "blend_width must be > 0. but got {}",
+                    blend_width
+                )));
+            }
+            if let Some(RuleRegion::DepthRange(_, _)) = &self.region {
+                return Err(PropertyRuleBuilderError::ValidationError(
+                    "blend_width is only supported for polygon rules".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PropertyRule {
+    fn weight(&self, x: f64, y: f64, depth: f64) -> f64 {
+        match &self.region {
+            RuleRegion::DepthRange(min_depth, max_depth) => {
+                if depth >= *min_depth && depth < *max_depth {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            RuleRegion::Polygon(polygon) => {
+                if !polygon.contains(x, y) {
+                    return 0.;
+                }
+                match self.blend_width {
+                    Some(blend_width) => {
+                        (polygon.distance_to_boundary(x, y) / blend_width).clamp(0., 1.)
+                    }
+                    None => 1.,
+                }
+            }
+        }
+    }
+
+    fn apply(&self, x: f64, y: f64, depth: f64, previous: f64) -> f64 {
+        let weight = self.weight(x, y, depth);
+        if weight == 0. {
+            return previous;
+        }
+        weight * self.value.evaluate(depth) + (1. - weight) * previous
+    }
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(setter(into))]
+pub struct PropertyRules {
+    rules: Vec<PropertyRule>,
+    default_value: f64,
+}
+
+impl PropertyRules {
+    pub fn rules(&self) -> &Vec<PropertyRule> {
+        &self.rules
+    }
+
+    pub fn evaluate(&self, x: f64, y: f64, depth: f64) -> f64 {
+        self.rules.iter().fold(self.default_value, |value, rule| {
+            rule.apply(x, y, depth, value)
+        })
+    }
+
+    pub fn node_values(&self, hgrid: &Hgrid) -> BTreeMap<u32, f64> {
+        let depths = hgrid.depths_btree_map();
+        hgrid
+            .nodes()
+            .btree_map()
+            .iter()
+            .map(|(&node_id, (coord, _values))| {
+                let depth = -depths.get(&node_id).copied().unwrap_or(0.);
+                (node_id, self.evaluate(coord[0], coord[1], depth))
+            })
+            .collect()
+    }
+
+    pub fn element_values(&self, hgrid: &Hgrid) -> BTreeMap<u32, f64> {
+        let depths = hgrid.depths_btree_map();
+        let centroids = hgrid.element_centroids();
+        hgrid
+            .elements()
+            .btree_map()
+            .iter()
+            .map(|(&element_id, node_ids)| {
+                let depth = -node_ids
+                    .iter()
+                    .map(|node_id| depths.get(node_id).copied().unwrap_or(0.))
+                    .sum::<f64>()
+                    / node_ids.len() as f64;
+                let centroid = centroids[&element_id];
+                (element_id, self.evaluate(centroid[0], centroid[1], depth))
+            })
+            .collect()
+    }
+
+    pub fn write_gr3(&self, hgrid: &Hgrid, path: &Path, description: &str) -> std::io::Result<()> {
+        hgrid.write_node_values(path, description, &self.node_values(hgrid))
+    }
+
+    pub fn element_prop(&self, hgrid: &Hgrid) -> Prop {
+        Prop::from(self.element_values(hgrid))
+    }
+
+    pub fn write_prop(&self, hgrid: &Hgrid, path: &Path) -> std::io::Result<()> {
+        self.element_prop(hgrid).write(path)
+    }
+}
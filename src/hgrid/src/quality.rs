@@ -0,0 +1,261 @@
+use super::geometry::GeometryMethod;
+use super::hgrid::Hgrid;
+use super::prop::Prop;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementQuality {
+    pub area: f64,
+    pub min_angle: f64,
+    pub max_angle: f64,
+    // Equiangle skewness: 0 for an equilateral triangle or a square, 1 for a degenerate element.
+    pub skewness: f64,
+    // Longest over shortest side.
+    pub aspect_ratio: f64,
+}
+
+impl ElementQuality {
+    fn new(area: f64, angles: &[f64], sides: &[f64]) -> Self {
+        let ideal = 180. - 360. / angles.len() as f64;
+        let min_angle = angles.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_angle = angles.iter().copied().fold(0., f64::max);
+        let skewness = ((max_angle - ideal) / (180. - ideal)).max((ideal - min_angle) / ideal);
+        let shortest = sides.iter().copied().fold(f64::INFINITY, f64::min);
+        let longest = sides.iter().copied().fold(0., f64::max);
+        Self {
+            area,
+            min_angle,
+            max_angle,
+            skewness: skewness.clamp(0., 1.),
+            aspect_ratio: if shortest > 0. {
+                longest / shortest
+            } else {
+                f64::INFINITY
+            },
+        }
+    }
+}
+
+pub type QualityMetric = fn(&ElementQuality) -> f64;
+
+pub const QUALITY_METRICS: [(&str, QualityMetric); 5] = [
+    ("area", |quality| quality.area),
+    ("minimum angle", |quality| quality.min_angle),
+    ("maximum angle", |quality| quality.max_angle),
+    ("skewness", |quality| quality.skewness),
+    ("aspect ratio", |quality| quality.aspect_ratio),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityIssue {
+    NonPositiveArea(f64),
+    SmallAngle(f64),
+    LargeAngle(f64),
+    Skewed(f64),
+    Elongated(f64),
+}
+
+impl fmt::Display for QualityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QualityIssue::NonPositiveArea(area) => write!(f, "area {:e}", area),
+            QualityIssue::SmallAngle(angle) => write!(f, "minimum angle {:.2} degrees", angle),
+            QualityIssue::LargeAngle(angle) => write!(f, "maximum angle {:.2} degrees", angle),
+            QualityIssue::Skewed(skewness) => write!(f, "skewness {:.3}", skewness),
+            QualityIssue::Elongated(aspect_ratio) => {
+                write!(f, "aspect ratio {:.2}", aspect_ratio)
+            }
+        }
+    }
+}
+
+// Per-element shape metrics, flagging elements outside of the given angle, skewness and aspect
+// ratio limits.
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct QualityCheck {
+    #[builder(default)]
+    method: GeometryMethod,
+    #[builder(default = "10.")]
+    min_angle: f64,
+    #[builder(default = "150.")]
+    max_angle: f64,
+    #[builder(default = "0.8")]
+    max_skewness: f64,
+    #[builder(default = "10.")]
+    max_aspect_ratio: f64,
+}
+
+impl QualityCheckBuilder {
+    pub fn validate(&self) -> Result<(), QualityCheckBuilderError> {
+        let min_angle = self.min_angle.unwrap_or(10.);
+        let max_angle = self.max_angle.unwrap_or(150.);
+        if min_angle < 0. || min_angle > max_angle || max_angle > 180. {
+            return Err(QualityCheckBuilderError::ValidationError(format!(
+                "Angle limits must satisfy 0 <= min_angle <= max_angle <= 180 but got [{}, {}]",
+                min_angle, max_angle
+            )));
+        }
+        if let Some(max_skewness) = self.max_skewness {
+            if !(0. ..=1.).contains(&max_skewness) {
+                return Err(QualityCheckBuilderError::ValidationError(format!(
+                    "max_skewness must be in [0, 1] but got {}",
+                    max_skewness
+                )));
+            }
+        }
+        if let Some(max_aspect_ratio) = self.max_aspect_ratio {
+            if max_aspect_ratio < 1. {
+                return Err(QualityCheckBuilderError::ValidationError(format!(
+                    "max_aspect_ratio must be >= 1. but got {}",
+                    max_aspect_ratio
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl QualityCheck {
+    pub fn check(&self, hgrid: &Hgrid) -> QualityReport {
+        let areas = hgrid.element_areas_with(self.method);
+        let angles = hgrid.element_angles(self.method);
+        let side_lengths = hgrid.side_lengths(self.method);
+        let elements = hgrid
+            .elements()
+            .btree_map()
+            .into_iter()
+            .map(|(element_id, node_ids)| {
+                let n = node_ids.len();
+                let sides: Vec<f64> = (0..n)
+                    .map(|i| {
+                        let (a, b) = (node_ids[i], node_ids[(i + 1) % n]);
+                        side_lengths[&(a.min(b), a.max(b))]
+                    })
+                    .collect();
+                let quality = ElementQuality::new(areas[&element_id], &angles[&element_id], &sides);
+                (element_id, quality)
+            })
+            .collect();
+        QualityReport {
+            elements,
+            check: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualitySummary {
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl QualitySummary {
+    fn new(values: impl Iterator<Item = f64>) -> Option<Self> {
+        let values: Vec<f64> = values.filter(|value| value.is_finite()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        Some(Self {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+impl fmt::Display for QualitySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {:.3}, mean {:.3}, max {:.3}",
+            self.min, self.mean, self.max
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QualityReport {
+    elements: BTreeMap<u32, ElementQuality>,
+    check: QualityCheck,
+}
+
+impl QualityReport {
+    pub fn elements(&self) -> &BTreeMap<u32, ElementQuality> {
+        &self.elements
+    }
+
+    pub fn issues(&self, quality: &ElementQuality) -> Vec<QualityIssue> {
+        let check = &self.check;
+        let mut issues = Vec::new();
+        if quality.area <= 0. {
+            issues.push(QualityIssue::NonPositiveArea(quality.area));
+        }
+        if quality.min_angle < check.min_angle {
+            issues.push(QualityIssue::SmallAngle(quality.min_angle));
+        }
+        if quality.max_angle > check.max_angle {
+            issues.push(QualityIssue::LargeAngle(quality.max_angle));
+        }
+        if quality.skewness > check.max_skewness {
+            issues.push(QualityIssue::Skewed(quality.skewness));
+        }
+        if quality.aspect_ratio > check.max_aspect_ratio {
+            issues.push(QualityIssue::Elongated(quality.aspect_ratio));
+        }
+        issues
+    }
+
+    pub fn flagged(&self) -> BTreeMap<u32, Vec<QualityIssue>> {
+        self.elements
+            .iter()
+            .map(|(&element_id, quality)| (element_id, self.issues(quality)))
+            .filter(|(_, issues)| !issues.is_empty())
+            .collect()
+    }
+
+    pub fn summary<F>(&self, metric: F) -> Option<QualitySummary>
+    where
+        F: Fn(&ElementQuality) -> f64,
+    {
+        QualitySummary::new(self.elements.values().map(metric))
+    }
+
+    pub fn skewness_prop(&self) -> Prop {
+        Prop::from(
+            self.elements
+                .iter()
+                .map(|(&element_id, quality)| (element_id, quality.skewness))
+                .collect::<BTreeMap<u32, f64>>(),
+        )
+    }
+
+    pub fn write_skewness(&self, path: &Path) -> std::io::Result<()> {
+        self.skewness_prop().write(path)
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "elements: {}", self.elements.len())?;
+        for (name, metric) in QUALITY_METRICS {
+            if let Some(summary) = self.summary(metric) {
+                writeln!(f, "{}: {}", name, summary)?;
+            }
+        }
+        let flagged = self.flagged();
+        write!(f, "flagged elements: {}", flagged.len())?;
+        for (element_id, issues) in flagged.iter().take(20) {
+            let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+            write!(f, "\n  element {}: {}", element_id, issues.join(", "))?;
+        }
+        if flagged.len() > 20 {
+            write!(f, "\n  ... and {} more", flagged.len() - 20)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use super::boundaries::BoundaryType;
+use super::geometry::{GeometryMethod, Metric};
+use super::hgrid::{Hgrid, HgridTryFromError};
+use std::collections::{BTreeMap, BTreeSet};
+
+type NodeMap = BTreeMap<u32, (Vec<f64>, Option<Vec<f64>>)>;
+
+fn side_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+fn mean_node(nodes: &NodeMap, node_ids: &[u32]) -> (Vec<f64>, Option<Vec<f64>>) {
+    let count = node_ids.len() as f64;
+    let mut coord = vec![0., 0.];
+    for node_id in node_ids {
+        let (this_coord, _) = &nodes[node_id];
+        coord[0] += this_coord[0] / count;
+        coord[1] += this_coord[1] / count;
+    }
+    let values: Option<Vec<Vec<f64>>> = node_ids
+        .iter()
+        .map(|node_id| nodes[node_id].1.clone())
+        .collect();
+    let values = values.and_then(|values| {
+        let length = values[0].len();
+        if values.iter().any(|v| v.len() != length) {
+            return None;
+        }
+        Some(
+            (0..length)
+                .map(|i| values.iter().map(|v| v[i]).sum::<f64>() / count)
+                .collect(),
+        )
+    });
+    (coord, values)
+}
+
+fn boundary_type_map(hgrid: &Hgrid) -> BTreeMap<BoundaryType, Vec<Vec<u32>>> {
+    hgrid
+        .boundaries()
+        .map(|boundaries| boundaries.to_boundary_type_map())
+        .unwrap_or_default()
+}
+
+// 1 for a rectangle, decreasing linearly to 0 as the worst corner angle deviates 90 degrees from
+// a right angle. Corner angles and convexity are measured under the given metric, so that quads
+// of geographic meshes are judged on the sphere rather than in degrees.
+pub fn quad_quality(metric: &Metric, coords: &[[f64; 2]]) -> f64 {
+    let n = coords.len();
+    let turns: Vec<f64> = (0..n)
+        .map(|i| metric.signed_polygon_area(&[coords[i], coords[(i + 1) % n], coords[(i + 2) % n]]))
+        .collect();
+    if !(turns.iter().all(|&s| s > 0.) || turns.iter().all(|&s| s < 0.)) {
+        return 0.;
+    }
+    let worst = (0..n)
+        .map(|i| metric.angle(coords[(i + n - 1) % n], coords[i], coords[(i + 1) % n]))
+        .fold(0., |acc: f64, angle| acc.max((angle - 90.).abs()));
+    (1. - worst / 90.).max(0.)
+}
+
+impl Hgrid {
+    // Splits every triangle and quad into four, adding a node at the middle of every side (and at
+    // the center of every quad) with coordinates and values interpolated linearly. Boundary
+    // segments get the new side nodes inserted between their original nodes.
+    pub fn refine_uniform(&self) -> Result<Hgrid, HgridTryFromError> {
+        let mut nodes = self.nodes().btree_map();
+        let mut next_node_id = nodes.keys().next_back().map_or(1, |node_id| node_id + 1);
+        let mut midpoints: BTreeMap<(u32, u32), u32> = BTreeMap::new();
+        let mut elements = BTreeMap::new();
+        let mut next_element_id = 1;
+        for node_ids in self.elements().btree_map().values() {
+            let n = node_ids.len();
+            let mids: Vec<u32> = (0..n)
+                .map(|i| {
+                    let a = node_ids[i];
+                    let b = node_ids[(i + 1) % n];
+                    *midpoints.entry(side_key(a, b)).or_insert_with(|| {
+                        let node = mean_node(&nodes, &[a, b]);
+                        nodes.insert(next_node_id, node);
+                        next_node_id += 1;
+                        next_node_id - 1
+                    })
+                })
+                .collect();
+            let children = if n == 3 {
+                vec![
+                    vec![node_ids[0], mids[0], mids[2]],
+                    vec![mids[0], node_ids[1], mids[1]],
+                    vec![mids[2], mids[1], node_ids[2]],
+                    vec![mids[0], mids[1], mids[2]],
+                ]
+            } else {
+                let center = next_node_id;
+                nodes.insert(center, mean_node(&nodes, node_ids));
+                next_node_id += 1;
+                vec![
+                    vec![node_ids[0], mids[0], center, mids[3]],
+                    vec![mids[0], node_ids[1], mids[1], center],
+                    vec![center, mids[1], node_ids[2], mids[2]],
+                    vec![mids[3], center, mids[2], node_ids[3]],
+                ]
+            };
+            for child in children {
+                elements.insert(next_element_id, child);
+                next_element_id += 1;
+            }
+        }
+        let boundaries = boundary_type_map(self)
+            .into_iter()
+            .map(|(boundary_type, segments)| {
+                let segments = segments
+                    .into_iter()
+                    .map(|segment| {
+                        let mut refined = vec![segment[0]];
+                        for pair in segment.windows(2) {
+                            if let Some(&mid) = midpoints.get(&side_key(pair[0], pair[1])) {
+                                refined.push(mid);
+                            }
+                            refined.push(pair[1]);
+                        }
+                        refined
+                    })
+                    .collect();
+                (boundary_type, segments)
+            })
+            .collect();
+        Hgrid::from_parts(
+            nodes,
+            self.crs(),
+            elements,
+            boundaries,
+            self.description().cloned(),
+        )
+    }
+
+    // Splits every quad into two triangles along its shorter diagonal.
+    pub fn split_quads(&self) -> Result<Hgrid, HgridTryFromError> {
+        let nodes = self.nodes().btree_map();
+        let distance = |a: u32, b: u32| {
+            let (coord_a, _) = &nodes[&a];
+            let (coord_b, _) = &nodes[&b];
+            (coord_a[0] - coord_b[0]).hypot(coord_a[1] - coord_b[1])
+        };
+        let mut elements = BTreeMap::new();
+        let mut next_element_id = 1;
+        for node_ids in self.elements().btree_map().values() {
+            let children = if node_ids.len() == 3 {
+                vec![node_ids.clone()]
+            } else {
+                let [a, b, c, d] = [node_ids[0], node_ids[1], node_ids[2], node_ids[3]];
+                if distance(a, c) <= distance(b, d) {
+                    vec![vec![a, b, c], vec![a, c, d]]
+                } else {
+                    vec![vec![a, b, d], vec![b, c, d]]
+                }
+            };
+            for child in children {
+                elements.insert(next_element_id, child);
+                next_element_id += 1;
+            }
+        }
+        Hgrid::from_parts(
+            nodes,
+            self.crs(),
+            elements,
+            boundary_type_map(self),
+            self.description().cloned(),
+        )
+    }
+
+    // Greedily merges pairs of triangles sharing a side into quads, best quads first, keeping only
+    // merges whose quad_quality is at least min_quality.
+    pub fn merge_triangles(&self, min_quality: f64) -> Result<Hgrid, HgridTryFromError> {
+        let metric = self.metric(GeometryMethod::Auto);
+        let nodes = self.nodes().btree_map();
+        let element_map = self.elements().btree_map();
+        let coords = |node_ids: &[u32]| -> Vec<[f64; 2]> {
+            node_ids
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect()
+        };
+        let mut candidates = Vec::new();
+        for ((p, q), element_ids) in self.side_elements() {
+            if element_ids.len() != 2 {
+                continue;
+            }
+            let (first, second) = (element_ids[0], element_ids[1]);
+            let (tri_a, tri_b) = (&element_map[&first], &element_map[&second]);
+            if tri_a.len() != 3 || tri_b.len() != 3 {
+                continue;
+            }
+            // Rotate the first triangle so that it reads (p, q, r) or (q, p, r).
+            let start = (0..3)
+                .find(|&i| {
+                    let a = tri_a[i];
+                    let b = tri_a[(i + 1) % 3];
+                    side_key(a, b) == (p, q)
+                })
+                .unwrap();
+            let (from, to, r) = (tri_a[start], tri_a[(start + 1) % 3], tri_a[(start + 2) % 3]);
+            let s = *tri_b
+                .iter()
+                .find(|&&node_id| node_id != p && node_id != q)
+                .unwrap();
+            let quad = vec![from, s, to, r];
+            let quality = quad_quality(&metric, &coords(&quad));
+            if quality >= min_quality {
+                candidates.push((quality, first, second, quad));
+            }
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut merged: BTreeSet<u32> = BTreeSet::new();
+        let mut quads = Vec::new();
+        for (_quality, first, second, quad) in candidates {
+            if merged.contains(&first) || merged.contains(&second) {
+                continue;
+            }
+            merged.insert(first);
+            merged.insert(second);
+            quads.push((first.min(second), quad));
+        }
+        let mut new_elements: BTreeMap<u32, Vec<u32>> = element_map
+            .into_iter()
+            .filter(|(element_id, _)| !merged.contains(element_id))
+            .collect();
+        new_elements.extend(quads);
+        let elements = new_elements
+            .into_values()
+            .enumerate()
+            .map(|(local_index, node_ids)| (local_index as u32 + 1, node_ids))
+            .collect();
+        Hgrid::from_parts(
+            nodes,
+            self.crs(),
+            elements,
+            boundary_type_map(self),
+            self.description().cloned(),
+        )
+    }
+}
@@ -0,0 +1,239 @@
+use super::boundaries::BoundaryType;
+use super::crs::{transformer, Crs, CrsError, GEOGRAPHIC_CRS};
+use super::hgrid::{Hgrid, HgridTryFromError};
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub fn reproject(hgrid: &Hgrid, target_crs: &str) -> Result<Hgrid, ReprojectError> {
+    let crs = hgrid.crs().ok_or(CrsError::MissingCrs)?;
+    reproject_from(hgrid, crs.definition(), target_crs)
+}
+
+pub fn reproject_from(
+    hgrid: &Hgrid,
+    source_crs: &str,
+    target_crs: &str,
+) -> Result<Hgrid, ReprojectError> {
+    let transformer = transformer(source_crs, target_crs)?;
+    let crs = Crs::new(target_crs)?;
+    let mut node_btree_map = hgrid.nodes().btree_map();
+    for (node_id, (coord, _values)) in node_btree_map.iter_mut() {
+        let (x, y) = transformer
+            .convert((coord[0], coord[1]))
+            .map_err(|e| CrsError::ConversionError(*node_id, e.to_string()))?;
+        coord[0] = x;
+        coord[1] = y;
+    }
+    let mut reprojected = hgrid.clone();
+    reprojected.replace_nodes_and_crs(node_btree_map, Some(Arc::new(crs)))?;
+    Ok(reprojected)
+}
+
+pub fn to_hgrid_ll(hgrid: &Hgrid) -> Result<Hgrid, ReprojectError> {
+    reproject(hgrid, GEOGRAPHIC_CRS)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HgridPairMismatch {
+    NodeCount(usize, usize),
+    ElementCount(usize, usize),
+    Connectivity(Vec<u32>),
+    Boundaries(String),
+    Depths(Vec<u32>),
+    Coordinates(Vec<u32>),
+}
+
+impl fmt::Display for HgridPairMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HgridPairMismatch::NodeCount(a, b) => {
+                write!(f, "node count differs: {} vs {}", a, b)
+            }
+            HgridPairMismatch::ElementCount(a, b) => {
+                write!(f, "element count differs: {} vs {}", a, b)
+            }
+            HgridPairMismatch::Connectivity(element_ids) => write!(
+                f,
+                "connectivity differs on {} elements, first: {:?}",
+                element_ids.len(),
+                element_ids.first()
+            ),
+            HgridPairMismatch::Boundaries(boundary_type) => {
+                write!(f, "{} boundaries differ", boundary_type)
+            }
+            HgridPairMismatch::Depths(node_ids) => write!(
+                f,
+                "depths differ on {} nodes, first: {:?}",
+                node_ids.len(),
+                node_ids.first()
+            ),
+            HgridPairMismatch::Coordinates(node_ids) => write!(
+                f,
+                "coordinates differ after reprojection on {} nodes, first: {:?}",
+                node_ids.len(),
+                node_ids.first()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HgridPairReport {
+    mismatches: Vec<HgridPairMismatch>,
+    max_coordinate_error: f64,
+    max_depth_error: f64,
+}
+
+impl HgridPairReport {
+    pub fn mismatches(&self) -> &Vec<HgridPairMismatch> {
+        &self.mismatches
+    }
+
+    pub fn max_coordinate_error(&self) -> f64 {
+        self.max_coordinate_error
+    }
+
+    pub fn max_depth_error(&self) -> f64 {
+        self.max_depth_error
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for HgridPairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_consistent() {
+            writeln!(f, "hgrid.gr3 and hgrid.ll are consistent")?;
+        }
+        for mismatch in self.mismatches.iter() {
+            writeln!(f, "{}", mismatch)?;
+        }
+        writeln!(f, "max coordinate error: {}", self.max_coordinate_error)?;
+        write!(f, "max depth error: {}", self.max_depth_error)
+    }
+}
+
+// Checks that a projected hgrid.gr3 and a geographic hgrid.ll describe the same mesh. The
+// coordinate tolerance is in the units of the hgrid.gr3 CRS.
+#[derive(Builder, Debug, Clone)]
+pub struct HgridPairCheck {
+    #[builder(default = "1e-2")]
+    coordinate_tolerance: f64,
+    #[builder(default = "1e-6")]
+    depth_tolerance: f64,
+    #[builder(setter(into, strip_option), default)]
+    gr3_crs: Option<String>,
+}
+
+impl HgridPairCheck {
+    pub fn check(&self, gr3: &Hgrid, ll: &Hgrid) -> Result<HgridPairReport, ReprojectError> {
+        let mut mismatches = Vec::new();
+        let gr3_nodes = gr3.nodes().btree_map();
+        let ll_nodes = ll.nodes().btree_map();
+        if gr3_nodes.len() != ll_nodes.len() {
+            mismatches.push(HgridPairMismatch::NodeCount(
+                gr3_nodes.len(),
+                ll_nodes.len(),
+            ));
+        }
+        let gr3_elements = gr3.elements().btree_map();
+        let ll_elements = ll.elements().btree_map();
+        if gr3_elements.len() != ll_elements.len() {
+            mismatches.push(HgridPairMismatch::ElementCount(
+                gr3_elements.len(),
+                ll_elements.len(),
+            ));
+        }
+        let connectivity: Vec<u32> = gr3_elements
+            .iter()
+            .filter(|(element_id, node_ids)| ll_elements.get(element_id) != Some(node_ids))
+            .map(|(&element_id, _)| element_id)
+            .collect();
+        if !connectivity.is_empty() {
+            mismatches.push(HgridPairMismatch::Connectivity(connectivity));
+        }
+        let gr3_boundaries = boundary_type_map(gr3);
+        let ll_boundaries = boundary_type_map(ll);
+        for (boundary_type, name) in [
+            (BoundaryType::Open, "open"),
+            (BoundaryType::Land, "land"),
+            (BoundaryType::Interior, "interior"),
+        ] {
+            if gr3_boundaries.get(&boundary_type) != ll_boundaries.get(&boundary_type) {
+                mismatches.push(HgridPairMismatch::Boundaries(name.to_string()));
+            }
+        }
+        let gr3_depths = gr3.depths_btree_map();
+        let ll_depths = ll.depths_btree_map();
+        let mut max_depth_error: f64 = 0.;
+        let mut depths = Vec::new();
+        for (node_id, gr3_depth) in gr3_depths.iter() {
+            let error = match ll_depths.get(node_id) {
+                Some(ll_depth) => (gr3_depth - ll_depth).abs(),
+                None => f64::INFINITY,
+            };
+            max_depth_error = max_depth_error.max(error);
+            if error > self.depth_tolerance {
+                depths.push(*node_id);
+            }
+        }
+        if !depths.is_empty() {
+            mismatches.push(HgridPairMismatch::Depths(depths));
+        }
+        let gr3_crs = match &self.gr3_crs {
+            Some(gr3_crs) => gr3_crs.clone(),
+            None => {
+                let crs = gr3.crs().ok_or(CrsError::MissingCrs)?;
+                crs.definition().to_string()
+            }
+        };
+        let transformer = transformer(GEOGRAPHIC_CRS, &gr3_crs)?;
+        let mut max_coordinate_error: f64 = 0.;
+        let mut coordinates = Vec::new();
+        for (node_id, (gr3_coord, _)) in gr3_nodes.iter() {
+            let Some((ll_coord, _)) = ll_nodes.get(node_id) else {
+                coordinates.push(*node_id);
+                continue;
+            };
+            let (x, y): (f64, f64) = transformer
+                .convert((ll_coord[0], ll_coord[1]))
+                .map_err(|e| CrsError::ConversionError(*node_id, e.to_string()))?;
+            let error = ((x - gr3_coord[0]).powi(2) + (y - gr3_coord[1]).powi(2)).sqrt();
+            max_coordinate_error = max_coordinate_error.max(error);
+            if error > self.coordinate_tolerance {
+                coordinates.push(*node_id);
+            }
+        }
+        if !coordinates.is_empty() {
+            mismatches.push(HgridPairMismatch::Coordinates(coordinates));
+        }
+        Ok(HgridPairReport {
+            mismatches,
+            max_coordinate_error,
+            max_depth_error,
+        })
+    }
+}
+
+fn boundary_type_map(hgrid: &Hgrid) -> BTreeMap<BoundaryType, Vec<Vec<u32>>> {
+    hgrid
+        .boundaries()
+        .map(|boundaries| boundaries.to_boundary_type_map())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, nodes_ids)| !nodes_ids.is_empty())
+        .collect()
+}
+
+#[derive(Error, Debug)]
+pub enum ReprojectError {
+    #[error(transparent)]
+    CrsError(#[from] CrsError),
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+}
@@ -0,0 +1,157 @@
+use super::editing::HgridEditError;
+use super::hgrid::{Hgrid, HgridTryFromError};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+// SMS 2dm meshes: ND lines hold the node values with the sign of Hgrid::depths(), i.e. as
+// elevations, and open boundaries are written as nodestrings. Nodestrings read back become open
+// boundaries, with the rest of the mesh boundary traced into land boundaries and islands.
+impl Hgrid {
+    pub fn from_2dm_path(path: &Path) -> Result<Hgrid, Sms2dmError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Sms2dmError::IoError(path.display().to_string(), e.to_string()))?;
+        Self::from_2dm_str(&contents)
+    }
+
+    pub fn from_2dm_str(contents: &str) -> Result<Hgrid, Sms2dmError> {
+        let mut nodes = BTreeMap::new();
+        let mut elements = BTreeMap::new();
+        let mut nodestrings: Vec<Vec<u32>> = Vec::new();
+        let mut nodestring = Vec::new();
+        let mut description = None;
+        for (index, line) in contents.lines().enumerate() {
+            let error = |message: &str| Sms2dmError::ParseError(index + 1, message.to_string());
+            let mut items = line.split_whitespace();
+            let Some(card) = items.next() else {
+                continue;
+            };
+            let numbers = |count: usize, items: std::str::SplitWhitespace| {
+                let values: Vec<f64> = items
+                    .take(count)
+                    .map(|item| item.parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error(&format!("expected {} numbers after {}", count, card)))?;
+                match values.len() == count {
+                    true => Ok(values),
+                    false => Err(error(&format!("expected {} numbers after {}", count, card))),
+                }
+            };
+            match card {
+                "MESH2D" => {}
+                "MESHNAME" | "GM" => {
+                    let name = line.trim()[card.len()..].trim().trim_matches('"');
+                    description = Some(name.to_string());
+                }
+                "ND" => {
+                    let values = numbers(4, items)?;
+                    nodes.insert(
+                        values[0] as u32,
+                        (vec![values[1], values[2]], Some(vec![values[3]])),
+                    );
+                }
+                "E3T" | "E4Q" => {
+                    let count = if card == "E3T" { 3 } else { 4 };
+                    let values = numbers(count + 1, items)?;
+                    let node_ids = values[1..].iter().map(|&value| value as u32).collect();
+                    elements.insert(values[0] as u32, node_ids);
+                }
+                "NS" => {
+                    for item in items {
+                        let Ok(node_id) = item.parse::<i64>() else {
+                            // A nodestring may end with its name after the last node.
+                            break;
+                        };
+                        nodestring.push(node_id.unsigned_abs() as u32);
+                        if node_id < 0 {
+                            nodestrings.push(std::mem::take(&mut nodestring));
+                            break;
+                        }
+                    }
+                }
+                _ => log::debug!("Skipping unsupported 2dm card {}", card),
+            }
+        }
+        if !nodestring.is_empty() {
+            nodestrings.push(nodestring);
+        }
+        let open_sides: BTreeSet<(u32, u32)> = nodestrings
+            .iter()
+            .flat_map(|nodestring| {
+                nodestring
+                    .windows(2)
+                    .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let hgrid = Hgrid::from_parts(nodes, None, elements, BTreeMap::new(), description)?;
+        let mut editor = hgrid.edit();
+        editor.rebuild_boundaries(&|a, b| open_sides.contains(&(a.min(b), a.max(b))))?;
+        Ok(editor.commit()?)
+    }
+
+    pub fn to_2dm_string(&self) -> String {
+        let mut out = String::new();
+        self.write_2dm_into(&mut out)
+            .expect("Writing to a String cannot fail");
+        out
+    }
+
+    fn write_2dm_into(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "MESH2D")?;
+        if let Some(description) = self.description() {
+            writeln!(out, "MESHNAME \"{}\"", description)?;
+        }
+        for (element_id, node_ids) in self.elements().btree_map() {
+            let card = if node_ids.len() == 3 { "E3T" } else { "E4Q" };
+            let node_ids: Vec<String> = node_ids.iter().map(u32::to_string).collect();
+            writeln!(out, "{} {} {} 1", card, element_id, node_ids.join(" "))?;
+        }
+        for (node_id, (coord, values)) in self.nodes().btree_map() {
+            let value = values
+                .as_ref()
+                .and_then(|values| values.first().copied())
+                .unwrap_or(0.);
+            writeln!(out, "ND {} {} {} {}", node_id, coord[0], coord[1], value)?;
+        }
+        let open = self
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids())
+            .unwrap_or_default();
+        for segment in open.iter() {
+            // Ten node ids per line, with the last one negated to end the nodestring.
+            for (chunk_index, chunk) in segment.chunks(10).enumerate() {
+                let is_last = (chunk_index + 1) * 10 >= segment.len();
+                let items: Vec<String> = chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, node_id)| match is_last && i == chunk.len() - 1 {
+                        true => format!("-{}", node_id),
+                        false => node_id.to_string(),
+                    })
+                    .collect();
+                writeln!(out, "NS {}", items.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_2dm(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.to_2dm_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Sms2dmError {
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Parse error in line {0}: {1}")]
+    ParseError(usize, String),
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+    #[error(transparent)]
+    HgridEditError(#[from] HgridEditError),
+}
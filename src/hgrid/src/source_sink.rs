@@ -0,0 +1,251 @@
+use super::geometry::GeometryMethod;
+use super::hgrid::Hgrid;
+use super::th::{interpolate, Series, ThError, TimeHistory};
+use derive_builder::Builder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+// Value SCHISM reads in msource.th as "use the ambient concentration".
+pub const AMBIENT: f64 = -9999.;
+
+fn validate_series(name: &str, series: &Series) -> Result<(), String> {
+    if series.is_empty() {
+        return Err(format!("The {} series is empty", name));
+    }
+    if series.windows(2).any(|pair| pair[1].0 <= pair[0].0) {
+        return Err(format!("The {} series times must be increasing", name));
+    }
+    if series.iter().any(|(_, value)| !value.is_finite()) {
+        return Err(format!("The {} series has non-finite values", name));
+    }
+    Ok(())
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct River {
+    #[builder(setter(into))]
+    name: String,
+    coord: [f64; 2],
+    // Discharge in m3/s.
+    discharge: Series,
+    // Temperature in C and salinity in PSU; the ambient values are used when missing.
+    #[builder(setter(strip_option), default)]
+    temperature: Option<Series>,
+    #[builder(setter(strip_option), default)]
+    salinity: Option<Series>,
+}
+
+impl RiverBuilder {
+    pub fn validate(&self) -> Result<(), RiverBuilderError> {
+        let series = [
+            ("discharge", self.discharge.as_ref()),
+            (
+                "temperature",
+                self.temperature.as_ref().and_then(Option::as_ref),
+            ),
+            ("salinity", self.salinity.as_ref().and_then(Option::as_ref)),
+        ];
+        for (name, series) in series {
+            if let Some(series) = series {
+                validate_series(name, series).map_err(RiverBuilderError::ValidationError)?;
+            }
+        }
+        if let Some(discharge) = &self.discharge {
+            if discharge.iter().any(|(_, value)| *value < 0.) {
+                return Err(RiverBuilderError::ValidationError(
+                    "River discharge must be >= 0.".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl River {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn coord(&self) -> [f64; 2] {
+        self.coord
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiverPlacement {
+    pub name: String,
+    pub element_id: u32,
+    // Distance from the river location to the element centroid.
+    pub distance: f64,
+}
+
+// Point sources for rivers: every river goes into the element nearest to it among those that
+// touch a land boundary, and its series are resampled every dt seconds up to the end of the
+// shortest discharge series unless an end time is given.
+#[derive(Debug, Clone)]
+pub struct SourceSink {
+    placements: Vec<RiverPlacement>,
+    times: Vec<f64>,
+    discharge: Vec<Vec<f64>>,
+    temperature: Vec<Vec<f64>>,
+    salinity: Vec<Vec<f64>>,
+}
+
+impl SourceSink {
+    pub fn new(
+        hgrid: &Hgrid,
+        rivers: &[River],
+        dt: f64,
+        end: Option<f64>,
+    ) -> Result<Self, SourceSinkError> {
+        if rivers.is_empty() {
+            return Err(SourceSinkError::NoRivers);
+        }
+        if dt <= 0. {
+            return Err(SourceSinkError::InvalidTimeStep(dt));
+        }
+        let land_nodes: BTreeSet<u32> = hgrid
+            .boundaries()
+            .and_then(|boundaries| boundaries.land())
+            .map(|land| land.nodes_ids().into_iter().flatten().collect())
+            .unwrap_or_default();
+        let centroids = hgrid.element_centroids();
+        let candidates: Vec<(u32, [f64; 2])> = hgrid
+            .elements()
+            .btree_map()
+            .into_iter()
+            .filter(|(_, node_ids)| node_ids.iter().any(|node_id| land_nodes.contains(node_id)))
+            .map(|(element_id, _)| (element_id, centroids[&element_id]))
+            .collect();
+        if candidates.is_empty() {
+            return Err(SourceSinkError::NoLandBoundary);
+        }
+        let metric = hgrid.metric(GeometryMethod::Auto);
+        let placements: Vec<RiverPlacement> = rivers
+            .iter()
+            .map(|river| {
+                let (element_id, distance) = candidates
+                    .iter()
+                    .map(|(element_id, centroid)| {
+                        (*element_id, metric.distance(river.coord, *centroid))
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap();
+                RiverPlacement {
+                    name: river.name.clone(),
+                    element_id,
+                    distance,
+                }
+            })
+            .collect();
+        let mut by_element: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for placement in placements.iter() {
+            by_element
+                .entry(placement.element_id)
+                .or_default()
+                .push(placement.name.clone());
+        }
+        if let Some((element_id, names)) = by_element.into_iter().find(|(_, names)| names.len() > 1)
+        {
+            return Err(SourceSinkError::DuplicateElement(element_id, names));
+        }
+        let end = end.unwrap_or_else(|| {
+            rivers
+                .iter()
+                .map(|river| river.discharge[river.discharge.len() - 1].0)
+                .fold(f64::INFINITY, f64::min)
+        });
+        let steps = (end / dt + 1e-9).floor().max(0.) as usize;
+        let times: Vec<f64> = (0..=steps).map(|step| step as f64 * dt).collect();
+        let resample = |series: Option<&Series>| -> Vec<f64> {
+            times
+                .iter()
+                .map(|&time| series.map_or(AMBIENT, |series| interpolate(series, time)))
+                .collect()
+        };
+        Ok(Self {
+            placements,
+            discharge: rivers
+                .iter()
+                .map(|river| resample(Some(&river.discharge)))
+                .collect(),
+            temperature: rivers
+                .iter()
+                .map(|river| resample(river.temperature.as_ref()))
+                .collect(),
+            salinity: rivers
+                .iter()
+                .map(|river| resample(river.salinity.as_ref()))
+                .collect(),
+            times,
+        })
+    }
+
+    pub fn placements(&self) -> &Vec<RiverPlacement> {
+        &self.placements
+    }
+
+    pub fn times(&self) -> &Vec<f64> {
+        &self.times
+    }
+
+    pub fn write_source_sink_in(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "{} !total # of elements with sources",
+            self.placements.len()
+        )?;
+        for placement in self.placements.iter() {
+            writeln!(file, "{} !{}", placement.element_id, placement.name)?;
+        }
+        writeln!(file)?;
+        writeln!(file, "0 !total # of elements with sinks")?;
+        Ok(())
+    }
+
+    pub fn vsource(&self) -> Result<TimeHistory, ThError> {
+        TimeHistory::from_columns(self.times.clone(), &self.discharge)
+    }
+
+    // Temperature for all sources followed by salinity for all sources, as SCHISM expects.
+    pub fn msource(&self) -> Result<TimeHistory, ThError> {
+        let columns: Vec<Vec<f64>> = self
+            .temperature
+            .iter()
+            .chain(self.salinity.iter())
+            .cloned()
+            .collect();
+        TimeHistory::from_columns(self.times.clone(), &columns)
+    }
+
+    pub fn write_vsource_th(&self, path: &Path) -> std::io::Result<()> {
+        self.vsource().map_err(std::io::Error::other)?.write(path)
+    }
+
+    pub fn write_msource_th(&self, path: &Path) -> std::io::Result<()> {
+        self.msource().map_err(std::io::Error::other)?.write(path)
+    }
+
+    pub fn write(&self, dir: &Path) -> std::io::Result<()> {
+        self.write_source_sink_in(&dir.join("source_sink.in"))?;
+        self.write_vsource_th(&dir.join("vsource.th"))?;
+        self.write_msource_th(&dir.join("msource.th"))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SourceSinkError {
+    #[error("At least one river is required")]
+    NoRivers,
+    #[error("The time step must be > 0. but got {0}")]
+    InvalidTimeStep(f64),
+    #[error("The hgrid has no elements on a land boundary")]
+    NoLandBoundary,
+    #[error("Rivers {1:?} fall on the same element {0}")]
+    DuplicateElement(u32, Vec<String>),
+}
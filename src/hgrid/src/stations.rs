@@ -0,0 +1,480 @@
+use super::boundaries::BoundaryType;
+use super::geometry::GeometryMethod;
+use super::hgrid::Hgrid;
+use super::locate::ElementLocator;
+use derive_builder::Builder;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Station {
+    name: String,
+    x: f64,
+    y: f64,
+    // Elevation of the output point relative to MSL, positive up, for 3D outputs.
+    z: f64,
+}
+
+impl Station {
+    pub fn new(name: &str, x: f64, y: f64, z: Option<f64>) -> Self {
+        Self {
+            name: name.to_string(),
+            x,
+            y,
+            z: z.unwrap_or(0.),
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn coord(&self) -> [f64; 2] {
+        [self.x, self.y]
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z
+    }
+}
+
+fn parse_field(value: &str, column: &str, line: usize) -> Result<f64, StationsError> {
+    value.trim().parse::<f64>().map_err(|_| {
+        StationsError::ParseError(format!(
+            "Expected {} in line {} to be a number but found {}",
+            column, line, value
+        ))
+    })
+}
+
+// Reads a CSV with a header naming the name, x (or lon) and y (or lat) columns and, optionally,
+// z. Stations without a name column are named after their row.
+pub fn stations_from_csv_path(path: &Path) -> Result<Vec<Station>, StationsError> {
+    let fname = path.display().to_string();
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| StationsError::IoError(fname.clone(), e.to_string()))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| StationsError::ParseError(e.to_string()))?
+        .clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.to_lowercase().as_str()))
+    };
+    let name_column = column(&["name", "station", "id"]);
+    let x_column = column(&["x", "lon", "longitude"])
+        .ok_or_else(|| StationsError::ParseError("Missing x or lon column".to_string()))?;
+    let y_column = column(&["y", "lat", "latitude"])
+        .ok_or_else(|| StationsError::ParseError("Missing y or lat column".to_string()))?;
+    let z_column = column(&["z", "elevation"]);
+    let mut stations = Vec::new();
+    for (row_index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| StationsError::ParseError(e.to_string()))?;
+        let line = row_index + 2;
+        let name = match name_column.and_then(|column| record.get(column)) {
+            Some(name) => name.to_string(),
+            None => format!("station_{}", row_index + 1),
+        };
+        let x = parse_field(record.get(x_column).unwrap_or_default(), "x", line)?;
+        let y = parse_field(record.get(y_column).unwrap_or_default(), "y", line)?;
+        let z = match z_column.and_then(|column| record.get(column)) {
+            Some(z) if !z.is_empty() => Some(parse_field(z, "z", line)?),
+            _ => None,
+        };
+        stations.push(Station::new(&name, x, y, z));
+    }
+    Ok(stations)
+}
+
+// Reads the Point features of a GeoJSON FeatureCollection, taking the name from the "name"
+// property and z from a third coordinate or a "z" property.
+pub fn stations_from_geojson_str(geojson: &str) -> Result<Vec<Station>, StationsError> {
+    let value: Value =
+        serde_json::from_str(geojson).map_err(|e| StationsError::ParseError(e.to_string()))?;
+    let features = match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => value
+            .get("features")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default(),
+        Some("Feature") => vec![value.clone()],
+        _ => {
+            return Err(StationsError::ParseError(
+                "Expected a GeoJSON Feature or FeatureCollection".to_string(),
+            ))
+        }
+    };
+    let mut stations = Vec::new();
+    for (local_index, feature) in features.iter().enumerate() {
+        let geometry = feature.get("geometry");
+        if geometry.and_then(|g| g.get("type")).and_then(Value::as_str) != Some("Point") {
+            log::warn!("Ignoring non-Point feature {}", local_index + 1);
+            continue;
+        }
+        let coordinates: Vec<f64> = geometry
+            .and_then(|g| g.get("coordinates"))
+            .and_then(Value::as_array)
+            .map(|coordinates| coordinates.iter().filter_map(Value::as_f64).collect())
+            .unwrap_or_default();
+        if coordinates.len() < 2 {
+            return Err(StationsError::ParseError(format!(
+                "Point feature {} has fewer than two coordinates",
+                local_index + 1
+            )));
+        }
+        let properties = feature.get("properties");
+        let name = match properties
+            .and_then(|p| p.get("name"))
+            .and_then(Value::as_str)
+        {
+            Some(name) => name.to_string(),
+            None => format!("station_{}", local_index + 1),
+        };
+        let z = coordinates
+            .get(2)
+            .copied()
+            .or_else(|| properties.and_then(|p| p.get("z")).and_then(Value::as_f64));
+        stations.push(Station::new(&name, coordinates[0], coordinates[1], z));
+    }
+    Ok(stations)
+}
+
+pub fn stations_from_geojson_path(path: &Path) -> Result<Vec<Station>, StationsError> {
+    let geojson = fs::read_to_string(path)
+        .map_err(|e| StationsError::IoError(path.display().to_string(), e.to_string()))?;
+    stations_from_geojson_str(&geojson)
+}
+
+// On/off flags of the first line of station.in, in SCHISM's order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StationOutputs {
+    pub elevation: bool,
+    pub air_pressure: bool,
+    pub wind_x: bool,
+    pub wind_y: bool,
+    pub temperature: bool,
+    pub salinity: bool,
+    pub u: bool,
+    pub v: bool,
+    pub w: bool,
+}
+
+impl Default for StationOutputs {
+    fn default() -> Self {
+        Self {
+            elevation: true,
+            air_pressure: true,
+            wind_x: true,
+            wind_y: true,
+            temperature: true,
+            salinity: true,
+            u: true,
+            v: true,
+            w: true,
+        }
+    }
+}
+
+impl fmt::Display for StationOutputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            self.elevation,
+            self.air_pressure,
+            self.wind_x,
+            self.wind_y,
+            self.temperature,
+            self.salinity,
+            self.u,
+            self.v,
+            self.w,
+        ]
+        .map(|flag| if flag { "1" } else { "0" });
+        write!(
+            f,
+            "{} !on (1)|off(0) flags for elev, air pressure, windx, windy, T, S, u, v, w",
+            flags.join(" ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StationIssue {
+    Outside,
+    OnLandBoundary,
+    // Some node of the element holding the station has no depth.
+    MissingDepth,
+    // Positive-down depth interpolated at the station.
+    Shallow(f64),
+}
+
+impl fmt::Display for StationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StationIssue::Outside => write!(f, "outside the mesh"),
+            StationIssue::OnLandBoundary => write!(f, "in an element touching a land boundary"),
+            StationIssue::MissingDepth => write!(f, "in an element with nodes without a depth"),
+            StationIssue::Shallow(depth) => write!(f, "too shallow ({:.3} m)", depth),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationCheck {
+    station: Station,
+    element_id: Option<u32>,
+    depth: Option<f64>,
+    issues: Vec<StationIssue>,
+    snapped_to: Option<(u32, [f64; 2])>,
+}
+
+impl StationCheck {
+    pub fn station(&self) -> &Station {
+        &self.station
+    }
+
+    pub fn element_id(&self) -> Option<u32> {
+        self.element_id
+    }
+
+    pub fn depth(&self) -> Option<f64> {
+        self.depth
+    }
+
+    pub fn issues(&self) -> &Vec<StationIssue> {
+        &self.issues
+    }
+
+    // Node the station was moved to, when snapping was requested and it had issues.
+    pub fn snapped_to(&self) -> Option<u32> {
+        self.snapped_to.map(|(node_id, _)| node_id)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[derive(Builder, Debug, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct StationFile {
+    stations: Vec<Station>,
+    #[builder(default)]
+    outputs: StationOutputs,
+    // Positive-down depth below which a station is reported as shallow.
+    #[builder(default = "0.5")]
+    min_depth: f64,
+    #[builder(default = "true")]
+    check_land_boundaries: bool,
+    // Move stations with issues to the nearest wet node away from land boundaries.
+    #[builder(default)]
+    snap: bool,
+}
+
+impl StationFileBuilder {
+    pub fn validate(&self) -> Result<(), StationFileBuilderError> {
+        if let Some(stations) = &self.stations {
+            if stations.is_empty() {
+                return Err(StationFileBuilderError::ValidationError(
+                    "At least one station is required".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StationFile {
+    pub fn check(&self, hgrid: &Hgrid) -> StationReport {
+        let locator = ElementLocator::new(hgrid);
+        let nodes = hgrid.nodes().btree_map();
+        let elements = hgrid.elements().btree_map();
+        let depths = hgrid.depths_btree_map();
+        let depth_at = |node_id: &u32| depths.get(node_id).map(|value| -value);
+        let land_nodes: BTreeSet<u32> = hgrid
+            .boundaries()
+            .map(|boundaries| boundaries.to_boundary_type_map())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(boundary_type, _)| *boundary_type != BoundaryType::Open)
+            .flat_map(|(_, segments)| segments.into_iter().flatten())
+            .collect();
+        let locate = |coord: [f64; 2]| {
+            let mut issues = Vec::new();
+            let located = locator.locate_with_weights(coord);
+            let depth = located.as_ref().and_then(|(_, weights)| {
+                weights
+                    .iter()
+                    .filter(|(_, weight)| *weight != 0.)
+                    .map(|(node_id, weight)| depth_at(node_id).map(|depth| weight * depth))
+                    .sum::<Option<f64>>()
+            });
+            match &located {
+                None => issues.push(StationIssue::Outside),
+                Some((element_id, _)) => {
+                    if self.check_land_boundaries
+                        && elements[element_id]
+                            .iter()
+                            .any(|node_id| land_nodes.contains(node_id))
+                    {
+                        issues.push(StationIssue::OnLandBoundary);
+                    }
+                    match depth {
+                        None => issues.push(StationIssue::MissingDepth),
+                        Some(depth) if depth < self.min_depth => {
+                            issues.push(StationIssue::Shallow(depth))
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            (located.map(|(element_id, _)| element_id), depth, issues)
+        };
+        let metric = hgrid.metric(GeometryMethod::Auto);
+        let checks = self
+            .stations
+            .iter()
+            .map(|station| {
+                let (element_id, depth, issues) = locate(station.coord());
+                // Candidate nodes are tried from the nearest one and kept only if the station
+                // moved there passes the same checks.
+                let snapped_to = if self.snap && !issues.is_empty() {
+                    let mut candidates: Vec<(f64, u32, [f64; 2])> = nodes
+                        .iter()
+                        .filter(|(node_id, _)| {
+                            depth_at(node_id).is_some_and(|depth| depth >= self.min_depth)
+                                && !(self.check_land_boundaries && land_nodes.contains(node_id))
+                        })
+                        .map(|(node_id, (coord, _))| {
+                            let coord = [coord[0], coord[1]];
+                            (metric.distance(station.coord(), coord), *node_id, coord)
+                        })
+                        .collect();
+                    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+                    candidates
+                        .into_iter()
+                        .find(|(_, _, coord)| locate(*coord).2.is_empty())
+                        .map(|(_, node_id, coord)| (node_id, coord))
+                } else {
+                    None
+                };
+                StationCheck {
+                    station: station.clone(),
+                    element_id,
+                    depth,
+                    issues,
+                    snapped_to,
+                }
+            })
+            .collect();
+        StationReport {
+            outputs: self.outputs,
+            checks,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StationReport {
+    outputs: StationOutputs,
+    checks: Vec<StationCheck>,
+}
+
+impl StationReport {
+    pub fn checks(&self) -> &Vec<StationCheck> {
+        &self.checks
+    }
+
+    pub fn invalid(&self) -> Vec<&StationCheck> {
+        self.checks
+            .iter()
+            .filter(|check| !check.is_valid())
+            .collect()
+    }
+
+    // Stations that go into station.in: the valid ones as given and the snapped ones at their
+    // node. Stations with issues that could not be snapped are left out.
+    pub fn output_stations(&self) -> Vec<Station> {
+        self.checks
+            .iter()
+            .filter_map(|check| match check.snapped_to {
+                Some((_, [x, y])) => Some(Station {
+                    x,
+                    y,
+                    ..check.station.clone()
+                }),
+                None if check.is_valid() => Some(check.station.clone()),
+                None => None,
+            })
+            .collect()
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let stations = self.output_stations();
+        for check in self.checks.iter() {
+            if !check.is_valid() && check.snapped_to.is_none() {
+                log::warn!(
+                    "Leaving station {} out of {}: {}",
+                    check.station.name,
+                    path.display(),
+                    check
+                        .issues
+                        .iter()
+                        .map(|issue| issue.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", self.outputs)?;
+        writeln!(file, "{} !# of stations", stations.len())?;
+        for (local_index, station) in stations.iter().enumerate() {
+            writeln!(
+                file,
+                "{} {} {} {} !{}",
+                local_index + 1,
+                station.x,
+                station.y,
+                station.z,
+                station.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let invalid = self.invalid();
+        write!(
+            f,
+            "{} stations, {} with issues",
+            self.checks.len(),
+            invalid.len()
+        )?;
+        for check in invalid {
+            let issues: Vec<String> = check.issues.iter().map(|issue| issue.to_string()).collect();
+            write!(f, "\n{}: {}", check.station.name, issues.join(", "))?;
+            if let Some(node_id) = check.snapped_to() {
+                write!(f, " (snapped to node {})", node_id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StationsError {
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Invalid station list: {0}")]
+    ParseError(String),
+}
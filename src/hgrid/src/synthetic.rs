@@ -0,0 +1,423 @@
+use super::crs::Crs;
+use super::editing::HgridEditError;
+use super::geometry::signed_polygon_area;
+use super::hgrid::{Hgrid, HgridTryFromError};
+use super::meshgen::SpatialFunction;
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ElementType {
+    #[default]
+    Triangle,
+    Quad,
+}
+
+// Structured (nx + 1) x (ny + 1) node lattice mapped to physical space. Cells for which
+// is_cell_active is false are left out, and nodes no element uses are dropped. Depths are
+// positive down, as in the gr3 files.
+struct Lattice<'a> {
+    nx: usize,
+    ny: usize,
+    periodic_i: bool,
+    element_type: ElementType,
+    map: &'a dyn Fn(usize, usize) -> [f64; 2],
+    is_cell_active: &'a dyn Fn(usize, usize) -> bool,
+    depth: &'a dyn Fn(f64, f64) -> f64,
+}
+
+impl Lattice<'_> {
+    fn hgrid(
+        &self,
+        crs: Option<Arc<Crs>>,
+        description: Option<String>,
+    ) -> Result<Hgrid, HgridTryFromError> {
+        let columns = if self.periodic_i {
+            self.nx
+        } else {
+            self.nx + 1
+        };
+        let lattice_index = |i: usize, j: usize| j * columns + (i % columns);
+        let mut cells = Vec::new();
+        for j in 0..self.ny {
+            for i in 0..self.nx {
+                if (self.is_cell_active)(i, j) {
+                    let mut cell = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                    // Maps such as the annulus (i along theta, j along r) turn the cell clockwise.
+                    let corners = cell.map(|(i, j)| (self.map)(i % columns, j));
+                    if signed_polygon_area(&corners) < 0. {
+                        cell.reverse();
+                    }
+                    cells.push(cell.map(|(i, j)| lattice_index(i, j)));
+                }
+            }
+        }
+        let mut node_ids: BTreeMap<usize, u32> = cells.iter().flatten().map(|&k| (k, 0)).collect();
+        let mut nodes = BTreeMap::new();
+        for (local_index, (&k, node_id)) in node_ids.iter_mut().enumerate() {
+            *node_id = local_index as u32 + 1;
+            let [x, y] = (self.map)(k % columns, k / columns);
+            nodes.insert(*node_id, (vec![x, y], Some(vec![-(self.depth)(x, y)])));
+        }
+        let mut elements = BTreeMap::new();
+        for cell in cells {
+            let [a, b, c, d] = cell.map(|k| node_ids[&k]);
+            let children = match self.element_type {
+                ElementType::Quad => vec![vec![a, b, c, d]],
+                ElementType::Triangle => vec![vec![a, b, c], vec![a, c, d]],
+            };
+            for child in children {
+                elements.insert(elements.len() as u32 + 1, child);
+            }
+        }
+        Hgrid::from_parts(nodes, crs, elements, BTreeMap::new(), description)
+    }
+}
+
+// Rebuilds the boundaries with open segments wherever is_open holds for both ends of a side of
+// the outer ring.
+fn assign_boundaries(
+    hgrid: &Hgrid,
+    is_open: &dyn Fn([f64; 2], [f64; 2]) -> bool,
+) -> Result<Hgrid, HgridEditError> {
+    let nodes = hgrid.nodes().btree_map();
+    let coord = |node_id: u32| {
+        let (coord, _) = &nodes[&node_id];
+        [coord[0], coord[1]]
+    };
+    let mut editor = hgrid.edit();
+    editor.rebuild_boundaries(&|a, b| is_open(coord(a), coord(b)))?;
+    editor.commit()
+}
+
+fn is_close(a: f64, b: f64, scale: f64) -> bool {
+    (a - b).abs() <= 1e-9 * scale.max(1.)
+}
+
+// Straight channel along x with open boundaries at both ends and a parabolic cross-section
+// unless a depth function is given.
+#[derive(Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct RectangularChannel {
+    #[builder(default = "10_000.")]
+    length: f64,
+    #[builder(default = "1_000.")]
+    width: f64,
+    #[builder(default = "50")]
+    nx: usize,
+    #[builder(default = "5")]
+    ny: usize,
+    #[builder(default)]
+    element_type: ElementType,
+    #[builder(default = "10.")]
+    max_depth: f64,
+    #[builder(default = "2.")]
+    bank_depth: f64,
+    #[builder(setter(strip_option), default)]
+    depth: Option<SpatialFunction>,
+    #[builder(default = "[0., 0.]")]
+    origin: [f64; 2],
+    #[builder(setter(strip_option), default)]
+    crs: Option<Arc<Crs>>,
+}
+
+impl RectangularChannelBuilder {
+    pub fn validate(&self) -> Result<(), RectangularChannelBuilderError> {
+        let (nx, ny) = (self.nx.unwrap_or(50), self.ny.unwrap_or(5));
+        if nx < 1 || ny < 1 {
+            return Err(RectangularChannelBuilderError::ValidationError(format!(
+                "nx and ny must be >= 1 but got nx={}, ny={}",
+                nx, ny
+            )));
+        }
+        let (length, width) = (self.length.unwrap_or(10_000.), self.width.unwrap_or(1_000.));
+        if length <= 0. || width <= 0. {
+            return Err(RectangularChannelBuilderError::ValidationError(format!(
+                "length and width must be > 0. but got length={}, width={}",
+                length, width
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl RectangularChannel {
+    pub fn generate(&self) -> Result<Hgrid, SyntheticMeshError> {
+        let [x0, y0] = self.origin;
+        let (dx, dy) = (self.length / self.nx as f64, self.width / self.ny as f64);
+        let half_width = self.width / 2.;
+        let default_depth = |_x: f64, y: f64| {
+            let eta = (y - y0 - half_width) / half_width;
+            self.bank_depth + (self.max_depth - self.bank_depth) * (1. - eta * eta)
+        };
+        let depth: &dyn Fn(f64, f64) -> f64 = match &self.depth {
+            Some(depth) => depth.as_ref(),
+            None => &default_depth,
+        };
+        let hgrid = Lattice {
+            nx: self.nx,
+            ny: self.ny,
+            periodic_i: false,
+            element_type: self.element_type,
+            map: &|i, j| [x0 + i as f64 * dx, y0 + j as f64 * dy],
+            is_cell_active: &|_, _| true,
+            depth,
+        }
+        .hgrid(self.crs.clone(), Some("rectangular channel".to_string()))?;
+        let x1 = x0 + self.length;
+        let scale = self.length;
+        Ok(assign_boundaries(&hgrid, &|a, b| {
+            (is_close(a[0], x0, scale) && is_close(b[0], x0, scale))
+                || (is_close(a[0], x1, scale) && is_close(b[0], x1, scale))
+        })?)
+    }
+}
+
+// Beach sloping linearly from offshore_depth at the open western boundary to shore_depth at the
+// eastern land boundary; a negative shore_depth gives a dry strip for wetting and drying tests.
+#[derive(Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct SlopingBeach {
+    #[builder(default = "10_000.")]
+    length: f64,
+    #[builder(default = "2_000.")]
+    width: f64,
+    #[builder(default = "50")]
+    nx: usize,
+    #[builder(default = "10")]
+    ny: usize,
+    #[builder(default)]
+    element_type: ElementType,
+    #[builder(default = "20.")]
+    offshore_depth: f64,
+    #[builder(default = "-2.")]
+    shore_depth: f64,
+    #[builder(default = "[0., 0.]")]
+    origin: [f64; 2],
+    #[builder(setter(strip_option), default)]
+    crs: Option<Arc<Crs>>,
+}
+
+impl SlopingBeachBuilder {
+    pub fn validate(&self) -> Result<(), SlopingBeachBuilderError> {
+        let (nx, ny) = (self.nx.unwrap_or(50), self.ny.unwrap_or(10));
+        if nx < 1 || ny < 1 {
+            return Err(SlopingBeachBuilderError::ValidationError(format!(
+                "nx and ny must be >= 1 but got nx={}, ny={}",
+                nx, ny
+            )));
+        }
+        let (length, width) = (self.length.unwrap_or(10_000.), self.width.unwrap_or(2_000.));
+        if length <= 0. || width <= 0. {
+            return Err(SlopingBeachBuilderError::ValidationError(format!(
+                "length and width must be > 0. but got length={}, width={}",
+                length, width
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl SlopingBeach {
+    pub fn generate(&self) -> Result<Hgrid, SyntheticMeshError> {
+        let [x0, y0] = self.origin;
+        let (dx, dy) = (self.length / self.nx as f64, self.width / self.ny as f64);
+        let hgrid = Lattice {
+            nx: self.nx,
+            ny: self.ny,
+            periodic_i: false,
+            element_type: self.element_type,
+            map: &|i, j| [x0 + i as f64 * dx, y0 + j as f64 * dy],
+            is_cell_active: &|_, _| true,
+            depth: &|x, _y| {
+                self.offshore_depth
+                    + (self.shore_depth - self.offshore_depth) * (x - x0) / self.length
+            },
+        }
+        .hgrid(self.crs.clone(), Some("sloping beach".to_string()))?;
+        let scale = self.length;
+        Ok(assign_boundaries(&hgrid, &|a, b| {
+            is_close(a[0], x0, scale) && is_close(b[0], x0, scale)
+        })?)
+    }
+}
+
+// Rectangular basin, open to the west, with a rectangular island at its center. The depth
+// shoals linearly from max_depth far from the island to island_depth along its shore.
+#[derive(Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct IslandBasin {
+    #[builder(default = "10_000.")]
+    length: f64,
+    #[builder(default = "10_000.")]
+    width: f64,
+    #[builder(default = "40")]
+    nx: usize,
+    #[builder(default = "40")]
+    ny: usize,
+    #[builder(default = "0.2")]
+    island_fraction: f64,
+    #[builder(default)]
+    element_type: ElementType,
+    #[builder(default = "30.")]
+    max_depth: f64,
+    #[builder(default = "5.")]
+    island_depth: f64,
+    #[builder(default = "[0., 0.]")]
+    origin: [f64; 2],
+    #[builder(setter(strip_option), default)]
+    crs: Option<Arc<Crs>>,
+}
+
+impl IslandBasinBuilder {
+    pub fn validate(&self) -> Result<(), IslandBasinBuilderError> {
+        let (nx, ny) = (self.nx.unwrap_or(40), self.ny.unwrap_or(40));
+        if nx < 3 || ny < 3 {
+            return Err(IslandBasinBuilderError::ValidationError(format!(
+                "nx and ny must be >= 3 but got nx={}, ny={}",
+                nx, ny
+            )));
+        }
+        let (length, width) = (
+            self.length.unwrap_or(10_000.),
+            self.width.unwrap_or(10_000.),
+        );
+        if length <= 0. || width <= 0. {
+            return Err(IslandBasinBuilderError::ValidationError(format!(
+                "length and width must be > 0. but got length={}, width={}",
+                length, width
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl IslandBasin {
+    pub fn generate(&self) -> Result<Hgrid, SyntheticMeshError> {
+        let [x0, y0] = self.origin;
+        let (dx, dy) = (self.length / self.nx as f64, self.width / self.ny as f64);
+        let island_cells = |n: usize| {
+            let size = ((n as f64 * self.island_fraction).round() as usize).clamp(1, n - 2);
+            let start = (n - size) / 2;
+            start..start + size
+        };
+        let (island_i, island_j) = (island_cells(self.nx), island_cells(self.ny));
+        let island = [
+            x0 + island_i.start as f64 * dx,
+            y0 + island_j.start as f64 * dy,
+            x0 + island_i.end as f64 * dx,
+            y0 + island_j.end as f64 * dy,
+        ];
+        let reach = (self.length.min(self.width) - (island[2] - island[0])) / 2.;
+        let hgrid = Lattice {
+            nx: self.nx,
+            ny: self.ny,
+            periodic_i: false,
+            element_type: self.element_type,
+            map: &|i, j| [x0 + i as f64 * dx, y0 + j as f64 * dy],
+            is_cell_active: &|i, j| !(island_i.contains(&i) && island_j.contains(&j)),
+            depth: &|x, y| {
+                let distance = (island[0] - x)
+                    .max(x - island[2])
+                    .max(island[1] - y)
+                    .max(y - island[3])
+                    .max(0.);
+                self.island_depth
+                    + (self.max_depth - self.island_depth) * (distance / reach).min(1.)
+            },
+        }
+        .hgrid(self.crs.clone(), Some("basin with island".to_string()))?;
+        let scale = self.length;
+        Ok(assign_boundaries(&hgrid, &|a, b| {
+            is_close(a[0], x0, scale) && is_close(b[0], x0, scale)
+        })?)
+    }
+}
+
+// Ring between two circles, split into ntheta sectors and nr radial bands. The inner circle is
+// an island; the outer circle is land unless open_outer is set.
+#[derive(Builder, Clone)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct Annulus {
+    #[builder(default = "1_000.")]
+    inner_radius: f64,
+    #[builder(default = "5_000.")]
+    outer_radius: f64,
+    #[builder(default = "10")]
+    nr: usize,
+    #[builder(default = "48")]
+    ntheta: usize,
+    #[builder(default)]
+    element_type: ElementType,
+    #[builder(default = "5.")]
+    inner_depth: f64,
+    #[builder(default = "20.")]
+    outer_depth: f64,
+    #[builder(default)]
+    open_outer: bool,
+    #[builder(default = "[0., 0.]")]
+    center: [f64; 2],
+    #[builder(setter(strip_option), default)]
+    crs: Option<Arc<Crs>>,
+}
+
+impl AnnulusBuilder {
+    pub fn validate(&self) -> Result<(), AnnulusBuilderError> {
+        let (ntheta, nr) = (self.ntheta.unwrap_or(48), self.nr.unwrap_or(10));
+        if ntheta < 3 || nr < 1 {
+            return Err(AnnulusBuilderError::ValidationError(format!(
+                "ntheta must be >= 3 and nr >= 1 but got ntheta={}, nr={}",
+                ntheta, nr
+            )));
+        }
+        let inner_radius = self.inner_radius.unwrap_or(1_000.);
+        let outer_radius = self.outer_radius.unwrap_or(5_000.);
+        if !(inner_radius > 0. && inner_radius < outer_radius) {
+            return Err(AnnulusBuilderError::ValidationError(format!(
+                "Radii must satisfy 0 < inner_radius < outer_radius but got {} and {}",
+                inner_radius, outer_radius
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Annulus {
+    pub fn generate(&self) -> Result<Hgrid, SyntheticMeshError> {
+        let [xc, yc] = self.center;
+        let dr = (self.outer_radius - self.inner_radius) / self.nr as f64;
+        let dtheta = 2. * PI / self.ntheta as f64;
+        let hgrid = Lattice {
+            nx: self.ntheta,
+            ny: self.nr,
+            periodic_i: true,
+            element_type: self.element_type,
+            map: &|i, j| {
+                let radius = self.inner_radius + j as f64 * dr;
+                let theta = i as f64 * dtheta;
+                [xc + radius * theta.cos(), yc + radius * theta.sin()]
+            },
+            is_cell_active: &|_, _| true,
+            depth: &|x, y| {
+                let radius = (x - xc).hypot(y - yc);
+                self.inner_depth
+                    + (self.outer_depth - self.inner_depth) * (radius - self.inner_radius)
+                        / (self.outer_radius - self.inner_radius)
+            },
+        }
+        .hgrid(self.crs.clone(), Some("annulus".to_string()))?;
+        let open_outer = self.open_outer;
+        Ok(assign_boundaries(&hgrid, &|_, _| open_outer)?)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SyntheticMeshError {
+    #[error(transparent)]
+    HgridTryFromError(#[from] HgridTryFromError),
+    #[error(transparent)]
+    HgridEditError(#[from] HgridEditError),
+}
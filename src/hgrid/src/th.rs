@@ -0,0 +1,304 @@
+use super::hgrid::Hgrid;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::fmt;
+use std::fs::File;
+use std::io::{prelude::*, BufReader};
+use std::path::Path;
+use thiserror::Error;
+
+// Series of (seconds since the start of the run, value) pairs.
+pub type Series = Vec<(f64, f64)>;
+
+// Linear interpolation, holding the first and last values outside of the series.
+pub fn interpolate(series: &[(f64, f64)], time: f64) -> f64 {
+    let after = series.partition_point(|(t, _)| *t <= time);
+    match after {
+        0 => series[0].1,
+        n if n == series.len() => series[n - 1].1,
+        n => {
+            let (t0, v0) = series[n - 1];
+            let (t1, v1) = series[n];
+            v0 + (v1 - v0) * (time - t0) / (t1 - t0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThKind {
+    Elevation,
+    Flux,
+    Temperature,
+    Salinity,
+}
+
+impl ThKind {
+    pub fn file_name(&self) -> &str {
+        match self {
+            ThKind::Elevation => "elev.th",
+            ThKind::Flux => "flux.th",
+            ThKind::Temperature => "TEM_1.th",
+            ThKind::Salinity => "SAL_1.th",
+        }
+    }
+}
+
+impl fmt::Display for ThKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file_name())
+    }
+}
+
+pub fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.naive_utc());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(datetime);
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+}
+
+fn open_boundary_count(hgrid: &Hgrid) -> usize {
+    hgrid
+        .boundaries()
+        .and_then(|boundaries| boundaries.open())
+        .map_or(0, |open| open.nodes_ids().len())
+}
+
+// ASCII time history: every row holds a time in seconds from the start of the run and one
+// value per column, e.g. per open boundary in elev.th or per source in vsource.th.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeHistory {
+    times: Vec<f64>,
+    rows: Vec<Vec<f64>>,
+}
+
+impl TimeHistory {
+    pub fn new(times: Vec<f64>, rows: Vec<Vec<f64>>) -> Result<Self, ThError> {
+        if times.is_empty() {
+            return Err(ThError::Empty);
+        }
+        if times.len() != rows.len() {
+            return Err(ThError::RowCountMismatch(times.len(), rows.len()));
+        }
+        if let Some(step) = times.windows(2).position(|pair| pair[1] <= pair[0]) {
+            return Err(ThError::NonIncreasingTime(step + 2));
+        }
+        let ncolumns = rows[0].len();
+        if let Some(row) = rows.iter().position(|row| row.len() != ncolumns) {
+            return Err(ThError::RaggedRow(row + 1, ncolumns, rows[row].len()));
+        }
+        Ok(Self { times, rows })
+    }
+
+    pub fn from_columns(times: Vec<f64>, columns: &[Vec<f64>]) -> Result<Self, ThError> {
+        if let Some(column) = columns
+            .iter()
+            .position(|column| column.len() != times.len())
+        {
+            return Err(ThError::RowCountMismatch(
+                times.len(),
+                columns[column].len(),
+            ));
+        }
+        let rows = (0..times.len())
+            .map(|step| columns.iter().map(|column| column[step]).collect())
+            .collect();
+        Self::new(times, rows)
+    }
+
+    // Samples every series at 0, dt, 2 dt, ... up to end, one column per series.
+    pub fn from_series(series: &[Series], dt: f64, end: f64) -> Result<Self, ThError> {
+        if dt <= 0. {
+            return Err(ThError::InvalidTimeStep(dt));
+        }
+        if let Some(column) = series.iter().position(|series| series.is_empty()) {
+            return Err(ThError::EmptySeries(column + 1));
+        }
+        let steps = (end / dt + 1e-9).floor().max(0.) as usize;
+        let times: Vec<f64> = (0..=steps).map(|step| step as f64 * dt).collect();
+        let rows = times
+            .iter()
+            .map(|&time| {
+                series
+                    .iter()
+                    .map(|series| interpolate(series, time))
+                    .collect()
+            })
+            .collect();
+        Self::new(times, rows)
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, ThError> {
+        let fname = path.display().to_string();
+        let file = File::open(path).map_err(|e| ThError::IoError(fname.clone(), e.to_string()))?;
+        let mut times = Vec::new();
+        let mut rows = Vec::new();
+        for (line_index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| ThError::IoError(fname.clone(), e.to_string()))?;
+            let mut values = Vec::new();
+            for item in line.split_whitespace() {
+                values.push(item.parse::<f64>().map_err(|_| {
+                    ThError::ParseError(format!(
+                        "{}: expected a number in line {} but found {}",
+                        fname,
+                        line_index + 1,
+                        item
+                    ))
+                })?);
+            }
+            if values.is_empty() {
+                continue;
+            }
+            times.push(values.remove(0));
+            rows.push(values);
+        }
+        Self::new(times, rows)
+    }
+
+    // Reads a CSV whose first column holds datetimes and whose other columns hold one value
+    // each. Times are counted from start, or from the first row when start is None.
+    pub fn from_csv_path(path: &Path, start: Option<NaiveDateTime>) -> Result<Self, ThError> {
+        let fname = path.display().to_string();
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)
+            .map_err(|e| ThError::IoError(fname.clone(), e.to_string()))?;
+        let mut datetimes = Vec::new();
+        let mut rows = Vec::new();
+        for (row_index, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| ThError::ParseError(e.to_string()))?;
+            let line = row_index + 2;
+            let datetime = record.get(0).and_then(parse_datetime).ok_or_else(|| {
+                ThError::ParseError(format!(
+                    "{}: expected a datetime in the first column of line {}",
+                    fname, line
+                ))
+            })?;
+            let mut values = Vec::new();
+            for item in record.iter().skip(1) {
+                values.push(item.parse::<f64>().map_err(|_| {
+                    ThError::ParseError(format!(
+                        "{}: expected a number in line {} but found {}",
+                        fname, line, item
+                    ))
+                })?);
+            }
+            datetimes.push(datetime);
+            rows.push(values);
+        }
+        let start = match start.or_else(|| datetimes.first().copied()) {
+            Some(start) => start,
+            None => return Err(ThError::Empty),
+        };
+        let times = datetimes
+            .iter()
+            .map(|datetime| (*datetime - start).num_milliseconds() as f64 / 1000.)
+            .collect();
+        Self::new(times, rows)
+    }
+
+    pub fn times(&self) -> &Vec<f64> {
+        &self.times
+    }
+
+    pub fn rows(&self) -> &Vec<Vec<f64>> {
+        &self.rows
+    }
+
+    pub fn ncolumns(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn column(&self, column: usize) -> Series {
+        self.times
+            .iter()
+            .zip(self.rows.iter())
+            .map(|(time, row)| (*time, row[column]))
+            .collect()
+    }
+
+    pub fn dt(&self) -> Option<f64> {
+        (self.times.len() > 1).then(|| self.times[1] - self.times[0])
+    }
+
+    // Linear interpolation of every column to a fixed time step, starting at 0 and ending at
+    // the last time unless an end is given; values before the first time and after the last
+    // are held constant.
+    pub fn resample(&self, dt: f64, end: Option<f64>) -> Result<Self, ThError> {
+        let series: Vec<Series> = (0..self.ncolumns()).map(|c| self.column(c)).collect();
+        let end = end.unwrap_or(self.times[self.times.len() - 1]);
+        Self::from_series(&series, dt, end)
+    }
+
+    // Checks that there is one column per open boundary segment of the hgrid, i.e. that the
+    // file forces every open boundary.
+    pub fn validate(&self, hgrid: &Hgrid) -> Result<(), ThError> {
+        let open_boundaries = open_boundary_count(hgrid);
+        self.validate_with_mask(hgrid, &vec![true; open_boundaries])
+    }
+
+    // SCHISM reads one column per open boundary whose flag for the file (iettype, ifltype,
+    // itetype or isatype) is 1; th_boundaries marks those boundaries in segment order.
+    pub fn validate_with_mask(&self, hgrid: &Hgrid, th_boundaries: &[bool]) -> Result<(), ThError> {
+        let open_boundaries = open_boundary_count(hgrid);
+        if th_boundaries.len() != open_boundaries {
+            return Err(ThError::BoundaryMaskMismatch(
+                open_boundaries,
+                th_boundaries.len(),
+            ));
+        }
+        let expected = th_boundaries.iter().filter(|&&uses_th| uses_th).count();
+        if self.ncolumns() != expected {
+            return Err(ThError::ColumnCountMismatch(expected, self.ncolumns()));
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)
+    }
+}
+
+impl fmt::Display for TimeHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (time, row) in self.times.iter().zip(self.rows.iter()) {
+            write!(f, "{}", time)?;
+            for value in row {
+                write!(f, " {}", value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ThError {
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    #[error("The time history has no rows")]
+    Empty,
+    #[error("Series {0} is empty")]
+    EmptySeries(usize),
+    #[error("Expected {0} rows but got {1}")]
+    RowCountMismatch(usize, usize),
+    #[error("Times must be increasing but row {0} is not")]
+    NonIncreasingTime(usize),
+    #[error("Row {0} has {2} values but the first row has {1}")]
+    RaggedRow(usize, usize, usize),
+    #[error("The time step must be > 0. but got {0}")]
+    InvalidTimeStep(f64),
+    #[error("Expected one column per open boundary forced by the file ({0}) but got {1}")]
+    ColumnCountMismatch(usize, usize),
+    #[error("The hgrid has {0} open boundaries but the boundary mask has {1} entries")]
+    BoundaryMaskMismatch(usize, usize),
+}
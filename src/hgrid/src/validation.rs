@@ -0,0 +1,174 @@
+use super::boundaries::BoundaryType;
+use super::geometry::signed_polygon_area;
+use super::hgrid::Hgrid;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshIssue {
+    OrphanNode(u32),
+    DuplicateNode(u32, u32),
+    ClockwiseElement(u32),
+    DegenerateElement(u32),
+    NonConvexQuad(u32),
+    NonManifoldSide(u32, u32),
+    BoundarySideNotOnMeshBoundary(BoundaryType, usize, u32, u32),
+    UnassignedBoundarySide(u32, u32),
+}
+
+impl fmt::Display for MeshIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshIssue::OrphanNode(node_id) => {
+                write!(f, "node {} does not belong to any element", node_id)
+            }
+            MeshIssue::DuplicateNode(a, b) => {
+                write!(f, "nodes {} and {} have the same coordinates", a, b)
+            }
+            MeshIssue::ClockwiseElement(element_id) => {
+                write!(f, "element {} is ordered clockwise", element_id)
+            }
+            MeshIssue::DegenerateElement(element_id) => {
+                write!(f, "element {} has zero area", element_id)
+            }
+            MeshIssue::NonConvexQuad(element_id) => {
+                write!(f, "quad {} is not convex", element_id)
+            }
+            MeshIssue::NonManifoldSide(a, b) => {
+                write!(f, "side {}-{} is shared by more than two elements", a, b)
+            }
+            MeshIssue::BoundarySideNotOnMeshBoundary(boundary_type, index, a, b) => write!(
+                f,
+                "side {}-{} of {:?} boundary {} is not on the mesh boundary",
+                a,
+                b,
+                boundary_type,
+                index + 1
+            ),
+            MeshIssue::UnassignedBoundarySide(a, b) => {
+                write!(f, "mesh boundary side {}-{} is not in any boundary", a, b)
+            }
+        }
+    }
+}
+
+fn is_convex(coords: &[[f64; 2]]) -> bool {
+    let n = coords.len();
+    (0..n).all(|i| {
+        let [a, b, c] = [coords[i], coords[(i + 1) % n], coords[(i + 2) % n]];
+        (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0]) > 0.
+    })
+}
+
+impl Hgrid {
+    // Structural problems that make the mesh unusable by SCHISM: connectivity, element orientation
+    // and boundary segments that do not follow the mesh boundary.
+    pub fn mesh_issues(&self) -> Vec<MeshIssue> {
+        let nodes = self.nodes().btree_map();
+        let elements = self.elements().btree_map();
+        let mut issues = Vec::new();
+        let used: BTreeSet<u32> = elements.values().flatten().copied().collect();
+        issues.extend(
+            nodes
+                .keys()
+                .filter(|node_id| !used.contains(node_id))
+                .map(|&node_id| MeshIssue::OrphanNode(node_id)),
+        );
+        let mut seen: BTreeMap<(u64, u64), u32> = BTreeMap::new();
+        for (&node_id, (coord, _)) in nodes.iter() {
+            let key = (coord[0].to_bits(), coord[1].to_bits());
+            match seen.get(&key) {
+                Some(&first) => issues.push(MeshIssue::DuplicateNode(first, node_id)),
+                None => {
+                    seen.insert(key, node_id);
+                }
+            }
+        }
+        for (&element_id, node_ids) in elements.iter() {
+            let coords: Vec<[f64; 2]> = node_ids
+                .iter()
+                .map(|node_id| {
+                    let (coord, _) = &nodes[node_id];
+                    [coord[0], coord[1]]
+                })
+                .collect();
+            let area = signed_polygon_area(&coords);
+            if area == 0. {
+                issues.push(MeshIssue::DegenerateElement(element_id));
+            } else if area < 0. {
+                issues.push(MeshIssue::ClockwiseElement(element_id));
+            } else if coords.len() == 4 && !is_convex(&coords) {
+                issues.push(MeshIssue::NonConvexQuad(element_id));
+            }
+        }
+        let side_elements = self.side_elements();
+        issues.extend(
+            side_elements
+                .iter()
+                .filter(|(_, element_ids)| element_ids.len() > 2)
+                .map(|(&(a, b), _)| MeshIssue::NonManifoldSide(a, b)),
+        );
+        let (boundary_issues, assigned) = self.check_boundary_sides(&side_elements);
+        issues.extend(boundary_issues);
+        issues.extend(
+            side_elements
+                .iter()
+                .filter(|(side, element_ids)| element_ids.len() == 1 && !assigned.contains(side))
+                .map(|(&(a, b), _)| MeshIssue::UnassignedBoundarySide(a, b)),
+        );
+        issues
+    }
+
+    // Boundary segment sides that are not mesh boundary sides, the check HgridEditor::commit()
+    // runs on every edit.
+    pub fn misplaced_boundary_sides(&self) -> Vec<MeshIssue> {
+        self.check_boundary_sides(&self.side_elements()).0
+    }
+
+    fn check_boundary_sides(
+        &self,
+        side_elements: &BTreeMap<(u32, u32), Vec<u32>>,
+    ) -> (Vec<MeshIssue>, BTreeSet<(u32, u32)>) {
+        let mut issues = Vec::new();
+        let boundary_type_map = self
+            .boundaries()
+            .map(|boundaries| boundaries.to_boundary_type_map())
+            .unwrap_or_default();
+        let mut assigned: BTreeSet<(u32, u32)> = BTreeSet::new();
+        for (&boundary_type, segments) in boundary_type_map.iter() {
+            for (index, segment) in segments.iter().enumerate() {
+                if segment.is_empty() {
+                    continue;
+                }
+                // Interior boundaries are closed rings that may not repeat their first node.
+                let first = segment[0];
+                let last = segment[segment.len() - 1];
+                let closing = match boundary_type {
+                    BoundaryType::Interior if segment.len() > 2 && first != last => {
+                        Some((last, first))
+                    }
+                    _ => None,
+                };
+                let sides = segment
+                    .windows(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .chain(closing);
+                for (a, b) in sides {
+                    let key = (a.min(b), a.max(b));
+                    match side_elements.get(&key) {
+                        Some(element_ids) if element_ids.len() == 1 => {
+                            assigned.insert(key);
+                        }
+                        _ => issues.push(MeshIssue::BoundarySideNotOnMeshBoundary(
+                            boundary_type,
+                            index,
+                            a,
+                            b,
+                        )),
+                    }
+                }
+            }
+        }
+        (issues, assigned)
+    }
+}
@@ -0,0 +1,46 @@
+use super::crs::CrsError;
+use super::hgrid::Hgrid;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+const NORTHWARD_OFFSET: f64 = 1e-4;
+
+// Angle (degrees, counterclockwise) that rotates a geographic (east, north) vector into the
+// projected frame of the hgrid, as read by SCHISM from windrot_geo2proj.gr3.
+pub fn windrot_geo2proj(hgrid: &Hgrid) -> Result<BTreeMap<u32, f64>, WindrotError> {
+    let crs = hgrid.crs().ok_or(CrsError::MissingCrs)?;
+    let nodes = hgrid.nodes().btree_map();
+    if crs.is_geographic() {
+        return Ok(nodes.keys().map(|&node_id| (node_id, 0.)).collect());
+    }
+    let to_geographic = crs.to_geographic()?;
+    let from_geographic = crs.from_geographic()?;
+    let mut angles = BTreeMap::new();
+    for (node_id, (coord, _values)) in nodes.iter() {
+        let (lon, lat) = to_geographic
+            .convert((coord[0], coord[1]))
+            .map_err(|e| CrsError::ConversionError(*node_id, e.to_string()))?;
+        let (x_north, y_north) = from_geographic
+            .convert((lon, lat + NORTHWARD_OFFSET))
+            .map_err(|e| CrsError::ConversionError(*node_id, e.to_string()))?;
+        let dx: f64 = x_north - coord[0];
+        let dy: f64 = y_north - coord[1];
+        angles.insert(*node_id, (-dx).atan2(dy).to_degrees());
+    }
+    Ok(angles)
+}
+
+pub fn write_windrot_geo2proj(hgrid: &Hgrid, path: &Path) -> Result<(), WindrotError> {
+    let angles = windrot_geo2proj(hgrid)?;
+    hgrid.write_node_values(path, "windrot_geo2proj", &angles)?;
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum WindrotError {
+    #[error(transparent)]
+    CrsError(#[from] CrsError),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,5 @@
+pub use namelist::Namelist;
+pub use param::Param;
+
+pub mod namelist;
+pub mod param;
@@ -0,0 +1,579 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    Logical(bool),
+    Text(String),
+    List(Vec<Value>),
+}
+
+// Splits on the separator outside of quoted strings.
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == separator => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+impl Value {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().trim_end_matches(',').trim();
+        let items = split_unquoted(raw, ',');
+        if items.len() > 1 {
+            return items
+                .into_iter()
+                .map(Value::parse)
+                .collect::<Option<Vec<_>>>()
+                .map(Value::List);
+        }
+        if raw.len() >= 2
+            && ((raw.starts_with('\'') && raw.ends_with('\''))
+                || (raw.starts_with('"') && raw.ends_with('"')))
+        {
+            return Some(Value::Text(raw[1..raw.len() - 1].to_string()));
+        }
+        let lower = raw.to_lowercase();
+        match lower.as_str() {
+            ".true." | ".t." | "t" | "true" => return Some(Value::Logical(true)),
+            ".false." | ".f." | "f" | "false" => return Some(Value::Logical(false)),
+            _ => {}
+        }
+        if let Ok(value) = lower.parse::<i64>() {
+            return Some(Value::Integer(value));
+        }
+        lower.replace('d', "e").parse::<f64>().ok().map(Value::Real)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Logical(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            // Debug keeps the decimal point so that Fortran reads a real.
+            Value::Real(value) => write!(f, "{:?}", value),
+            Value::Logical(true) => write!(f, ".true."),
+            Value::Logical(false) => write!(f, ".false."),
+            Value::Text(value) => write!(f, "'{}'", value),
+            Value::List(values) => {
+                let items: Vec<String> = values.iter().map(Value::to_string).collect();
+                write!(f, "{}", items.join(", "))
+            }
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Logical(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    // Blank and comment lines are kept verbatim.
+    Verbatim(String),
+    Entry {
+        key: String,
+        raw_value: String,
+        comment: Option<String>,
+        // Original text, dropped once the value changes.
+        original: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    name: String,
+    lines: Vec<Line>,
+}
+
+impl Group {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Entry { key, .. } => Some(key.as_str()),
+                Line::Verbatim(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry {
+                key: k, raw_value, ..
+            } if k.eq_ignore_ascii_case(key) => Some(raw_value.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.raw(key).and_then(Value::parse)
+    }
+
+    // Replaces the value in place, keeping its comment, or appends a new entry.
+    pub fn set(&mut self, key: &str, value: Value) {
+        let new_value = value.to_string();
+        for line in self.lines.iter_mut() {
+            if let Line::Entry {
+                key: k,
+                raw_value,
+                original,
+                ..
+            } = line
+            {
+                if k.eq_ignore_ascii_case(key) {
+                    if *raw_value != new_value {
+                        *raw_value = new_value;
+                        *original = None;
+                    }
+                    return;
+                }
+            }
+        }
+        self.lines.push(Line::Entry {
+            key: key.to_string(),
+            raw_value: new_value,
+            comment: None,
+            original: None,
+        });
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        let count = self.lines.len();
+        self.lines.retain(
+            |line| !matches!(line, Line::Entry { key: k, .. } if k.eq_ignore_ascii_case(key)),
+        );
+        self.lines.len() != count
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "&{}", self.name)?;
+        for line in self.lines.iter() {
+            match line {
+                Line::Verbatim(text) => writeln!(f, "{}", text)?,
+                Line::Entry {
+                    original: Some(text),
+                    ..
+                } => writeln!(f, "{}", text)?,
+                Line::Entry {
+                    key,
+                    raw_value,
+                    comment,
+                    original: None,
+                } => match comment {
+                    Some(comment) => writeln!(f, "  {} = {} !{}", key, raw_value, comment)?,
+                    None => writeln!(f, "  {} = {}", key, raw_value)?,
+                },
+            }
+        }
+        write!(f, "/")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Verbatim(String),
+    Group(Group),
+}
+
+// Fortran namelist file that keeps comments, blank lines and the order of groups and entries,
+// so that reading and writing an unchanged file gives back the same text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Namelist {
+    items: Vec<Item>,
+}
+
+impl Namelist {
+    pub fn from_path(path: &Path) -> Result<Self, NamelistError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| NamelistError::IoError(path.display().to_string(), e.to_string()))?;
+        contents.parse()
+    }
+
+    pub fn groups(&self) -> Vec<&Group> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Group(group) => Some(group),
+                Item::Verbatim(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups()
+            .into_iter()
+            .find(|group| group.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut Group> {
+        self.items.iter_mut().find_map(|item| match item {
+            Item::Group(group) if group.name.eq_ignore_ascii_case(name) => Some(group),
+            _ => None,
+        })
+    }
+
+    pub fn get(&self, group: &str, key: &str) -> Option<Value> {
+        self.group(group).and_then(|group| group.get(key))
+    }
+
+    // Sets the value, creating the group at the end of the file when missing.
+    pub fn set(&mut self, group: &str, key: &str, value: Value) {
+        if self.group(group).is_none() {
+            self.items.push(Item::Group(Group::new(group)));
+        }
+        self.group_mut(group).unwrap().set(key, value);
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+// Adds the entries of one line of a group, keeping its text as the original when given, and
+// returns whether the line ends the group with "/". A new assignment starts at every comma
+// separated item holding an "=", while items without one continue the array value of the
+// previous assignment, also when it started on an earlier line.
+fn parse_group_line(
+    group: &mut Group,
+    index: usize,
+    text: &str,
+    original: Option<&str>,
+) -> Result<bool, NamelistError> {
+    let parts = split_unquoted(text, '!');
+    let mut body = parts[0].trim();
+    let closes = body.ends_with('/');
+    if closes {
+        body = body[..body.len() - 1].trim_end();
+    }
+    // A line closing the group is written back without its "/", one entry per line.
+    let original = original.filter(|_| !closes);
+    if body.is_empty() {
+        if let Some(line) = original {
+            group.lines.push(Line::Verbatim(line.to_string()));
+        }
+        return Ok(closes);
+    }
+    let mut continued: Vec<String> = Vec::new();
+    let mut assignments: Vec<(String, Vec<String>)> = Vec::new();
+    for item in split_unquoted(body, ',') {
+        match split_unquoted(item, '=').as_slice() {
+            [key, raw_value] => {
+                assignments.push((key.trim().to_string(), vec![raw_value.trim().to_string()]))
+            }
+            [raw_value] => match assignments.last_mut() {
+                Some((_, values)) => values.push(raw_value.trim().to_string()),
+                None => continued.push(raw_value.trim().to_string()),
+            },
+            _ => {
+                return Err(NamelistError::ParseError(
+                    index + 1,
+                    format!("Expected key = value but found {}", text.trim()),
+                ))
+            }
+        }
+    }
+    continued.retain(|value| !value.is_empty());
+    let mut comment = (parts.len() > 1).then(|| parts[1..].join("!"));
+    if !continued.is_empty() {
+        let adjacent = matches!(group.lines.last(), Some(Line::Entry { .. }));
+        let previous = group.lines.iter_mut().rev().find_map(|line| match line {
+            Line::Entry {
+                raw_value,
+                comment,
+                original,
+                ..
+            } => Some((raw_value, comment, original)),
+            Line::Verbatim(_) => None,
+        });
+        let Some((raw_value, previous_comment, previous_original)) = previous else {
+            return Err(NamelistError::ParseError(
+                index + 1,
+                format!("Expected key = value but found {}", text.trim()),
+            ));
+        };
+        if !raw_value.is_empty() {
+            raw_value.push_str(", ");
+        }
+        raw_value.push_str(&continued.join(", "));
+        // The continuation line is written back with the entry while nothing sits between
+        // them and the line holds no other assignment.
+        *previous_original = match (previous_original.take(), original) {
+            (Some(previous), Some(line)) if adjacent && assignments.is_empty() => {
+                Some(format!("{}\n{}", previous, line))
+            }
+            _ => None,
+        };
+        if assignments.is_empty() && previous_comment.is_none() {
+            *previous_comment = comment.take();
+        }
+    }
+    let single = continued.is_empty() && assignments.len() == 1;
+    for (key, values) in assignments {
+        let values: Vec<&String> = values.iter().filter(|v| !v.is_empty()).collect();
+        group.lines.push(Line::Entry {
+            key,
+            raw_value: values
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            comment: comment.take(),
+            // Lines with several assignments are written back one per line.
+            original: original.filter(|_| single).map(str::to_string),
+        });
+    }
+    Ok(closes)
+}
+
+impl std::str::FromStr for Namelist {
+    type Err = NamelistError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let mut items = Vec::new();
+        let mut current: Option<Group> = None;
+        for (index, line) in contents.lines().enumerate() {
+            let trimmed = line.trim();
+            let Some(group) = current.as_mut() else {
+                if let Some(header) = trimmed.strip_prefix('&') {
+                    let name = header.split_whitespace().next().unwrap_or_default();
+                    if name.is_empty() {
+                        return Err(NamelistError::ParseError(
+                            index + 1,
+                            "Missing group name".to_string(),
+                        ));
+                    }
+                    // Entries may follow the name, e.g. "&CORE ipre = 0 /".
+                    let mut group = Group::new(name);
+                    let rest = header.trim_start()[name.len()..].trim();
+                    match parse_group_line(&mut group, index, rest, None)? {
+                        true => items.push(Item::Group(group)),
+                        false => current = Some(group),
+                    }
+                } else {
+                    items.push(Item::Verbatim(line.to_string()));
+                }
+                continue;
+            };
+            if trimmed == "/" || trimmed.eq_ignore_ascii_case("&end") {
+                items.push(Item::Group(current.take().unwrap()));
+                continue;
+            }
+            if parse_group_line(group, index, line, Some(line))? {
+                items.push(Item::Group(current.take().unwrap()));
+            }
+        }
+        if let Some(group) = current {
+            return Err(NamelistError::UnterminatedGroup(group.name));
+        }
+        Ok(Self { items })
+    }
+}
+
+impl fmt::Display for Namelist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in self.items.iter() {
+            match item {
+                Item::Verbatim(text) => writeln!(f, "{}", text)?,
+                Item::Group(group) => writeln!(f, "{}", group)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NamelistError {
+    #[error("Error reading {0}: {1}")]
+    IoError(String, String),
+    #[error("Parse error in line {0}: {1}")]
+    ParseError(usize, String),
+    #[error("Group {0} is not terminated by /")]
+    UnterminatedGroup(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+! leading comment
+
+&CORE
+  ipre = 0 !pre-processor flag
+  dt = 1.5d2
+  name = 'a, b ! c'
+
+  ! comment inside a group
+  levels = 1, 2,
+           3, 4 !continued
+  flag = .true., n = 3
+/
+&OUTPUT iof_hydro(1) = 1 /
+&EMPTY
+/
+";
+
+    #[test]
+    fn test_round_trip() {
+        let namelist: Namelist = SAMPLE.parse().unwrap();
+        let expected = "\
+! leading comment
+
+&CORE
+  ipre = 0 !pre-processor flag
+  dt = 1.5d2
+  name = 'a, b ! c'
+
+  ! comment inside a group
+  levels = 1, 2,
+           3, 4 !continued
+  flag = .true.
+  n = 3
+/
+&OUTPUT
+  iof_hydro(1) = 1
+/
+&EMPTY
+/
+";
+        assert_eq!(namelist.to_string(), expected);
+        assert_eq!(expected.parse::<Namelist>().unwrap().to_string(), expected);
+        assert_eq!(
+            namelist
+                .groups()
+                .iter()
+                .map(|group| group.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["CORE", "OUTPUT", "EMPTY"]
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        let mut namelist: Namelist = SAMPLE.parse().unwrap();
+        assert_eq!(namelist.get("core", "IPRE"), Some(Value::Integer(0)));
+        assert_eq!(namelist.get("CORE", "dt"), Some(Value::Real(150.)));
+        assert_eq!(namelist.get("CORE", "name"), Some(Value::from("a, b ! c")));
+        assert_eq!(
+            namelist.get("CORE", "levels"),
+            Some(Value::List((1..=4).map(Value::Integer).collect()))
+        );
+        assert_eq!(namelist.get("CORE", "flag"), Some(Value::Logical(true)));
+        assert_eq!(namelist.get("CORE", "n"), Some(Value::Integer(3)));
+        assert_eq!(
+            namelist.get("OUTPUT", "iof_hydro(1)"),
+            Some(Value::Integer(1))
+        );
+        // Changed values keep their comment; unchanged ones keep their original text.
+        namelist.set("CORE", "ipre", Value::from(1));
+        namelist.set("CORE", "dt", Value::from(150.));
+        namelist.set("CORE", "levels", Value::from(2));
+        namelist.set("EMPTY", "x", Value::from(false));
+        namelist.set("NEW", "y", Value::from(0.5));
+        let text = namelist.to_string();
+        assert!(text.contains("  ipre = 1 !pre-processor flag\n"));
+        assert!(text.contains("  dt = 150.0\n"));
+        assert!(text.contains("  levels = 2 !continued\n"));
+        assert!(text.contains("&EMPTY\n  x = .false.\n/\n"));
+        assert!(text.ends_with("&NEW\n  y = 0.5\n/\n"));
+        assert_eq!(text.parse::<Namelist>().unwrap().to_string(), text);
+        assert!(namelist.group_mut("CORE").unwrap().remove("n"));
+        assert_eq!(namelist.get("CORE", "n"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "&CORE\n  ipre = 0\n".parse::<Namelist>(),
+            Err(NamelistError::UnterminatedGroup(name)) if name == "CORE"
+        ));
+        assert!(matches!(
+            "&CORE\n  1, 2\n/\n".parse::<Namelist>(),
+            Err(NamelistError::ParseError(2, _))
+        ));
+        assert!(matches!(
+            "&\n/\n".parse::<Namelist>(),
+            Err(NamelistError::ParseError(1, _))
+        ));
+    }
+}
@@ -0,0 +1,425 @@
+use super::namelist::{Namelist, NamelistError, Value};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use schismrs_hgrid::Hgrid;
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+// Minimal param.nml with the CORE parameters SCHISM has no defaults for.
+const TEMPLATE: &str = "\
+! param.nml for SCHISM; see sample_inputs/param.nml in the SCHISM sources for all options.
+&CORE
+  ipre = 0 !pre-processor flag (1: on; 0: off)
+  ibc = 0 !baroclinic (0) or barotropic (1) option
+  ibtp = 1 !transport tracers in barotropic runs (1) or not (0)
+  rnday = 1.0 !total run time in days
+  dt = 100.0 !time step in seconds
+  msc2 = 24 !same as msc in .nml for WWM
+  mdc2 = 30 !same as mdc in .nml for WWM
+  ntracer_gen = 2
+  ntracer_age = 4
+  sed_class = 5
+  eco_class = 27
+  nspool = 36 !output step spool
+  ihfskip = 864 !stack spool; must be a multiple of nspool
+/
+
+&OPT
+  start_year = 2000
+  start_month = 1
+  start_day = 1
+  start_hour = 0.0
+  utc_start = 0.0 !hours behind UTC
+  ics = 2 !coordinate option: 1 Cartesian, 2 lon/lat
+  ihot = 0 !0: cold start; 1: hotstart with time reset; 2: continue from hotstart time
+/
+
+&SCHOUT
+  nhot = 0 !write hotstart (1) or not (0)
+  nhot_write = 864 !must be a multiple of ihfskip
+  iout_sta = 0 !station output (1) or not (0)
+  nspool_sta = 10
+  iof_hydro(1) = 1 !elevation
+/";
+
+// Coordinate option: 1 for Cartesian and 2 for longitude/latitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ics {
+    Cartesian,
+    Spherical,
+}
+
+impl Ics {
+    pub fn flag(&self) -> i64 {
+        match self {
+            Ics::Cartesian => 1,
+            Ics::Spherical => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ihot {
+    ColdStart,
+    // Hotstart with the time reset to 0.
+    HotStart,
+    // Hotstart continuing from the hotstart time, appending to outputs.
+    Continue,
+}
+
+impl Ihot {
+    pub fn flag(&self) -> i64 {
+        match self {
+            Ihot::ColdStart => 0,
+            Ihot::HotStart => 1,
+            Ihot::Continue => 2,
+        }
+    }
+}
+
+// Typed access to the CORE, OPT and SCHOUT groups of a param.nml. Everything else in the file,
+// including comments and ordering, is carried through untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    namelist: Namelist,
+}
+
+impl Default for Param {
+    fn default() -> Self {
+        Self {
+            namelist: TEMPLATE.parse().expect("The param.nml template is valid"),
+        }
+    }
+}
+
+impl From<Namelist> for Param {
+    fn from(namelist: Namelist) -> Self {
+        Self { namelist }
+    }
+}
+
+impl Param {
+    pub fn from_path(path: &Path) -> Result<Self, ParamError> {
+        Ok(Self::from(Namelist::from_path(path)?))
+    }
+
+    pub fn namelist(&self) -> &Namelist {
+        &self.namelist
+    }
+
+    pub fn namelist_mut(&mut self) -> &mut Namelist {
+        &mut self.namelist
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        self.namelist.write(path)
+    }
+
+    fn value(&self, group: &str, key: &str) -> Result<Value, ParamError> {
+        let raw = self
+            .namelist
+            .group(group)
+            .and_then(|g| g.raw(key).map(str::to_string))
+            .ok_or_else(|| ParamError::MissingKey(group.to_string(), key.to_string()))?;
+        Value::parse(&raw)
+            .ok_or_else(|| ParamError::InvalidValue(group.to_string(), key.to_string(), raw))
+    }
+
+    fn typed<T>(
+        &self,
+        group: &str,
+        key: &str,
+        convert: impl Fn(&Value) -> Option<T>,
+    ) -> Result<T, ParamError> {
+        let value = self.value(group, key)?;
+        convert(&value).ok_or_else(|| {
+            ParamError::InvalidValue(group.to_string(), key.to_string(), value.to_string())
+        })
+    }
+
+    fn f64(&self, group: &str, key: &str) -> Result<f64, ParamError> {
+        self.typed(group, key, Value::as_f64)
+    }
+
+    fn i64(&self, group: &str, key: &str) -> Result<i64, ParamError> {
+        self.typed(group, key, Value::as_i64)
+    }
+
+    pub fn start_time(&self) -> Result<NaiveDateTime, ParamError> {
+        let year = self.i64("OPT", "start_year")?;
+        let month = self.i64("OPT", "start_month")?;
+        let day = self.i64("OPT", "start_day")?;
+        let hour = self.f64("OPT", "start_hour")?;
+        let date =
+            NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).ok_or_else(|| {
+                ParamError::Invalid(format!("Invalid start date {}-{}-{}", year, month, day))
+            })?;
+        Ok(date.and_hms_opt(0, 0, 0).unwrap() + Duration::seconds((hour * 3600.).round() as i64))
+    }
+
+    pub fn set_start_time(&mut self, start_time: NaiveDateTime) {
+        let hour = start_time.hour() as f64
+            + start_time.minute() as f64 / 60.
+            + start_time.second() as f64 / 3600.;
+        self.namelist
+            .set("OPT", "start_year", Value::from(start_time.year() as i64));
+        self.namelist
+            .set("OPT", "start_month", Value::from(start_time.month() as i64));
+        self.namelist
+            .set("OPT", "start_day", Value::from(start_time.day() as i64));
+        self.namelist.set("OPT", "start_hour", Value::from(hour));
+    }
+
+    pub fn end_time(&self) -> Result<NaiveDateTime, ParamError> {
+        Ok(self.start_time()? + Duration::seconds((self.rnday()? * 86400.).round() as i64))
+    }
+
+    pub fn utc_start(&self) -> Result<f64, ParamError> {
+        self.f64("OPT", "utc_start")
+    }
+
+    pub fn set_utc_start(&mut self, utc_start: f64) {
+        self.namelist
+            .set("OPT", "utc_start", Value::from(utc_start));
+    }
+
+    pub fn rnday(&self) -> Result<f64, ParamError> {
+        self.f64("CORE", "rnday")
+    }
+
+    pub fn set_rnday(&mut self, rnday: f64) {
+        self.namelist.set("CORE", "rnday", Value::from(rnday));
+    }
+
+    pub fn dt(&self) -> Result<f64, ParamError> {
+        self.f64("CORE", "dt")
+    }
+
+    pub fn set_dt(&mut self, dt: f64) {
+        self.namelist.set("CORE", "dt", Value::from(dt));
+    }
+
+    pub fn nspool(&self) -> Result<i64, ParamError> {
+        self.i64("CORE", "nspool")
+    }
+
+    pub fn set_nspool(&mut self, nspool: i64) {
+        self.namelist.set("CORE", "nspool", Value::from(nspool));
+    }
+
+    pub fn ihfskip(&self) -> Result<i64, ParamError> {
+        self.i64("CORE", "ihfskip")
+    }
+
+    pub fn set_ihfskip(&mut self, ihfskip: i64) {
+        self.namelist.set("CORE", "ihfskip", Value::from(ihfskip));
+    }
+
+    pub fn ics(&self) -> Result<Ics, ParamError> {
+        match self.i64("OPT", "ics")? {
+            1 => Ok(Ics::Cartesian),
+            2 => Ok(Ics::Spherical),
+            other => Err(ParamError::InvalidValue(
+                "OPT".to_string(),
+                "ics".to_string(),
+                other.to_string(),
+            )),
+        }
+    }
+
+    pub fn set_ics(&mut self, ics: Ics) {
+        self.namelist.set("OPT", "ics", Value::from(ics.flag()));
+    }
+
+    pub fn ihot(&self) -> Result<Ihot, ParamError> {
+        match self.i64("OPT", "ihot")? {
+            0 => Ok(Ihot::ColdStart),
+            1 => Ok(Ihot::HotStart),
+            2 => Ok(Ihot::Continue),
+            other => Err(ParamError::InvalidValue(
+                "OPT".to_string(),
+                "ihot".to_string(),
+                other.to_string(),
+            )),
+        }
+    }
+
+    pub fn set_ihot(&mut self, ihot: Ihot) {
+        self.namelist.set("OPT", "ihot", Value::from(ihot.flag()));
+    }
+
+    // Output flags such as iof_hydro(1) in SCHOUT; missing flags are off.
+    pub fn output_flag(&self, name: &str, index: usize) -> Result<bool, ParamError> {
+        let key = format!("{}({})", name, index);
+        match self.i64("SCHOUT", &key) {
+            Ok(flag) => Ok(flag != 0),
+            Err(ParamError::MissingKey(..)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_output_flag(&mut self, name: &str, index: usize, on: bool) {
+        let key = format!("{}({})", name, index);
+        self.namelist.set("SCHOUT", &key, Value::from(on as i64));
+    }
+
+    // Indices of the flags of an output family that are on, e.g. [1, 26] for iof_hydro.
+    pub fn enabled_outputs(&self, name: &str) -> Vec<usize> {
+        let prefix = format!("{}(", name.to_lowercase());
+        self.namelist
+            .group("SCHOUT")
+            .map(|group| {
+                group
+                    .keys()
+                    .into_iter()
+                    .filter_map(|key| {
+                        let index = key
+                            .to_lowercase()
+                            .strip_prefix(&prefix)?
+                            .strip_suffix(')')?
+                            .trim()
+                            .parse::<usize>()
+                            .ok()?;
+                        group
+                            .get(key)
+                            .and_then(|value| value.as_i64())
+                            .is_some_and(|flag| flag != 0)
+                            .then_some(index)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), ParamError> {
+        let dt = self.dt()?;
+        if dt <= 0. {
+            return Err(ParamError::Invalid(format!(
+                "dt must be > 0. but got {}",
+                dt
+            )));
+        }
+        let rnday = self.rnday()?;
+        if rnday <= 0. {
+            return Err(ParamError::Invalid(format!(
+                "rnday must be > 0. but got {}",
+                rnday
+            )));
+        }
+        let nspool = self.nspool()?;
+        let ihfskip = self.ihfskip()?;
+        if nspool <= 0 || ihfskip <= 0 || ihfskip % nspool != 0 {
+            return Err(ParamError::Invalid(format!(
+                "ihfskip ({}) must be a positive multiple of nspool ({})",
+                ihfskip, nspool
+            )));
+        }
+        self.start_time()?;
+        self.ics()?;
+        self.ihot()?;
+        let utc_start = self.utc_start()?;
+        if !(-14. ..=12.).contains(&utc_start) {
+            return Err(ParamError::Invalid(format!(
+                "utc_start must be within [-14, 12] hours but got {}",
+                utc_start
+            )));
+        }
+        if self.i64("SCHOUT", "nhot").unwrap_or(0) == 1 {
+            let nhot_write = self.i64("SCHOUT", "nhot_write")?;
+            if nhot_write <= 0 || nhot_write % ihfskip != 0 {
+                return Err(ParamError::Invalid(format!(
+                    "nhot_write ({}) must be a positive multiple of ihfskip ({})",
+                    nhot_write, ihfskip
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // ics = 2 needs node coordinates in degrees and ics = 1 projected ones. Hgrids without a CRS
+    // are only checked for coordinates outside of the longitude and latitude ranges.
+    pub fn check_hgrid(&self, hgrid: &Hgrid) -> Result<(), ParamError> {
+        let ics = self.ics()?;
+        let in_degrees = match hgrid.crs() {
+            Some(_) => hgrid.is_geographic(),
+            None => hgrid
+                .nodes()
+                .btree_map()
+                .values()
+                .all(|(coord, _)| coord[0].abs() <= 360. && coord[1].abs() <= 90.),
+        };
+        match (ics, in_degrees) {
+            (Ics::Spherical, false) => Err(ParamError::Invalid(
+                "ics = 2 but the hgrid coordinates are not longitude/latitude".to_string(),
+            )),
+            (Ics::Cartesian, true) if hgrid.crs().is_some() => Err(ParamError::Invalid(
+                "ics = 1 but the hgrid CRS is geographic".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.namelist)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ParamError {
+    #[error(transparent)]
+    NamelistError(#[from] NamelistError),
+    #[error("Missing {1} in group {0}")]
+    MissingKey(String, String),
+    #[error("Invalid value for {1} in group {0}: {2}")]
+    InvalidValue(String, String, String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schismrs_hgrid::crs::Crs;
+    use schismrs_hgrid::synthetic::RectangularChannelBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_utc_start_range() {
+        let mut param = Param::default();
+        assert!(param.validate().is_ok());
+        for (utc_start, valid) in [(-14., true), (12., true), (-14.5, false), (13., false)] {
+            param.set_utc_start(utc_start);
+            assert_eq!(param.validate().is_ok(), valid, "utc_start = {}", utc_start);
+        }
+    }
+
+    #[test]
+    fn test_check_hgrid() {
+        let channel = |origin: [f64; 2], length: f64, crs: Option<&str>| {
+            let mut builder = RectangularChannelBuilder::default();
+            builder.origin(origin).length(length).width(length / 10.);
+            if let Some(crs) = crs {
+                builder.crs(Crs::new(crs).map(Arc::new).unwrap());
+            }
+            builder.build().unwrap().generate().unwrap()
+        };
+        let geographic = channel([-75., 35.], 1., Some("EPSG:4326"));
+        let projected = channel([-8_350_000., 4_160_000.], 10_000., Some("EPSG:3857"));
+        let degrees = channel([-75., 35.], 1., None);
+        let meters = channel([500_000., 4_000_000.], 10_000., None);
+        let mut param = Param::default();
+        assert_eq!(param.ics().unwrap(), Ics::Spherical);
+        assert!(param.check_hgrid(&geographic).is_ok());
+        assert!(param.check_hgrid(&degrees).is_ok());
+        assert!(param.check_hgrid(&projected).is_err());
+        assert!(param.check_hgrid(&meters).is_err());
+        param.set_ics(Ics::Cartesian);
+        assert!(param.check_hgrid(&geographic).is_err());
+        assert!(param.check_hgrid(&projected).is_ok());
+        assert!(param.check_hgrid(&meters).is_ok());
+        // Without a CRS, small Cartesian coordinates cannot be told apart from degrees.
+        assert!(param.check_hgrid(&degrees).is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+use clap::{Args, Parser, Subcommand};
+use schismrs_bctides::atlas::AtlasFilesBuilder;
+use schismrs_bctides::bctides::{
+    BoundaryForcing, BoundaryForcingBuilder, ElevationBoundary, VelocityBoundary,
+};
+use schismrs_bctides::{BctidesBuilder, TidalFactors};
+use schismrs_hgrid::stations::{stations_from_csv_path, StationFileBuilder};
+use schismrs_hgrid::th::parse_datetime;
+use schismrs_hgrid::Hgrid;
+use schismrs_param::param::Ics;
+use schismrs_param::Param;
+use schismrs_rundir::{RunDirectoryBuilder, RunDirectoryCheck};
+use schismrs_vgrid::sz::SZBuilder;
+use std::process::ExitCode;
+use std::{error::Error, path::PathBuf};
+
+const VERSION: &str = concat! {
+    env! {"CARGO_PKG_VERSION"},
+    "-",
+    env! {"VERGEN_GIT_DESCRIBE"}
+};
+
+#[derive(Parser, Debug)]
+#[command(author, about, long_about = None)]
+#[command(version = VERSION)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(
+        about = "Write a run directory from an hgrid and SZ vgrid, bctides and param.nml options"
+    )]
+    Write(Box<WriteArgs>),
+    #[clap(about = "Check an existing run directory for consistency with its hgrid.gr3")]
+    Check { dir: PathBuf },
+}
+
+#[derive(Args, Debug)]
+struct WriteArgs {
+    hgrid_path: PathBuf,
+    output_dir: PathBuf,
+    #[clap(
+        long,
+        help = "param.nml to start from. Defaults to the built-in template."
+    )]
+    param: Option<PathBuf>,
+    #[clap(long, help = "Start date and time, e.g. 2024-01-01T00:00:00")]
+    start: Option<String>,
+    #[clap(long, help = "Run length in days")]
+    rnday: Option<f64>,
+    #[clap(long, help = "Time step in seconds")]
+    dt: Option<f64>,
+    #[clap(long, default_value = "2", help = "Number of sigma-levels.")]
+    slevels: usize,
+    #[clap(long, value_delimiter = ' ', num_args = 1..,
+        help = "Space delimited list of depths for each z-level. \
+                Optional. Defaults to pure sigma grid."
+        )]
+    zlevels: Option<Vec<f64>>,
+    #[clap(long, default_value = "0.1")]
+    theta_f: f64,
+    #[clap(long, default_value = "0.")]
+    theta_b: f64,
+    #[clap(long, alias = "hc", default_value = "5.")]
+    critical_depth: f64,
+    #[clap(
+        long,
+        default_value = "0.",
+        help = "Water level offset. Not typically needed."
+    )]
+    etal: f64,
+    #[clap(long, value_delimiter = ' ', num_args = 1..,
+        help = "Space delimited list of tidal constituents forced at the open boundaries."
+        )]
+    constituents: Option<Vec<String>>,
+    #[clap(
+        long,
+        help = "Directory with the <constituent>_amp.asc and <constituent>_phase.asc files \
+                of the tidal atlas."
+    )]
+    atlas: Option<PathBuf>,
+    #[clap(
+        long,
+        action,
+        help = "Only force tidal elevations at the open boundaries."
+    )]
+    no_velocity: bool,
+    #[clap(long, default_value = "40.")]
+    cutoff_depth: f64,
+    #[clap(
+        long,
+        help = "CSV file with name, x, y and optionally z station columns."
+    )]
+    stations: Option<PathBuf>,
+}
+
+fn write(args: &WriteArgs) -> Result<bool, Box<dyn Error>> {
+    let hgrid = Hgrid::try_from(&args.hgrid_path)?;
+    let mut param = match &args.param {
+        Some(path) => Param::from_path(path)?,
+        None => {
+            let mut param = Param::default();
+            param.set_ics(match hgrid.is_geographic() {
+                true => Ics::Spherical,
+                false => Ics::Cartesian,
+            });
+            param
+        }
+    };
+    if let Some(start) = &args.start {
+        let start = parse_datetime(start).ok_or_else(|| format!("Invalid start date {}", start))?;
+        param.set_start_time(start);
+    }
+    if let Some(rnday) = args.rnday {
+        param.set_rnday(rnday);
+    }
+    if let Some(dt) = args.dt {
+        param.set_dt(dt);
+    }
+    let mut builder = SZBuilder::default();
+    builder.hgrid(&hgrid);
+    builder.slevels(&args.slevels);
+    builder.theta_f(&args.theta_f);
+    builder.theta_b(&args.theta_b);
+    builder.critical_depth(&args.critical_depth);
+    builder.etal(&args.etal);
+    if let Some(zlevels) = &args.zlevels {
+        builder.zlevels(zlevels);
+    }
+    let sz = builder.build()?;
+    let open_boundary_count = hgrid
+        .boundaries()
+        .and_then(|boundaries| boundaries.open())
+        .map(|open| open.nodes_ids().len())
+        .unwrap_or(0);
+    let mut bctides = BctidesBuilder::default();
+    bctides.cutoff_depth(args.cutoff_depth);
+    match (&args.constituents, &args.atlas) {
+        (Some(constituents), Some(atlas)) => {
+            let names: Vec<&str> = constituents.iter().map(String::as_str).collect();
+            let factors = TidalFactors::new(param.start_time()?, param.rnday()?);
+            let potential_names: Vec<&str> = names
+                .iter()
+                .copied()
+                .filter(|name| factors.tidal_potential(&[name]).is_ok())
+                .collect();
+            let atlas = AtlasFilesBuilder::default()
+                .directory(atlas)
+                .constituents(constituents.clone())
+                .velocity(!args.no_velocity)
+                .build()?
+                .load()?;
+            let elevation = atlas.boundary_elevation(&hgrid)?;
+            let velocity = if args.no_velocity {
+                vec![Vec::new(); elevation.len()]
+            } else {
+                atlas.boundary_velocity(&hgrid)?
+            };
+            let boundaries = elevation
+                .into_iter()
+                .zip(velocity)
+                .map(|(elevation, velocity)| {
+                    let mut boundary = BoundaryForcingBuilder::default();
+                    boundary.elevation(ElevationBoundary::Tidal(elevation));
+                    if !velocity.is_empty() {
+                        boundary.velocity(VelocityBoundary::Tidal(velocity));
+                    }
+                    boundary.build()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bctides
+                .start_date(param.start_time()?)
+                .tidal_potential(factors.tidal_potential(&potential_names)?)
+                .tidal_forcing(factors.tidal_forcing(&names)?)
+                .boundaries(boundaries);
+        }
+        (Some(_), None) => return Err("--constituents needs an --atlas directory".into()),
+        _ => {
+            if open_boundary_count > 0 {
+                log::warn!(
+                    "No tidal constituents given, leaving the {} open boundaries unforced",
+                    open_boundary_count
+                );
+            }
+            bctides.boundaries(vec![BoundaryForcing::default(); open_boundary_count]);
+        }
+    }
+    let bctides = bctides.build()?;
+    let station_report = match &args.stations {
+        Some(path) => Some(
+            StationFileBuilder::default()
+                .stations(stations_from_csv_path(path)?)
+                .build()?
+                .check(&hgrid),
+        ),
+        None => None,
+    };
+    let mut rundir = RunDirectoryBuilder::default();
+    rundir
+        .hgrid(&hgrid)
+        .vgrid(&sz)
+        .bctides(&bctides)
+        .param(param);
+    if let Some(station_report) = &station_report {
+        rundir.stations(station_report);
+    }
+    let report = rundir.build()?.write(&args.output_dir)?;
+    println!("{}", report);
+    Ok(report.is_consistent())
+}
+
+fn entrypoint() -> Result<bool, Box<dyn Error>> {
+    pretty_env_logger::init();
+    let cli = Cli::parse();
+    match &cli.command {
+        Command::Write(args) => write(args),
+        Command::Check { dir } => {
+            let report = RunDirectoryCheck::new(dir).check();
+            println!("{}", report);
+            Ok(report.is_consistent())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match entrypoint() {
+        Err(e) => {
+            eprintln!("Error: {:?}: {}", e, e);
+            ExitCode::FAILURE
+        }
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,699 @@
+use schismrs_hgrid::Hgrid;
+use schismrs_param::{Namelist, Param};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const REQUIRED_FILES: [&str; 4] = ["hgrid.gr3", "vgrid.in", "bctides.in", "param.nml"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunDirectoryIssue {
+    MissingFile(String),
+    ParseError(String, String),
+    NodeCountMismatch {
+        file: String,
+        expected: usize,
+        found: usize,
+    },
+    ElementCountMismatch {
+        file: String,
+        expected: usize,
+        found: usize,
+    },
+    Vgrid(String),
+    BoundaryCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    BoundaryNodeCountMismatch {
+        boundary: usize,
+        expected: usize,
+        found: usize,
+    },
+    StationOutside {
+        index: usize,
+        coord: [f64; 2],
+    },
+    Param(String),
+}
+
+impl fmt::Display for RunDirectoryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunDirectoryIssue::MissingFile(file) => write!(f, "{} is missing", file),
+            RunDirectoryIssue::ParseError(file, message) => {
+                write!(f, "{} could not be read: {}", file, message)
+            }
+            RunDirectoryIssue::NodeCountMismatch {
+                file,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} nodes but hgrid.gr3 has {}",
+                file, found, expected
+            ),
+            RunDirectoryIssue::ElementCountMismatch {
+                file,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} elements but hgrid.gr3 has {}",
+                file, found, expected
+            ),
+            RunDirectoryIssue::Vgrid(message) => write!(f, "vgrid.in: {}", message),
+            RunDirectoryIssue::BoundaryCountMismatch { expected, found } => write!(
+                f,
+                "bctides.in has {} open boundaries but hgrid.gr3 has {}",
+                found, expected
+            ),
+            RunDirectoryIssue::BoundaryNodeCountMismatch {
+                boundary,
+                expected,
+                found,
+            } => write!(
+                f,
+                "bctides.in open boundary {} has {} nodes but hgrid.gr3 has {}",
+                boundary, found, expected
+            ),
+            RunDirectoryIssue::StationOutside { index, coord } => write!(
+                f,
+                "station.in station {} at ({}, {}) is outside of the mesh",
+                index, coord[0], coord[1]
+            ),
+            RunDirectoryIssue::Param(message) => write!(f, "param.nml: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunDirectoryReport {
+    checked: Vec<String>,
+    issues: Vec<RunDirectoryIssue>,
+}
+
+impl RunDirectoryReport {
+    pub fn checked(&self) -> &Vec<String> {
+        &self.checked
+    }
+
+    pub fn issues(&self) -> &Vec<RunDirectoryIssue> {
+        &self.issues
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for RunDirectoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "checked: {}", self.checked.join(", "))?;
+        if self.issues.is_empty() {
+            return write!(f, "no issues found");
+        }
+        writeln!(f, "{} issues found:", self.issues.len())?;
+        let lines: Vec<String> = self.issues.iter().map(|issue| issue.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+fn numbers(line: Option<&str>) -> Vec<f64> {
+    line.unwrap_or_default()
+        .split('!')
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map_while(|item| item.parse::<f64>().ok())
+        .collect()
+}
+
+// Initial conditions written like a gr3, with one value per node. Others such as ts.ic or the
+// *_vvar_*.ic tracer files hold vertical profiles instead.
+fn is_gr3_ic(file: &str) -> bool {
+    let file = file.to_lowercase();
+    ["elev.ic", "temp.ic", "salt.ic"].contains(&file.as_str()) || file.contains("_hvar_")
+}
+
+type FileCheck = fn(&RunDirectoryCheck, &Hgrid, &str) -> Vec<RunDirectoryIssue>;
+
+// Cross-file consistency checks of a SCHISM run directory against its hgrid.gr3.
+#[derive(Debug, Clone)]
+pub struct RunDirectoryCheck {
+    dir: PathBuf,
+}
+
+impl RunDirectoryCheck {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    pub fn check(&self) -> RunDirectoryReport {
+        let mut report = RunDirectoryReport::default();
+        for file in REQUIRED_FILES {
+            if !self.dir.join(file).is_file() {
+                report
+                    .issues
+                    .push(RunDirectoryIssue::MissingFile(file.to_string()));
+            }
+        }
+        let hgrid_path = self.dir.join("hgrid.gr3");
+        if !hgrid_path.is_file() {
+            return report;
+        }
+        let hgrid = match Hgrid::try_from(&hgrid_path) {
+            Ok(hgrid) => hgrid,
+            Err(e) => {
+                report.issues.push(RunDirectoryIssue::ParseError(
+                    "hgrid.gr3".to_string(),
+                    e.to_string(),
+                ));
+                return report;
+            }
+        };
+        report.checked.push("hgrid.gr3".to_string());
+        let checks: [(&str, FileCheck); 4] = [
+            ("vgrid.in", Self::check_vgrid),
+            ("bctides.in", Self::check_bctides),
+            ("station.in", Self::check_stations),
+            ("param.nml", Self::check_param),
+        ];
+        for (file, check) in checks {
+            let path = self.dir.join(file);
+            if let Ok(contents) = fs::read_to_string(&path) {
+                report.checked.push(file.to_string());
+                report.issues.extend(check(self, &hgrid, &contents));
+            }
+        }
+        report
+            .issues
+            .extend(self.check_node_files(&hgrid, &mut report.checked));
+        report
+    }
+
+    // Every *.gr3, *.ll and gr3-layout *.ic file shares the hgrid node count and every *.prop
+    // file its element count.
+    fn check_node_files(&self, hgrid: &Hgrid, checked: &mut Vec<String>) -> Vec<RunDirectoryIssue> {
+        let mut issues = Vec::new();
+        let node_count = hgrid.nodes().len();
+        let element_count = hgrid.elements().btree_map().len();
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        paths.sort();
+        for path in paths {
+            let file = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if file == "hgrid.gr3" || !["gr3", "ll", "ic", "prop"].contains(&extension.as_str()) {
+                continue;
+            }
+            if extension == "ic" && !is_gr3_ic(&file) {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(&path) else {
+                issues.push(RunDirectoryIssue::ParseError(
+                    file,
+                    "not a text file".to_string(),
+                ));
+                continue;
+            };
+            checked.push(file.clone());
+            if extension == "prop" {
+                let found = contents
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .count();
+                if found != element_count {
+                    issues.push(RunDirectoryIssue::ElementCountMismatch {
+                        file,
+                        expected: element_count,
+                        found,
+                    });
+                }
+                continue;
+            }
+            match numbers(contents.lines().nth(1)).as_slice() {
+                [elements, nodes, ..] => {
+                    let (elements, nodes) = (*elements as usize, *nodes as usize);
+                    if nodes != node_count {
+                        issues.push(RunDirectoryIssue::NodeCountMismatch {
+                            file: file.clone(),
+                            expected: node_count,
+                            found: nodes,
+                        });
+                    }
+                    if extension != "ic" && elements != element_count {
+                        issues.push(RunDirectoryIssue::ElementCountMismatch {
+                            file,
+                            expected: element_count,
+                            found: elements,
+                        });
+                    }
+                }
+                _ => issues.push(RunDirectoryIssue::ParseError(
+                    file,
+                    "expected the element and node counts in line 2".to_string(),
+                )),
+            }
+        }
+        issues
+    }
+
+    fn check_vgrid(&self, hgrid: &Hgrid, contents: &str) -> Vec<RunDirectoryIssue> {
+        let node_count = hgrid.nodes().len();
+        let lines: Vec<&str> = contents.lines().collect();
+        let issue = |message: String| vec![RunDirectoryIssue::Vgrid(message)];
+        let ivcor = numbers(lines.first().copied());
+        let header = numbers(lines.get(1).copied());
+        match (ivcor.first(), header.first()) {
+            (Some(ivcor), Some(nvrt)) if *ivcor == 1. => {
+                let nvrt = *nvrt as usize;
+                let bottom_levels = numbers(lines.get(2).copied());
+                if bottom_levels.len() != node_count {
+                    return issue(format!(
+                        "{} bottom level indices for {} hgrid nodes",
+                        bottom_levels.len(),
+                        node_count
+                    ));
+                }
+                if let Some(k) = bottom_levels
+                    .iter()
+                    .position(|&level| level < 1. || level > nvrt as f64)
+                {
+                    return issue(format!(
+                        "bottom level {} of node {} is outside of [1, nvrt = {}]",
+                        bottom_levels[k],
+                        k + 1,
+                        nvrt
+                    ));
+                }
+                let levels: Vec<&str> = lines[3..]
+                    .iter()
+                    .copied()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect();
+                if levels.len() != nvrt {
+                    return issue(format!("{} levels but nvrt = {}", levels.len(), nvrt));
+                }
+                match levels
+                    .iter()
+                    .position(|line| numbers(Some(line)).len() != node_count + 1)
+                {
+                    Some(level) => issue(format!(
+                        "level {} does not have one value per hgrid node ({})",
+                        level + 1,
+                        node_count
+                    )),
+                    None => Vec::new(),
+                }
+            }
+            (Some(ivcor), Some(nvrt)) if *ivcor == 2. => {
+                let kz = header.get(1).copied().unwrap_or(0.) as usize;
+                let nvrt = *nvrt as usize;
+                let count = |title: &str| {
+                    lines
+                        .iter()
+                        .position(|line| line.trim().eq_ignore_ascii_case(title))
+                        .map(|start| {
+                            lines[start + 1..]
+                                .iter()
+                                .take_while(|line| numbers(Some(line)).len() == 2)
+                                .count()
+                        })
+                };
+                let z_levels = count("Z levels");
+                // The S levels title is followed by the hc, theta_b and theta_f line.
+                let s_levels = lines
+                    .iter()
+                    .position(|line| line.trim().eq_ignore_ascii_case("S levels"))
+                    .map(|start| {
+                        lines[(start + 2).min(lines.len())..]
+                            .iter()
+                            .take_while(|line| numbers(Some(line)).len() == 2)
+                            .count()
+                    });
+                match (z_levels, s_levels) {
+                    (Some(z), Some(s)) if z == kz && z + s == nvrt + 1 => Vec::new(),
+                    (Some(z), Some(s)) => issue(format!(
+                        "{} Z levels and {} S levels for nvrt = {} and kz = {}",
+                        z, s, nvrt, kz
+                    )),
+                    _ => issue("missing Z levels or S levels section".to_string()),
+                }
+            }
+            _ => issue("expected ivcor 1 or 2 followed by nvrt".to_string()),
+        }
+    }
+
+    fn check_bctides(&self, hgrid: &Hgrid, contents: &str) -> Vec<RunDirectoryIssue> {
+        let expected: Vec<usize> = hgrid
+            .boundaries()
+            .and_then(|boundaries| boundaries.open())
+            .map(|open| open.nodes_ids().iter().map(Vec::len).collect())
+            .unwrap_or_default();
+        match parse_bctides_segments(contents) {
+            Err(message) => vec![RunDirectoryIssue::ParseError(
+                "bctides.in".to_string(),
+                message,
+            )],
+            Ok((nope, _)) if nope != expected.len() => {
+                vec![RunDirectoryIssue::BoundaryCountMismatch {
+                    expected: expected.len(),
+                    found: nope,
+                }]
+            }
+            Ok((_, node_counts)) => node_counts
+                .into_iter()
+                .zip(expected)
+                .enumerate()
+                .filter(|(_, (found, expected))| found != expected)
+                .map(
+                    |(index, (found, expected))| RunDirectoryIssue::BoundaryNodeCountMismatch {
+                        boundary: index + 1,
+                        expected,
+                        found,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    fn check_stations(&self, hgrid: &Hgrid, contents: &str) -> Vec<RunDirectoryIssue> {
+        let lines: Vec<&str> = contents.lines().collect();
+        let Some(count) = numbers(lines.get(1).copied()).first().map(|n| *n as usize) else {
+            return vec![RunDirectoryIssue::ParseError(
+                "station.in".to_string(),
+                "expected the number of stations in line 2".to_string(),
+            )];
+        };
+        let locator = hgrid.element_locator();
+        let mut issues = Vec::new();
+        for index in 1..=count {
+            match numbers(lines.get(index + 1).copied()).as_slice() {
+                [_, x, y, ..] => {
+                    if locator.locate([*x, *y]).is_none() {
+                        issues.push(RunDirectoryIssue::StationOutside {
+                            index,
+                            coord: [*x, *y],
+                        });
+                    }
+                }
+                _ => {
+                    issues.push(RunDirectoryIssue::ParseError(
+                        "station.in".to_string(),
+                        format!("expected index, x, y and z for station {}", index),
+                    ));
+                    break;
+                }
+            }
+        }
+        issues
+    }
+
+    fn check_param(&self, hgrid: &Hgrid, contents: &str) -> Vec<RunDirectoryIssue> {
+        let result = contents
+            .parse::<Namelist>()
+            .map_err(|e| e.to_string())
+            .map(Param::from)
+            .and_then(|param| {
+                param.validate().map_err(|e| e.to_string())?;
+                param.check_hgrid(hgrid).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => Vec::new(),
+            Err(message) => vec![RunDirectoryIssue::Param(message)],
+        }
+    }
+}
+
+// Number of open boundary segments in bctides.in and the node count of each one, walking over
+// the tidal potential, tidal forcing and per-boundary blocks.
+pub fn parse_bctides_segments(contents: &str) -> Result<(usize, Vec<usize>), String> {
+    let mut lines = contents.lines().skip(1);
+    let mut next = |what: &str| {
+        lines
+            .next()
+            .map(|line| numbers(Some(line)))
+            .ok_or_else(|| format!("unexpected end of file while reading {}", what))
+    };
+    let count = |values: Vec<f64>, what: &str| {
+        values
+            .first()
+            .map(|value| *value as usize)
+            .ok_or_else(|| format!("expected {}", what))
+    };
+    let ntip = count(next("ntip")?, "ntip")?;
+    for _ in 0..2 * ntip {
+        next("the tidal potential")?;
+    }
+    let nbfr = count(next("nbfr")?, "nbfr")?;
+    for _ in 0..2 * nbfr {
+        next("the tidal forcing frequencies")?;
+    }
+    let nope = count(next("nope")?, "nope")?;
+    let mut node_counts = Vec::with_capacity(nope);
+    for boundary in 1..=nope {
+        let header = next("an open boundary header")?;
+        let [neta, iettype, ifltype, itetype, isatype] = match header.as_slice() {
+            [neta, iettype, ifltype, itetype, isatype, ..] => {
+                [*neta, *iettype, *ifltype, *itetype, *isatype].map(|value| value as i64)
+            }
+            _ => {
+                return Err(format!(
+                    "expected the node count and 4 flags for open boundary {}",
+                    boundary
+                ))
+            }
+        };
+        let neta_lines = neta as usize;
+        let tidal_lines = nbfr * (neta_lines + 1);
+        let elevation_lines = match iettype {
+            2 => 1,
+            3 | 5 => tidal_lines,
+            _ => 0,
+        };
+        let velocity_lines = match ifltype {
+            2 | -4 => 1,
+            3 | 5 => tidal_lines,
+            -1 => {
+                return Err(format!(
+                    "ifltype -1 of open boundary {} is not supported",
+                    boundary
+                ))
+            }
+            _ => 0,
+        };
+        let tracer_lines = |flag: i64| match flag {
+            1 | 3 | 4 => 1,
+            2 => 2,
+            _ => 0,
+        };
+        for _ in 0..elevation_lines + velocity_lines + tracer_lines(itetype) + tracer_lines(isatype)
+        {
+            next("open boundary values")?;
+        }
+        node_counts.push(neta_lines);
+    }
+    Ok((nope, node_counts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RunDirectoryBuilder;
+    use schismrs_bctides::bctides::BoundaryForcing;
+    use schismrs_bctides::BctidesBuilder;
+    use schismrs_hgrid::crs::Crs;
+    use schismrs_hgrid::synthetic::RectangularChannelBuilder;
+    use schismrs_param::param::Ics;
+    use schismrs_vgrid::sz::SZBuilder;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    // Writes a run directory for a channel with two open boundaries of 6 nodes each.
+    fn run_dir(
+        crs: &str,
+        origin: [f64; 2],
+        length: f64,
+        ics: Ics,
+    ) -> (TempDir, RunDirectoryReport) {
+        let hgrid = RectangularChannelBuilder::default()
+            .origin(origin)
+            .length(length)
+            .width(length / 10.)
+            .crs(Crs::new(crs).map(Arc::new).unwrap())
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut sz = SZBuilder::default();
+        sz.hgrid(&hgrid)
+            .slevels(&5)
+            .theta_f(&5.)
+            .theta_b(&0.5)
+            .critical_depth(&5.)
+            .etal(&0.);
+        let sz = sz.build().unwrap();
+        let bctides = BctidesBuilder::default()
+            .boundaries(vec![BoundaryForcing::default(); 2])
+            .build()
+            .unwrap();
+        let mut param = Param::default();
+        param.set_ics(ics);
+        let dir = TempDir::new().unwrap();
+        let report = RunDirectoryBuilder::default()
+            .hgrid(&hgrid)
+            .vgrid(&sz)
+            .bctides(&bctides)
+            .param(param)
+            .build()
+            .unwrap()
+            .write(dir.path())
+            .unwrap();
+        (dir, report)
+    }
+
+    fn hgrid(dir: &TempDir) -> Hgrid {
+        Hgrid::try_from(&dir.path().join("hgrid.gr3")).unwrap()
+    }
+
+    #[test]
+    fn test_geographic_run_directory() {
+        let (dir, report) = run_dir("EPSG:4326", [-75., 35.], 1., Ics::Spherical);
+        assert!(report.is_consistent(), "{}", report);
+        assert!(report.checked().contains(&"hgrid.ll".to_string()));
+        // A geographic hgrid is written as hgrid.ll unchanged.
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hgrid.ll")).unwrap(),
+            fs::read_to_string(dir.path().join("hgrid.gr3")).unwrap()
+        );
+        let param = fs::read_to_string(dir.path().join("param.nml")).unwrap();
+        let param = Param::from(param.parse::<Namelist>().unwrap());
+        assert!(matches!(param.ics().unwrap(), Ics::Spherical));
+    }
+
+    #[test]
+    fn test_projected_run_directory() {
+        let (dir, report) = run_dir("EPSG:3857", [0., 0.], 10_000., Ics::Cartesian);
+        assert!(report.is_consistent(), "{}", report);
+        let ll = Hgrid::try_from(&dir.path().join("hgrid.ll")).unwrap();
+        assert_eq!(ll.nodes().len(), hgrid(&dir).nodes().len());
+        assert!(ll
+            .nodes()
+            .btree_map()
+            .values()
+            .all(|(coord, _)| coord[0].abs() < 1. && coord[1].abs() < 1.));
+    }
+
+    #[test]
+    fn test_parse_bctides_segments() {
+        let (dir, _) = run_dir("EPSG:4326", [-75., 35.], 1., Ics::Spherical);
+        let contents = fs::read_to_string(dir.path().join("bctides.in")).unwrap();
+        assert_eq!(parse_bctides_segments(&contents), Ok((2, vec![6, 6])));
+        let lines: Vec<&str> = contents.lines().collect();
+        let truncated = lines[..lines.len() - 1].join("\n");
+        assert!(parse_bctides_segments(&truncated)
+            .unwrap_err()
+            .contains("open boundary header"));
+        let hgrid = hgrid(&dir);
+        let check = RunDirectoryCheck::new(dir.path());
+        let one_boundary = lines[..lines.len() - 1]
+            .join("\n")
+            .replacen("\n2", "\n1", 1);
+        assert_eq!(parse_bctides_segments(&one_boundary), Ok((1, vec![6])));
+        assert_eq!(
+            check.check_bctides(&hgrid, &one_boundary),
+            vec![RunDirectoryIssue::BoundaryCountMismatch {
+                expected: 2,
+                found: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_vgrid() {
+        let (dir, _) = run_dir("EPSG:4326", [-75., 35.], 1., Ics::Spherical);
+        let hgrid = hgrid(&dir);
+        let check = RunDirectoryCheck::new(dir.path());
+        let contents = fs::read_to_string(dir.path().join("vgrid.in")).unwrap();
+        assert!(check.check_vgrid(&hgrid, &contents).is_empty());
+        let missing_level = contents.trim_end().rsplit_once('\n').unwrap().0;
+        assert!(matches!(
+            check.check_vgrid(&hgrid, missing_level).as_slice(),
+            [RunDirectoryIssue::Vgrid(_)]
+        ));
+        let node_count = hgrid.nodes().len();
+        let level = |z: f64| {
+            let values: Vec<String> = (0..node_count).map(|_| z.to_string()).collect();
+            values.join(" ")
+        };
+        let bottom = vec!["1"; node_count].join(" ");
+        let ivcor1 = format!("1\n2\n{}\n1 {}\n2 {}\n", bottom, level(-1.), level(0.));
+        assert!(check.check_vgrid(&hgrid, &ivcor1).is_empty());
+        let short_level = format!("1\n2\n{}\n1 {}\n2 -1\n", bottom, level(-1.));
+        assert_eq!(
+            check.check_vgrid(&hgrid, &short_level),
+            vec![RunDirectoryIssue::Vgrid(format!(
+                "level 2 does not have one value per hgrid node ({})",
+                node_count
+            ))]
+        );
+        let deep_bottom = format!("1\n2\n3 {}\n", bottom);
+        assert!(matches!(
+            check.check_vgrid(&hgrid, &deep_bottom).as_slice(),
+            [RunDirectoryIssue::Vgrid(message)] if message.contains("bottom level")
+        ));
+    }
+
+    #[test]
+    fn test_check_node_files() {
+        let (dir, _) = run_dir("EPSG:4326", [-75., 35.], 1., Ics::Spherical);
+        let hgrid = hgrid(&dir);
+        let node_count = hgrid.nodes().len();
+        let element_count = hgrid.elements().btree_map().len();
+        let gr3 = fs::read_to_string(dir.path().join("hgrid.gr3")).unwrap();
+        fs::write(dir.path().join("manning.gr3"), &gr3).unwrap();
+        let header = format!("{} {}", element_count, node_count - 1);
+        fs::write(
+            dir.path().join("elev.ic"),
+            gr3.replacen(&format!("{} {}", element_count, node_count), &header, 1),
+        )
+        .unwrap();
+        fs::write(dir.path().join("ts.ic"), "1\n1 -10 20 30\n").unwrap();
+        fs::write(
+            dir.path().join("tvd.prop"),
+            vec!["1 1\n"; element_count - 1].concat(),
+        )
+        .unwrap();
+        let mut checked = Vec::new();
+        let issues = RunDirectoryCheck::new(dir.path()).check_node_files(&hgrid, &mut checked);
+        assert_eq!(
+            checked,
+            ["elev.ic", "hgrid.ll", "manning.gr3", "tvd.prop"].map(String::from)
+        );
+        assert_eq!(
+            issues,
+            vec![
+                RunDirectoryIssue::NodeCountMismatch {
+                    file: "elev.ic".to_string(),
+                    expected: node_count,
+                    found: node_count - 1,
+                },
+                RunDirectoryIssue::ElementCountMismatch {
+                    file: "tvd.prop".to_string(),
+                    expected: element_count,
+                    found: element_count - 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub use check::{RunDirectoryCheck, RunDirectoryReport};
+pub use rundir::{RunDirectory, RunDirectoryBuilder};
+
+pub mod check;
+pub mod rundir;
@@ -0,0 +1,89 @@
+use super::check::{RunDirectoryCheck, RunDirectoryReport};
+use derive_builder::Builder;
+use schismrs_bctides::bctides::BctidesError;
+use schismrs_bctides::Bctides;
+use schismrs_hgrid::reproject::{to_hgrid_ll, ReprojectError};
+use schismrs_hgrid::stations::StationReport;
+use schismrs_hgrid::Hgrid;
+use schismrs_param::namelist::Value;
+use schismrs_param::param::ParamError;
+use schismrs_param::Param;
+use schismrs_vgrid::VerticalGrid;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+// Inputs of a SCHISM run directory: hgrid.gr3, hgrid.ll, vgrid.in, bctides.in, param.nml and
+// optionally station.in.
+#[derive(Builder, Clone)]
+pub struct RunDirectory<'a> {
+    hgrid: &'a Hgrid,
+    vgrid: &'a dyn VerticalGrid,
+    bctides: &'a Bctides,
+    #[builder(default)]
+    param: Param,
+    #[builder(setter(strip_option), default)]
+    stations: Option<&'a StationReport>,
+}
+
+impl<'a> RunDirectory<'a> {
+    pub fn param(&self) -> Param {
+        let mut param = self.param.clone();
+        if self.stations.is_some() {
+            param
+                .namelist_mut()
+                .set("SCHOUT", "iout_sta", Value::Integer(1));
+        }
+        param
+    }
+
+    // Checks the inputs against each other before anything is written, then writes the files
+    // and returns the consistency report of the written directory.
+    pub fn write(&self, dir: &Path) -> Result<RunDirectoryReport, RunDirectoryError> {
+        let param = self.param();
+        param.validate()?;
+        param.check_hgrid(self.hgrid)?;
+        self.bctides.check(self.hgrid)?;
+        fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+        let path = dir.join("hgrid.gr3");
+        self.hgrid.write(&path).map_err(|e| io_error(&path, e))?;
+        let path = dir.join("hgrid.ll");
+        match self.hgrid.crs() {
+            Some(_) if self.hgrid.is_geographic() => {
+                self.hgrid.write(&path).map_err(|e| io_error(&path, e))?
+            }
+            Some(_) => to_hgrid_ll(self.hgrid)?
+                .write(&path)
+                .map_err(|e| io_error(&path, e))?,
+            None => log::warn!("The hgrid has no CRS, not writing {}", path.display()),
+        }
+        let path = dir.join("vgrid.in");
+        self.vgrid
+            .write_to_file(&path)
+            .map_err(|e| io_error(&path, e))?;
+        self.bctides.write(self.hgrid, &dir.join("bctides.in"))?;
+        let path = dir.join("param.nml");
+        param.write(&path).map_err(|e| io_error(&path, e))?;
+        if let Some(stations) = self.stations {
+            let path = dir.join("station.in");
+            stations.write(&path).map_err(|e| io_error(&path, e))?;
+        }
+        Ok(RunDirectoryCheck::new(dir).check())
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> RunDirectoryError {
+    RunDirectoryError::IoError(path.display().to_string(), e.to_string())
+}
+
+#[derive(Error, Debug)]
+pub enum RunDirectoryError {
+    #[error("Error writing {0}: {1}")]
+    IoError(String, String),
+    #[error(transparent)]
+    ParamError(#[from] ParamError),
+    #[error(transparent)]
+    BctidesError(#[from] BctidesError),
+    #[error(transparent)]
+    ReprojectError(#[from] ReprojectError),
+}
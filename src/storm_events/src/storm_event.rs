@@ -19,6 +19,12 @@ pub struct StormEvent {
     track: DataFrame,
 }
 
+impl StormEvent {
+    pub fn track(&self) -> &DataFrame {
+        &self.track
+    }
+}
+
 #[derive(Default)]
 pub struct StormEventBuilder<'a> {
     file_deck: Option<&'a ATCFFileDeck>,
@@ -0,0 +1,311 @@
+use crate::traits::VerticalGrid;
+use ndarray::Array2;
+use plotly::color::Rgb;
+use plotly::common::{Marker, Mode};
+use plotly::{Plot, Scatter};
+use schismrs_hgrid::prop::Prop;
+use schismrs_hgrid::Hgrid;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Clone)]
+pub struct SideDiagnostic {
+    pub node_ids: (u32, u32),
+    pub elements: Vec<u32>,
+    pub midpoint: [f64; 2],
+    pub rx0: f64,
+    pub rx1: f64,
+}
+
+pub struct HydrostaticConsistency {
+    sides: Vec<SideDiagnostic>,
+    element_rx0: BTreeMap<u32, f64>,
+    element_rx1: BTreeMap<u32, f64>,
+    element_centroids: BTreeMap<u32, [f64; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HydrostaticNumber {
+    Rx0,
+    Rx1,
+}
+
+impl HydrostaticConsistency {
+    pub fn sides(&self) -> &Vec<SideDiagnostic> {
+        &self.sides
+    }
+
+    pub fn element_rx0(&self) -> &BTreeMap<u32, f64> {
+        &self.element_rx0
+    }
+
+    pub fn element_rx1(&self) -> &BTreeMap<u32, f64> {
+        &self.element_rx1
+    }
+
+    pub fn max_rx0(&self) -> f64 {
+        self.sides.iter().fold(0., |acc, side| acc.max(side.rx0))
+    }
+
+    pub fn max_rx1(&self) -> f64 {
+        self.sides.iter().fold(0., |acc, side| acc.max(side.rx1))
+    }
+
+    pub fn worst_sides(&self, number: HydrostaticNumber, count: usize) -> Vec<&SideDiagnostic> {
+        let mut sides: Vec<&SideDiagnostic> = self.sides.iter().collect();
+        let key = |side: &SideDiagnostic| match number {
+            HydrostaticNumber::Rx0 => side.rx0,
+            HydrostaticNumber::Rx1 => side.rx1,
+        };
+        sides.sort_by(|a, b| key(b).total_cmp(&key(a)));
+        sides.truncate(count);
+        sides
+    }
+
+    fn element_field(&self, number: HydrostaticNumber) -> &BTreeMap<u32, f64> {
+        match number {
+            HydrostaticNumber::Rx0 => &self.element_rx0,
+            HydrostaticNumber::Rx1 => &self.element_rx1,
+        }
+    }
+
+    pub fn element_prop(&self, number: HydrostaticNumber) -> Prop {
+        Prop::from(self.element_field(number).clone())
+    }
+
+    pub fn write_element_field(
+        &self,
+        number: HydrostaticNumber,
+        filename: &Path,
+    ) -> std::io::Result<()> {
+        self.element_prop(number).write(filename)
+    }
+
+    pub fn make_element_field_plot(&self, number: HydrostaticNumber) -> Plot {
+        let field = self.element_field(number);
+        let max_value = field.values().fold(f64::EPSILON, |acc, &v| acc.max(v));
+        let mut x = Vec::with_capacity(field.len());
+        let mut y = Vec::with_capacity(field.len());
+        let mut colors = Vec::with_capacity(field.len());
+        for (element_id, value) in field.iter() {
+            let centroid = self.element_centroids[element_id];
+            x.push(centroid[0]);
+            y.push(centroid[1]);
+            let fraction = (value / max_value).clamp(0., 1.);
+            colors.push(Rgb::new(
+                (255. * fraction) as u8,
+                0,
+                (255. * (1. - fraction)) as u8,
+            ));
+        }
+        let mut plot = Plot::new();
+        let trace = Scatter::new(x, y)
+            .mode(Mode::Markers)
+            .marker(Marker::new().color_array(colors));
+        plot.add_trace(trace);
+        plot
+    }
+}
+
+#[derive(Default)]
+pub struct HydrostaticConsistencyBuilder<'a> {
+    hgrid: Option<&'a Hgrid>,
+    vgrid: Option<&'a dyn VerticalGrid>,
+}
+
+impl<'a> HydrostaticConsistencyBuilder<'a> {
+    pub fn build(&self) -> Result<HydrostaticConsistency, HydrostaticConsistencyBuilderError> {
+        let hgrid = self.hgrid.ok_or_else(|| {
+            HydrostaticConsistencyBuilderError::UninitializedFieldError("hgrid".to_string())
+        })?;
+        let vgrid = self.vgrid.ok_or_else(|| {
+            HydrostaticConsistencyBuilderError::UninitializedFieldError("vgrid".to_string())
+        })?;
+        let depths = hgrid.depths_btree_map();
+        let node_index: BTreeMap<u32, usize> = hgrid
+            .nodes()
+            .btree_map()
+            .keys()
+            .enumerate()
+            .map(|(index, &node_id)| (node_id, index))
+            .collect();
+        let z_coordinates = vgrid.z_coordinates(hgrid);
+        if z_coordinates.ncols() != node_index.len() {
+            return Err(HydrostaticConsistencyBuilderError::NodeCountMismatch(
+                node_index.len(),
+                z_coordinates.ncols(),
+            ));
+        }
+        let nodes = hgrid.nodes().btree_map();
+        let side_elements = hgrid.side_elements();
+        let mut sides = Vec::with_capacity(side_elements.len());
+        let mut element_rx0 = BTreeMap::new();
+        let mut element_rx1 = BTreeMap::new();
+        for ((node_a, node_b), elements) in side_elements.into_iter() {
+            let depth_a = -depths.get(&node_a).copied().unwrap_or(0.);
+            let depth_b = -depths.get(&node_b).copied().unwrap_or(0.);
+            let rx0 = Self::rx0(depth_a, depth_b);
+            let rx1 = Self::rx1(&z_coordinates, node_index[&node_a], node_index[&node_b]);
+            for element_id in elements.iter() {
+                let this_rx0 = element_rx0.entry(*element_id).or_insert(0.);
+                *this_rx0 = f64::max(*this_rx0, rx0);
+                let this_rx1 = element_rx1.entry(*element_id).or_insert(0.);
+                *this_rx1 = f64::max(*this_rx1, rx1);
+            }
+            let (coord_a, _) = &nodes[&node_a];
+            let (coord_b, _) = &nodes[&node_b];
+            sides.push(SideDiagnostic {
+                node_ids: (node_a, node_b),
+                elements,
+                midpoint: [
+                    (coord_a[0] + coord_b[0]) / 2.,
+                    (coord_a[1] + coord_b[1]) / 2.,
+                ],
+                rx0,
+                rx1,
+            });
+        }
+        Ok(HydrostaticConsistency {
+            sides,
+            element_rx0,
+            element_rx1,
+            element_centroids: hgrid.element_centroids(),
+        })
+    }
+
+    fn rx0(depth_a: f64, depth_b: f64) -> f64 {
+        if depth_a <= 0. || depth_b <= 0. {
+            return 0.;
+        }
+        (depth_a - depth_b).abs() / (depth_a + depth_b)
+    }
+
+    // Haney (1991) number, evaluated on every layer that is wet on both ends of the side.
+    fn rx1(z_coordinates: &Array2<f64>, index_a: usize, index_b: usize) -> f64 {
+        let mut rx1: f64 = 0.;
+        for k in 1..z_coordinates.nrows() {
+            let za_top = z_coordinates[[k, index_a]];
+            let za_bottom = z_coordinates[[k - 1, index_a]];
+            let zb_top = z_coordinates[[k, index_b]];
+            let zb_bottom = z_coordinates[[k - 1, index_b]];
+            if za_top.is_nan() || za_bottom.is_nan() || zb_top.is_nan() || zb_bottom.is_nan() {
+                continue;
+            }
+            let denominator = (za_top + zb_top - za_bottom - zb_bottom).abs();
+            if denominator <= 0. {
+                continue;
+            }
+            rx1 = rx1.max((za_top - zb_top + za_bottom - zb_bottom).abs() / denominator);
+        }
+        rx1
+    }
+
+    pub fn hgrid(&mut self, hgrid: &'a Hgrid) -> &mut Self {
+        self.hgrid = Some(hgrid);
+        self
+    }
+
+    pub fn vgrid(&mut self, vgrid: &'a dyn VerticalGrid) -> &mut Self {
+        self.vgrid = Some(vgrid);
+        self
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HydrostaticConsistencyBuilderError {
+    #[error("Unitialized field on HydrostaticConsistencyBuilder: {0}")]
+    UninitializedFieldError(String),
+    #[error("The hgrid has {0} nodes but the vgrid has {1} columns")]
+    NodeCountMismatch(usize, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sz::SZBuilder;
+    use schismrs_hgrid::synthetic::{ElementType, RectangularChannelBuilder};
+    use std::sync::Arc;
+
+    // Bottom sloping from 2 m to 4 m along a 10 km channel of 200 m quads.
+    fn sloping_channel() -> Hgrid {
+        RectangularChannelBuilder::default()
+            .element_type(ElementType::Quad)
+            .depth(Arc::new(|x: f64, _y: f64| 2. + 2. * x / 10_000.))
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap()
+    }
+
+    // Depths shallower than the critical depth give plain sigma levels z = sigma * h.
+    fn sigma_grid(hgrid: &Hgrid) -> crate::sz::SZ {
+        SZBuilder::default()
+            .hgrid(hgrid)
+            .slevels(&11)
+            .theta_b(&0.)
+            .theta_f(&1.)
+            .critical_depth(&5.)
+            .etal(&0.)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rx0_rx1_on_sigma_levels() {
+        let hgrid = sloping_channel();
+        let sz = sigma_grid(&hgrid);
+        let consistency = HydrostaticConsistencyBuilder::default()
+            .hgrid(&hgrid)
+            .vgrid(&sz)
+            .build()
+            .unwrap();
+        // The steepest relative slope is at the shallow end: 0.04 m over a 2 m + 2.04 m side.
+        let rx0 = 0.04 / 4.04;
+        assert!((consistency.max_rx0() - rx0).abs() < 1e-9);
+        // For z = sigma * h the bottom layer (sigma -1 to -0.9) amplifies rx0 by 1.9 / 0.1.
+        assert!((consistency.max_rx1() - 19. * rx0).abs() < 1e-9);
+        // Sides across the channel have equal depths at both ends.
+        let worst = consistency.worst_sides(HydrostaticNumber::Rx1, 1);
+        assert!((worst[0].rx1 - consistency.max_rx1()).abs() < 1e-12);
+        assert!(worst[0].midpoint[0] < 200.);
+        let nodes = hgrid.nodes().btree_map();
+        assert!(consistency
+            .sides()
+            .iter()
+            .filter(|side| nodes[&side.node_ids.0].0[0] == nodes[&side.node_ids.1].0[0])
+            .all(|side| side.rx0 < 1e-12 && side.rx1 < 1e-12));
+        assert_eq!(
+            consistency.element_rx0().len(),
+            hgrid.elements().btree_map().len()
+        );
+    }
+
+    #[test]
+    fn test_nodes_without_values() {
+        let mut hgrid = sloping_channel();
+        let dry = *hgrid.nodes().btree_map().keys().nth(20).unwrap();
+        hgrid.set_node_values(dry, None).unwrap();
+        let sz = sigma_grid(&hgrid);
+        let z_coordinates = sz.z_coordinates(&hgrid);
+        assert_eq!(z_coordinates.ncols(), hgrid.nodes().len());
+        let dry_index = hgrid
+            .nodes()
+            .btree_map()
+            .keys()
+            .position(|&node_id| node_id == dry)
+            .unwrap();
+        assert!(z_coordinates.column(dry_index).iter().all(|z| z.is_nan()));
+        assert_eq!(sz.node_level_counts(&hgrid)[&dry], 0);
+        let consistency = HydrostaticConsistencyBuilder::default()
+            .hgrid(&hgrid)
+            .vgrid(&sz)
+            .build()
+            .unwrap();
+        assert!(consistency
+            .sides()
+            .iter()
+            .filter(|side| side.node_ids.0 == dry || side.node_ids.1 == dry)
+            .all(|side| side.rx0 == 0. && side.rx1 == 0.));
+    }
+}
@@ -1,5 +1,8 @@
 pub use kmeans_hsm::{kmeans_hsm, KMeansHSMCreateError};
+pub use traits::VerticalGrid;
+pub mod hydrostatic;
 pub mod kmeans_hsm;
 pub mod sz;
+pub mod traits;
 pub mod transforms;
 pub mod vqs;
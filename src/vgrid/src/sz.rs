@@ -1,7 +1,9 @@
+use crate::traits::VerticalGrid;
 use libm::sinh;
 use libm::tanh;
 use ndarray::Array;
 use ndarray::Array1;
+use ndarray::Array2;
 use ndarray_stats::QuantileExt;
 use plotly::color::NamedColor;
 use plotly::common::{Line, Marker, Mode};
@@ -11,7 +13,7 @@ use std::f64::NAN;
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 pub struct SZ {
@@ -54,6 +56,52 @@ impl SZ {
         }
         Ok(plot)
     }
+    pub fn z_coordinates(&self, hgrid: &Hgrid) -> Array2<f64> {
+        // One column per node, so that nodes without a value keep their place as a dry column.
+        let depths: Vec<f64> = hgrid
+            .nodes()
+            .btree_map()
+            .values()
+            .map(|(_coord, values)| {
+                values
+                    .as_ref()
+                    .and_then(|values| values.first())
+                    .map_or(NAN, |value| -value)
+            })
+            .collect();
+        let kz = self.z_array.len();
+        let hs = -self.z_array[kz - 1];
+        let mut z_coordinates = Array2::from_elem((self.nvrt(), depths.len()), NAN);
+        for (i, &depth) in depths.iter().enumerate() {
+            if depth.is_nan() || depth <= 0. {
+                continue;
+            }
+            let hh = depth.min(hs);
+            for (k, &sigma) in self.sigma.iter().enumerate() {
+                z_coordinates[[kz - 1 + k, i]] = if hh <= self.hc {
+                    sigma * (hh + self.etal) + self.etal
+                } else {
+                    self.etal * (1. + sigma) + self.hc * sigma + (hh - self.hc) * self.cs(sigma)
+                };
+            }
+            if depth > hs {
+                for k in (0..kz - 1).rev() {
+                    if self.z_array[k] > -depth {
+                        z_coordinates[[k, i]] = self.z_array[k];
+                    } else {
+                        z_coordinates[[k, i]] = -depth;
+                        break;
+                    }
+                }
+            }
+        }
+        z_coordinates
+    }
+    fn cs(&self, sigma: f64) -> f64 {
+        (1. - self.theta_b) * sinh(self.theta_f * sigma) / sinh(self.theta_f)
+            + self.theta_b * (tanh(self.theta_f * (sigma + 0.5)) - tanh(self.theta_f * 0.5))
+                / (2. * tanh(self.theta_f * 0.5))
+    }
     fn compute_zcor(&self, bottom: &f64) -> Array1<f64> {
         let mut zcor = Array1::from_elem(self.sigma.len(), NAN);
         let hc = -self.hc;
@@ -67,6 +115,21 @@ impl SZ {
     }
 }
 
+impl VerticalGrid for SZ {
+    fn ivcor(&self) -> usize {
+        self.ivcor()
+    }
+    fn nvrt(&self) -> usize {
+        self.nvrt()
+    }
+    fn z_coordinates(&self, hgrid: &Hgrid) -> Array2<f64> {
+        self.z_coordinates(hgrid)
+    }
+    fn write_to_file(&self, filename: &Path) -> std::io::Result<()> {
+        self.write_to_file(&filename.to_path_buf())
+    }
+}
+
 impl fmt::Display for SZ {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}\n", self.ivcor())?;
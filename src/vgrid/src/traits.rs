@@ -0,0 +1,23 @@
+use ndarray::Array2;
+use schismrs_hgrid::Hgrid;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub trait VerticalGrid {
+    fn ivcor(&self) -> usize;
+    fn nvrt(&self) -> usize;
+    fn z_coordinates(&self, hgrid: &Hgrid) -> Array2<f64>;
+    fn write_to_file(&self, filename: &Path) -> std::io::Result<()>;
+
+    // Number of wet levels at each node, e.g. as partitioning weights.
+    fn node_level_counts(&self, hgrid: &Hgrid) -> BTreeMap<u32, usize> {
+        let z_coordinates = self.z_coordinates(hgrid);
+        hgrid
+            .nodes()
+            .btree_map()
+            .keys()
+            .zip(z_coordinates.columns())
+            .map(|(&node_id, column)| (node_id, column.iter().filter(|z| !z.is_nan()).count()))
+            .collect()
+    }
+}
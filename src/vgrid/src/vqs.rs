@@ -1,3 +1,4 @@
+use crate::traits::VerticalGrid;
 use crate::transforms::quadratic::QuadraticTransformBuilderError;
 use crate::transforms::s::STransformBuilderError;
 use crate::transforms::traits::{Transform, TransformPlotterError};
@@ -16,7 +17,7 @@ use std::f64::NAN;
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use thiserror::Error;
 
@@ -24,7 +25,7 @@ pub struct VQS {
     sigma_vqs: Array2<f64>,
     // _depths: Array1<f64>,
     // _etal: f64,
-    _znd: Array2<f64>,
+    znd: Array2<f64>,
     // z_mas: Array2<f64>,
     transform: Rc<dyn Transform>,
 }
@@ -48,6 +49,12 @@ impl VQS {
         &self.sigma_vqs
     }
 
+    pub fn z_coordinates(&self) -> Array2<f64> {
+        let mut z_coordinates = self.znd.clone();
+        z_coordinates.invert_axis(Axis(0));
+        z_coordinates
+    }
+
     pub fn transform(&self) -> Rc<dyn Transform> {
         self.transform.clone()
     }
@@ -183,7 +190,7 @@ impl<'a> VQSBuilder<'a> {
             sigma_vqs,
             // _depths: depths,
             // _etal: *etal,
-            _znd: znd,
+            znd,
             // z_mas: z_mas.clone(),
             transform,
         })
@@ -648,3 +655,18 @@ pub enum VQSAutoBuilderError {
     #[error(transparent)]
     QuadraticTransformBuilderError(#[from] QuadraticTransformBuilderError),
 }
+
+impl VerticalGrid for VQS {
+    fn ivcor(&self) -> usize {
+        self.ivcor()
+    }
+    fn nvrt(&self) -> usize {
+        self.nvrt()
+    }
+    fn z_coordinates(&self, _hgrid: &Hgrid) -> Array2<f64> {
+        self.z_coordinates()
+    }
+    fn write_to_file(&self, filename: &Path) -> std::io::Result<()> {
+        self.write_to_file(&filename.to_path_buf())
+    }
+}